@@ -0,0 +1,12 @@
+use async_trait::async_trait;
+use http::{Request, Response};
+use std::error::Error;
+use std::fmt::Debug;
+
+// the transport used by an acme server, bodies are small so they are buffered
+#[async_trait]
+pub trait HttpClient: Debug + Send + Sync + 'static {
+    type Error: Error + Send + Sync + 'static;
+
+    async fn request(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Self::Error>;
+}
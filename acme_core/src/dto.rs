@@ -1,83 +1,20 @@
 use http::uri::InvalidUri;
 use serde::de::{self, Error as DeError, Visitor};
-use serde::ser::SerializeStruct;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
-use std::marker::PhantomData;
+use time::format_description::well_known::Rfc3339;
 use time::serde::rfc3339::option as rfc3339_option;
 use time::OffsetDateTime;
 
+const ERROR_PREFIX: &str = "urn:ietf:params:acme:error:";
+// name of the unit struct PostAsGet serializes as, the request recognizes the payload by it
+pub(crate) const POST_AS_GET: &str = "PostAsGet";
+
 const fn default_false() -> bool {
     false
 }
 
-pub struct SignedRequest<P> {
-    pub protected: String,
-    pub payload: Payload<P>,
-    pub signature: String,
-}
-
-// Implement serialize manually otherwise its only implemented if P is Serialize
-impl<P> Serialize for SignedRequest<P> {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut serializer = serializer.serialize_struct("SignedRequest", 3)?;
-
-        serializer.serialize_field("protected", &self.protected)?;
-        serializer.serialize_field("payload", &self.payload)?;
-        serializer.serialize_field("signature", &self.signature)?;
-
-        serializer.end()
-    }
-}
-
-pub enum Payload<P> {
-    Post {
-        inner: String,
-        phantom: PhantomData<P>,
-    },
-    Get,
-}
-
-impl<P> Payload<P> {
-    pub fn len(&self) -> usize {
-        match self {
-            Payload::Post { inner, .. } => inner.len(),
-            Payload::Get => 0,
-        }
-    }
-}
-
-impl<P> From<String> for Payload<P> {
-    fn from(inner: String) -> Self {
-        Self::Post {
-            inner,
-            phantom: PhantomData,
-        }
-    }
-}
-
-impl<P> Default for Payload<P> {
-    fn default() -> Self {
-        Payload::Get
-    }
-}
-
-impl<P> Serialize for Payload<P> {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        match self {
-            Payload::Post { inner, .. } => inner.serialize(serializer),
-            Payload::Get => "".serialize(serializer),
-        }
-    }
-}
-
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Uri(http::Uri);
 
@@ -105,6 +42,12 @@ impl TryFrom<&str> for Uri {
     }
 }
 
+impl fmt::Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl From<&Uri> for http::Uri {
     fn from(input: &Uri) -> Self {
         input.0.clone()
@@ -169,6 +112,9 @@ pub struct ApiDirectory {
     pub new_authz: Option<Uri>,
     pub revoke_cert: Uri,
     pub key_change: Uri,
+    // acme renewal information, only offered by some CAs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renewal_info: Option<Uri>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub meta: Option<ApiMeta>,
 }
@@ -192,31 +138,168 @@ pub enum ApiAccountStatus {
     Revoked,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiAccount<E = NoExternalAccountBinding> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status: Option<ApiAccountStatus>,
-    pub contact: Vec<String>,
+    #[serde(default)]
+    pub contact: Vec<Contact>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub terms_of_service_agreed: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub external_account_binding: Option<E>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub orders: Option<String>,
+    // only sent on registration, returns the account of the key instead of creating one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only_return_existing: Option<bool>,
+}
+
+// derived Default would require E: Default which NoExternalAccountBinding can't implement
+impl<E> Default for ApiAccount<E> {
+    fn default() -> Self {
+        Self {
+            status: None,
+            contact: Vec::new(),
+            terms_of_service_agreed: None,
+            external_account_binding: None,
+            orders: None,
+            only_return_existing: None,
+        }
+    }
 }
 
 impl ApiAccount<()> {
-    pub fn new(mail: String, tos: bool) -> Self {
+    pub fn new(mail: Email, tos: bool) -> Self {
         Self {
-            contact: vec![mail],
+            contact: vec![Contact::Mailto(mail)],
             terms_of_service_agreed: Some(tos),
             ..Default::default()
         }
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidContact(pub String);
+
+impl fmt::Display for InvalidContact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid contact {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidContact {}
+
+// a single address, RFC 8555 section 7.3 forbids hfields and multiple addresses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email(String);
+
+impl Email {
+    // the domain is lowercased, the local part is kept as is
+    pub fn new(mail: &str) -> Result<Self, InvalidContact> {
+        let mail = mail.trim();
+        let invalid = || InvalidContact(mail.to_owned());
+
+        let (local, domain) = mail.rsplit_once('@').ok_or_else(invalid)?;
+        let forbidden = |c: char| c.is_whitespace() || matches!(c, '@' | ',' | '?' | '<' | '>');
+        if local.is_empty() || local.contains(forbidden) || local.starts_with("mailto:") {
+            return Err(invalid());
+        }
+        if domain.is_empty() || domain.contains(forbidden) || domain.starts_with('.') {
+            return Err(invalid());
+        }
+
+        Ok(Self(format!("{}@{}", local, domain.to_ascii_lowercase())))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+// account contact, serialized as uri
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Contact {
+    Mailto(Email),
+    // digits with an optional leading +, separators are removed
+    Tel(String),
+    // any other uri, and contacts of the CA which don't validate
+    Other(String),
+}
+
+impl Contact {
+    pub fn mailto(mail: &str) -> Result<Self, InvalidContact> {
+        Email::new(mail).map(Contact::Mailto)
+    }
+
+    pub fn tel(number: &str) -> Result<Self, InvalidContact> {
+        let number: String = number
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+            .collect();
+        let digits = number.strip_prefix('+').unwrap_or(&number);
+        match !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+            true => Ok(Contact::Tel(number)),
+            false => Err(InvalidContact(number)),
+        }
+    }
+
+    // mailto: and tel: are validated, everything else has to be a valid uri
+    pub fn parse(contact: &str) -> Result<Self, InvalidContact> {
+        let contact = contact.trim();
+        if let Some(mail) = strip_scheme(contact, "mailto:") {
+            return Contact::mailto(mail);
+        }
+        if let Some(number) = strip_scheme(contact, "tel:") {
+            return Contact::tel(number);
+        }
+
+        match Uri::try_from(contact) {
+            Ok(_) => Ok(Contact::Other(contact.to_owned())),
+            Err(_) => Err(InvalidContact(contact.to_owned())),
+        }
+    }
+}
+
+fn strip_scheme<'a>(contact: &'a str, scheme: &str) -> Option<&'a str> {
+    match contact.get(..scheme.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(scheme) => Some(&contact[scheme.len()..]),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Contact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Contact::Mailto(mail) => write!(f, "mailto:{}", mail.as_str()),
+            Contact::Tel(number) => write!(f, "tel:{}", number),
+            Contact::Other(contact) => f.write_str(contact),
+        }
+    }
+}
+
+impl Serialize for Contact {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+// lenient as the account of the CA shouldn't fail on a contact this crate wouldn't send
+impl<'de> Deserialize<'de> for Contact {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let contact = String::deserialize(deserializer)?;
+        Ok(Contact::parse(&contact).unwrap_or(Contact::Other(contact)))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum ApiOrderStatus {
     Pending,
@@ -226,6 +309,38 @@ pub enum ApiOrderStatus {
     Invalid,
 }
 
+impl ApiOrderStatus {
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Pending)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        matches!(self, Self::Ready)
+    }
+
+    pub fn is_processing(&self) -> bool {
+        matches!(self, Self::Processing)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid)
+    }
+
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, Self::Invalid)
+    }
+
+    // the CA is still working on the order, either validating or issuing
+    pub fn is_in_progress(&self) -> bool {
+        matches!(self, Self::Pending | Self::Processing)
+    }
+
+    // the order won't change anymore
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Valid | Self::Invalid)
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum ApiIdentifierType {
@@ -244,10 +359,18 @@ pub struct ApiIdentifier {
 #[serde(rename_all = "camelCase")]
 pub struct ApiNewOrder {
     pub identifiers: Vec<ApiIdentifier>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub not_before: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub not_after: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rfc3339_option"
+    )]
+    pub not_before: Option<OffsetDateTime>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rfc3339_option"
+    )]
+    pub not_after: Option<OffsetDateTime>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -256,6 +379,55 @@ pub struct ApiOrderFinalization {
     pub csr: String,
 }
 
+// reason codes of RFC 5280, 7 is unused
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ApiRevocationReason {
+    Unspecified = 0,
+    KeyCompromise = 1,
+    CaCompromise = 2,
+    AffiliationChanged = 3,
+    Superseded = 4,
+    CessationOfOperation = 5,
+    CertificateHold = 6,
+    RemoveFromCrl = 8,
+    PrivilegeWithdrawn = 9,
+    AaCompromise = 10,
+}
+
+impl Serialize for ApiRevocationReason {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiRenewalInfo {
+    pub suggested_window: ApiSuggestedWindow,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation_url: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ApiSuggestedWindow {
+    #[serde(with = "time::serde::rfc3339")]
+    pub start: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub end: OffsetDateTime,
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiRevocation {
+    // base64url encoded der of the certificate
+    pub certificate: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<ApiRevocationReason>,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiOrder {
@@ -263,10 +435,18 @@ pub struct ApiOrder {
     #[serde(skip_serializing_if = "Option::is_none", with = "rfc3339_option")]
     pub expires: Option<OffsetDateTime>,
     pub identifiers: Vec<ApiIdentifier>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub not_before: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub not_after: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rfc3339_option"
+    )]
+    pub not_before: Option<OffsetDateTime>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rfc3339_option"
+    )]
+    pub not_after: Option<OffsetDateTime>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<ApiError>,
     pub authorizations: Vec<Uri>,
@@ -275,14 +455,35 @@ pub struct ApiOrder {
     pub certificate: Option<Uri>,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum ApiAuthorizationStatus {
     Pending,
-    Ready,
-    Processing,
     Valid,
     Invalid,
+    Deactivated,
+    Expired,
+    Revoked,
+}
+
+impl ApiAuthorizationStatus {
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Pending)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid)
+    }
+
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, Self::Invalid)
+    }
+
+    // a valid authorization can still be deactivated, expire or be revoked,
+    // but the CA won't change it on its own while validating
+    pub fn is_terminal(&self) -> bool {
+        !self.is_pending()
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -290,8 +491,13 @@ pub enum ApiAuthorizationStatus {
 pub struct ApiAuthorization {
     pub identifier: ApiIdentifier,
     pub status: ApiAuthorizationStatus,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub expires: Option<String>,
+    // only required for valid authorizations
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rfc3339_option"
+    )]
+    pub expires: Option<OffsetDateTime>,
     pub challenges: Vec<ApiChallenge>,
     #[serde(default = "default_false")]
     pub wildcard: bool,
@@ -332,7 +538,7 @@ impl<'de> Deserialize<'de> for ApiChallengeType {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum ApiChallengeStatus {
     Pending,
@@ -341,6 +547,28 @@ pub enum ApiChallengeStatus {
     Invalid,
 }
 
+impl ApiChallengeStatus {
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Pending)
+    }
+
+    pub fn is_processing(&self) -> bool {
+        matches!(self, Self::Processing)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid)
+    }
+
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, Self::Invalid)
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Valid | Self::Invalid)
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiChallenge {
@@ -349,9 +577,13 @@ pub struct ApiChallenge {
     pub url: String,
     pub status: ApiChallengeStatus,
     pub token: String,
-    // todo: turn into rfc3339
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub validated: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "rfc3339_option::serialize",
+        deserialize_with = "lenient_rfc3339"
+    )]
+    pub validated: Option<OffsetDateTime>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<ApiError>,
 }
@@ -362,9 +594,34 @@ pub struct ApiError {
     #[serde(rename = "type")]
     pub type_val: ApiErrorType,
     pub detail: String,
+    #[serde(default)]
     pub subproblems: Vec<ApiSubproblem>,
 }
 
+// some CAs send timestamps without offset or with a space instead of the T,
+// unparsable timestamps are dropped instead of failing the whole challenge
+fn lenient_rfc3339<'de, D>(deserializer: D) -> Result<Option<OffsetDateTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let timestamp = match Option::<String>::deserialize(deserializer)? {
+        Some(timestamp) => timestamp,
+        None => return Ok(None),
+    };
+
+    let timestamp = timestamp.trim().replacen(' ', "T", 1);
+    let parse = |timestamp: &str| OffsetDateTime::parse(timestamp, &Rfc3339).ok();
+    Ok(parse(&timestamp).or_else(|| parse(&format!("{}Z", timestamp))))
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.type_val.as_ref(), self.detail)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiSubproblem {
@@ -374,7 +631,7 @@ pub struct ApiSubproblem {
     pub identifier: ApiIdentifier,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ApiErrorType {
     AccountDoesNotExist,
     AlreadyRevoked,
@@ -440,7 +697,10 @@ impl Serialize for ApiErrorType {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.as_ref())
+        match self {
+            Self::Other(inner) => serializer.serialize_str(inner),
+            this => serializer.serialize_str(&format!("{}{}", ERROR_PREFIX, this.as_ref())),
+        }
     }
 }
 
@@ -449,9 +709,10 @@ impl<'de> Deserialize<'de> for ApiErrorType {
     where
         D: Deserializer<'de>,
     {
-        let this = <&'de str>::deserialize(deserializer)?;
+        let full = <&'de str>::deserialize(deserializer)?;
 
-        let this = match this {
+        // errors defined by RFC 8555 are namespaced, keep everything else as is
+        let this = match full.strip_prefix(ERROR_PREFIX).unwrap_or(full) {
             "accountDoesNotExist" => Self::AccountDoesNotExist,
             "alreadyRevoked" => Self::AlreadyRevoked,
             "badCSR" => Self::BadCSR,
@@ -476,20 +737,25 @@ impl<'de> Deserialize<'de> for ApiErrorType {
             "unsupportedContact" => Self::UnsupportedContact,
             "unsupportedIdentifier" => Self::UnsupportedIdentifier,
             "userActionRequired" => Self::UserActionRequired,
-            this => Self::Other(this.to_string()),
+            _ => Self::Other(full.to_string()),
         };
 
         Ok(this)
     }
 }
 
+// payload of the inner jws of a key rollover, RFC 8555 section 7.3.5.
+// K is the jwk of the old key, the new key is in the protected header of the inner jws
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiKeyChange<K> {
-    account: Uri,
-    old_key: K,
+    pub account: Uri,
+    pub old_key: K,
 }
 
+// RFC 8555 section 6.3, the payload member is the empty string instead of encoded json.
+// RequestImpl recognizes the unit struct and sends the empty payload
+#[derive(Clone, Copy, Debug)]
 pub struct PostAsGet;
 
 impl serde::Serialize for PostAsGet {
@@ -497,10 +763,15 @@ impl serde::Serialize for PostAsGet {
     where
         S: Serializer,
     {
-        "".serialize(serializer)
+        serializer.serialize_unit_struct(POST_AS_GET)
     }
 }
 
+// responding to a challenge, RFC 8555 section 7.5.1. unlike post-as-get the payload is {}
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ApiChallengeResponse {}
+
+#[derive(Clone, Debug)]
 pub enum NoExternalAccountBinding {}
 
 impl serde::Serialize for NoExternalAccountBinding {
@@ -523,7 +794,7 @@ impl<'de> Deserialize<'de> for NoExternalAccountBinding {
 
 #[cfg(test)]
 mod tests {
-    use serde_test::{assert_tokens, Token};
+    use serde_test::{assert_de_tokens, assert_ser_tokens, assert_tokens, Token};
 
     use super::*;
 
@@ -534,6 +805,152 @@ mod tests {
         "https://google.com/test?hihi=was",
     ];
 
+    #[test]
+    fn status_helpers() {
+        assert!(ApiOrderStatus::Processing.is_in_progress());
+        assert!(!ApiOrderStatus::Ready.is_in_progress());
+        assert!(!ApiOrderStatus::Ready.is_terminal());
+        assert!(ApiOrderStatus::Invalid.is_terminal());
+
+        assert!(ApiAuthorizationStatus::Valid.is_terminal());
+        assert!(!ApiAuthorizationStatus::Pending.is_terminal());
+        assert!(ApiAuthorizationStatus::Expired.is_terminal());
+        assert!(ApiChallengeStatus::Invalid.is_terminal());
+        assert!(!ApiChallengeStatus::Processing.is_terminal());
+
+        let status: ApiOrderStatus = serde_json::from_str(r#""ready""#).unwrap();
+        assert_eq!(status, ApiOrderStatus::Ready);
+    }
+
+    #[test]
+    fn authorization_status() {
+        let status: Vec<ApiAuthorizationStatus> = serde_json::from_str(
+            r#"["pending", "valid", "invalid", "deactivated", "expired", "revoked"]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            status,
+            [
+                ApiAuthorizationStatus::Pending,
+                ApiAuthorizationStatus::Valid,
+                ApiAuthorizationStatus::Invalid,
+                ApiAuthorizationStatus::Deactivated,
+                ApiAuthorizationStatus::Expired,
+                ApiAuthorizationStatus::Revoked,
+            ]
+        );
+        assert!(serde_json::from_str::<ApiAuthorizationStatus>(r#""ready""#).is_err());
+    }
+
+    #[test]
+    fn contacts() {
+        assert_eq!(
+            Contact::parse(" MAILTO:Admin@Example.COM ")
+                .unwrap()
+                .to_string(),
+            "mailto:Admin@example.com"
+        );
+        assert_eq!(
+            Contact::parse("tel:+41 79 123-45-67").unwrap().to_string(),
+            "tel:+41791234567"
+        );
+        assert!(Contact::parse("mailto:admin").is_err());
+        assert!(Contact::parse("mailto:a@example.com,b@example.com").is_err());
+        assert!(Contact::parse("mailto:admin@example.com?subject=acme").is_err());
+        assert!(Contact::parse("tel:call me").is_err());
+
+        let contact: Vec<Contact> =
+            serde_json::from_str(r#"["mailto:admin@example.com"]"#).unwrap();
+        assert_eq!(contact, [Contact::mailto("admin@example.com").unwrap()]);
+        assert_eq!(
+            serde_json::to_string(&contact).unwrap(),
+            r#"["mailto:admin@example.com"]"#
+        );
+
+        let json = r#"["https://example.com/contact","mailto:admin"]"#;
+        let contact: Vec<Contact> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            contact,
+            [
+                Contact::Other("https://example.com/contact".to_owned()),
+                Contact::Other("mailto:admin".to_owned()),
+            ]
+        );
+        assert_eq!(serde_json::to_string(&contact).unwrap(), json);
+    }
+
+    #[test]
+    fn key_change_payload() {
+        let key_change = ApiKeyChange {
+            account: Uri::try_from("https://example.com/acme/acct/evOfKhNU60wg").unwrap(),
+            old_key: serde_json::json!({"kty": "EC", "crv": "P-384"}),
+        };
+        assert_eq!(
+            serde_json::to_value(&key_change).unwrap(),
+            serde_json::json!({
+                "account": "https://example.com/acme/acct/evOfKhNU60wg",
+                "oldKey": {"kty": "EC", "crv": "P-384"},
+            })
+        );
+    }
+
+    #[test]
+    fn new_order_validity() {
+        let new_order = ApiNewOrder {
+            identifiers: Vec::new(),
+            not_before: Some(time::macros::datetime!(2021-06-01 00:00:00 UTC)),
+            not_after: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&new_order).unwrap(),
+            r#"{"identifiers":[],"notBefore":"2021-06-01T00:00:00Z"}"#
+        );
+    }
+
+    #[test]
+    fn authorization_expires() {
+        let authorization = |expires: &str| {
+            let json = format!(
+                r#"{{"identifier":{{"type":"dns","value":"example.com"}},"status":"pending","challenges":[]{}}}"#,
+                expires
+            );
+            serde_json::from_str::<ApiAuthorization>(&json)
+                .unwrap()
+                .expires
+        };
+
+        assert_eq!(
+            authorization(r#","expires":"2021-06-08T12:30:00Z""#),
+            Some(time::macros::datetime!(2021-06-08 12:30:00 UTC))
+        );
+        assert_eq!(authorization(""), None);
+    }
+
+    #[test]
+    fn challenge_validated() {
+        let challenge = |validated: &str| {
+            let json = format!(
+                r#"{{"type":"http-01","url":"https://ca/chall/1","status":"valid","token":"t"{}}}"#,
+                validated
+            );
+            serde_json::from_str::<ApiChallenge>(&json)
+                .unwrap()
+                .validated
+        };
+
+        let expected = time::macros::datetime!(2021-06-01 12:30:00 UTC);
+        assert_eq!(
+            challenge(r#","validated":"2021-06-01T12:30:00Z""#),
+            Some(expected)
+        );
+        assert_eq!(
+            challenge(r#","validated":"2021-06-01 12:30:00""#),
+            Some(expected)
+        );
+        assert_eq!(challenge(r#","validated":"yesterday""#), None);
+        assert_eq!(challenge(""), None);
+    }
+
     #[test]
     fn uri_try_from_str() {
         for uri in URIS {
@@ -563,6 +980,24 @@ mod tests {
         assert_eq!(uri.0, http_uri);
     }
 
+    #[test]
+    fn serde_api_error_type() {
+        assert_de_tokens(
+            &ApiErrorType::ServerInternal,
+            &[Token::BorrowedStr(
+                "urn:ietf:params:acme:error:serverInternal",
+            )],
+        );
+        assert_ser_tokens(
+            &ApiErrorType::ServerInternal,
+            &[Token::Str("urn:ietf:params:acme:error:serverInternal")],
+        );
+
+        let other = ApiErrorType::Other("urn:example:error:custom".to_string());
+        assert_de_tokens(&other, &[Token::BorrowedStr("urn:example:error:custom")]);
+        assert_ser_tokens(&other, &[Token::Str("urn:example:error:custom")]);
+    }
+
     #[test]
     fn serde_api_challenge_type() {
         assert_tokens(&ApiChallengeType::DNS, &[Token::Str("dns-01")]);
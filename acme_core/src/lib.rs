@@ -1,5 +1,7 @@
+pub mod client;
 pub mod dto;
 pub mod request;
+pub mod response;
 pub mod server;
 
 mod sealed {
@@ -1,10 +1,10 @@
-use super::{DynProtected, KeyType, Kid, NoNonce, NonceType, Request, RequestImpl, Signer};
+use super::{DynProtected, KeyType, Kid, Nonce, NonceType, Request, RequestImpl, Signer};
 use serde::{Serialize, Serializer};
 use std::any::Any;
 use std::marker::PhantomData;
 use std::ops::Deref;
 
-pub struct DynRequest<'a, B, K: KeyType = Kid, N: NonceType = NoNonce> {
+pub struct DynRequest<'a, B, K: KeyType = Kid, N: NonceType = Nonce> {
     pub(crate) inner: RequestImpl<K, N, &'a dyn DynProtected, &'a B, &'a dyn Signer>,
     pub(crate) protected_any: &'a (dyn Any + Send + Sync),
     pub(crate) signer_any: &'a (dyn Any + Send + Sync),
@@ -1,24 +1,28 @@
+use base64::write::EncoderStringWriter;
 use base64::URL_SAFE_NO_PAD;
 use ref_cast::RefCast;
-use serde::ser::SerializeStruct;
+use serde::ser::{Error, SerializeStruct};
 use serde::Serializer;
 use std::any::Any;
 use std::marker::PhantomData;
 use std::ops::Deref;
 
-mod dyanmic;
+mod dynamic;
+mod post_as_get;
 mod protected;
 
-pub use dyanmic::*;
+pub use dynamic::*;
 pub use protected::*;
 
+use post_as_get::is_post_as_get;
+
 trait Serialize: serde::Serialize + Send + Sync {}
 
 impl<T: serde::Serialize + Send + Sync> Serialize for T {}
 
 // todo: add sealed back
 // todo: change location of nonce
-pub trait Request<B, K: KeyType = Kid, N: NonceType = NoNonce>:
+pub trait Request<B, K: KeyType = Kid, N: NonceType = Nonce>:
     serde::Serialize + Send + Sync
 {
     // todo: make private
@@ -27,6 +31,30 @@ pub trait Request<B, K: KeyType = Kid, N: NonceType = NoNonce>:
     fn protected_as_any(&self) -> &(dyn Any + Send + Sync);
 
     fn signer_as_any(&self) -> &(dyn Any + Send + Sync);
+
+    // flattened json serialization as sent to the CA, e.g. for curl or test fixtures
+    fn to_jws_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    fn to_jws_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    // compact serialization, the payload is empty for post-as-get
+    fn to_jws_compact(&self) -> serde_json::Result<String>
+    where
+        B: serde::Serialize + Send + Sync,
+    {
+        let request = self.as_dyn_request();
+        let (protected, payload) = request.inner.signing_input()?;
+        let signature = request
+            .inner
+            .signer
+            .sign(protected.clone(), payload.clone())
+            .map_err(serde_json::Error::custom)?;
+        Ok(format!("{}.{}.{}", protected, payload, signature))
+    }
 }
 
 // maybe does not need to be public
@@ -37,16 +65,10 @@ pub struct RequestImpl<K, N, P, B, S> {
     pub(crate) signer: S,
 }
 
-impl<
-        'a,
-        K: KeyType,
-        N: NonceType,
-        P: Protected<K, N>,
-        B: serde::Serialize + Send + Sync,
-        S: Signer,
-    > RequestImpl<K, N, &'a P, &'a B, &'a S>
+impl<K: KeyType, N: NonceType, P: Protected<K, N>, B: Serialize, S: Signer>
+    RequestImpl<K, N, P, B, S>
 {
-    pub fn new(protected: &'a P, payload: &'a B, signer: &'a S) -> Self {
+    pub fn new(protected: P, payload: B, signer: S) -> Self {
         Self {
             phantom: PhantomData,
             protected,
@@ -54,6 +76,25 @@ impl<
             signer,
         }
     }
+
+    // base64url protected header and payload, the signature is over both joined by a dot
+    pub fn signing_input(&self) -> serde_json::Result<(String, String)> {
+        let protected = base64_json(&ProtectedWrapper::new(&self.protected))?;
+        let payload = base64_payload(&self.payload)?;
+        Ok((protected, payload))
+    }
+}
+
+// retried requests are sent again with a fresh nonce
+impl<K, N, P: Clone, B: Clone, S: Clone> Clone for RequestImpl<K, N, P, B, S> {
+    fn clone(&self) -> Self {
+        Self {
+            phantom: PhantomData,
+            protected: self.protected.clone(),
+            payload: self.payload.clone(),
+            signer: self.signer.clone(),
+        }
+    }
 }
 
 impl<K: KeyType, N: NonceType, P: Protected<K, N> + AsAny, B: Serialize, S: Signer + AsAny>
@@ -100,32 +141,45 @@ impl<K: KeyType, N: NonceType, P: Protected<K, N>, B: Serialize, S: Signer> serd
     {
         let mut request_impl = serializer.serialize_struct("Request", 3)?;
 
-        let protected = ProtectedWrapper::new(&self.protected);
-        let protected = base64_and_serialize(&protected);
+        // failures of the inner serializers and the signer are returned instead of panicking
+        let (protected, payload) = self.signing_input().map_err(Ser::Error::custom)?;
         request_impl.serialize_field("protected", &protected)?;
-
-        let payload = base64_and_serialize(&self.payload);
         request_impl.serialize_field("payload", &payload)?;
 
-        let signature = self.signer.sign(protected, payload);
+        let signature = self
+            .signer
+            .sign(protected, payload)
+            .map_err(Ser::Error::custom)?;
         request_impl.serialize_field("signature", &signature)?;
 
         request_impl.end()
     }
 }
 
-fn base64_and_serialize<T: Serialize + ?Sized>(input: &T) -> String {
-    // todo: remove unwrap
-    let json = serde_json::to_vec(input).unwrap();
-    base64::encode_config(json, URL_SAFE_NO_PAD)
+// PostAsGet is sent as the empty string, never as base64 of "" or null
+fn base64_payload<T: serde::Serialize + ?Sized>(payload: &T) -> serde_json::Result<String> {
+    match is_post_as_get(payload) {
+        true => Ok(String::new()),
+        false => base64_json(payload),
+    }
+}
+
+// streams the json straight into the base64 encoder without buffering it first
+pub fn base64_json<T: serde::Serialize + ?Sized>(input: &T) -> serde_json::Result<String> {
+    let mut writer = EncoderStringWriter::new(URL_SAFE_NO_PAD);
+    serde_json::to_writer(&mut writer, input)?;
+    Ok(writer.into_inner())
 }
 
+// returns the base64url encoded signature over the signing input
 pub trait Signer: Send + Sync {
-    fn sign(&self, protected: String, payload: String) -> String;
+    fn sign(&self, protected: String, payload: String) -> Result<String, SignError>;
 }
 
+pub type SignError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
 impl<T: Signer + ?Sized> Signer for &T {
-    fn sign(&self, protected: String, payload: String) -> String {
+    fn sign(&self, protected: String, payload: String) -> Result<String, SignError> {
         self.deref().sign(protected, payload)
     }
 }
@@ -142,3 +196,125 @@ where
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::dto::{PostAsGet, Uri};
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+
+    struct TestProtected {
+        kid: Kid,
+        url: Uri,
+    }
+
+    fn protected(url: &str) -> TestProtected {
+        TestProtected {
+            kid: Kid::new("https://ca/acct/1".to_owned()),
+            url: Uri::try_from(url).unwrap(),
+        }
+    }
+
+    impl Protected<Kid, NoNonce> for TestProtected {
+        fn alg(&self) -> &str {
+            "ES384"
+        }
+
+        fn key(&self) -> &Kid {
+            &self.kid
+        }
+
+        fn nonce(&self) -> &NoNonce {
+            &NoNonce
+        }
+
+        fn url(&self) -> &Uri {
+            &self.url
+        }
+    }
+
+    struct TestSigner;
+
+    impl Signer for TestSigner {
+        fn sign(&self, _: String, _: String) -> Result<String, SignError> {
+            Ok("signature".to_owned())
+        }
+    }
+
+    #[test]
+    fn failing_payload_returns_error() {
+        // json maps need string keys
+        let payload: HashMap<(u8, u8), u8> = vec![((1, 2), 3)].into_iter().collect();
+
+        let request: RequestImpl<Kid, NoNonce, _, _, _> =
+            RequestImpl::new(protected("https://ca/new-order"), &payload, &TestSigner);
+        assert!(serde_json::to_string(&request).is_err());
+
+        let payload: HashMap<String, u8> = HashMap::new();
+        let request: RequestImpl<Kid, NoNonce, _, _, _> =
+            RequestImpl::new(protected("https://ca/new-order"), &payload, &TestSigner);
+        assert!(serde_json::to_string(&request).is_ok());
+    }
+
+    #[test]
+    fn post_as_get_has_empty_payload() {
+        let request: RequestImpl<Kid, NoNonce, _, _, _> =
+            RequestImpl::new(protected("https://ca/order/1"), &PostAsGet, &TestSigner);
+        let request = serde_json::to_value(&request).unwrap();
+        assert_eq!(request["payload"], "");
+
+        let payload = serde_json::json!({});
+        let request: RequestImpl<Kid, NoNonce, _, _, _> =
+            RequestImpl::new(protected("https://ca/order/1"), &payload, &TestSigner);
+        let request = serde_json::to_value(&request).unwrap();
+        assert_eq!(request["payload"], "e30");
+
+        // only the type marks post-as-get, other null payloads are encoded
+        let request: RequestImpl<Kid, NoNonce, _, _, _> =
+            RequestImpl::new(protected("https://ca/order/1"), &None::<u8>, &TestSigner);
+        let request = serde_json::to_value(&request).unwrap();
+        assert_eq!(request["payload"], "bnVsbA");
+    }
+
+    #[test]
+    fn jws_serialization() {
+        let request: RequestImpl<Kid, NoNonce, _, _, _> =
+            RequestImpl::new(protected("https://ca/order/1"), &PostAsGet, &TestSigner);
+        let (header, payload) = request.signing_input().unwrap();
+        assert_eq!(payload, "");
+        assert_eq!(
+            request.to_jws_json().unwrap(),
+            format!(
+                r#"{{"protected":"{}","payload":"","signature":"signature"}}"#,
+                header
+            )
+        );
+        assert_eq!(
+            request.to_jws_compact().unwrap(),
+            format!("{}..signature", header)
+        );
+
+        let payload = serde_json::json!({});
+        let request: RequestImpl<Kid, NoNonce, _, _, _> =
+            RequestImpl::new(protected("https://ca/order/1"), &payload, &TestSigner);
+        assert_eq!(
+            request.to_jws_compact().unwrap(),
+            format!("{}.e30.signature", header)
+        );
+        // servers only see the type erased request
+        assert_eq!(
+            request.as_dyn_request().to_jws_compact().unwrap(),
+            format!("{}.e30.signature", header)
+        );
+    }
+
+    #[test]
+    fn base64_json_matches_buffered() {
+        let input = serde_json::json!({ "contact": ["mailto:admin@example.com"] });
+        let buffered = base64::encode_config(serde_json::to_vec(&input).unwrap(), URL_SAFE_NO_PAD);
+
+        assert_eq!(base64_json(&input).unwrap(), buffered);
+    }
+}
@@ -0,0 +1,185 @@
+use serde::ser::{self, Impossible, Serialize, Serializer};
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::dto::POST_AS_GET;
+
+// any other payload fails on the first call into the probe, so it's never serialized twice
+pub(super) fn is_post_as_get<T: Serialize + ?Sized>(payload: &T) -> bool {
+    payload.serialize(Probe).is_ok()
+}
+
+struct Probe;
+
+#[derive(Debug)]
+struct NotPostAsGet;
+
+impl Display for NotPostAsGet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("payload is not post-as-get")
+    }
+}
+
+impl Error for NotPostAsGet {}
+
+impl ser::Error for NotPostAsGet {
+    fn custom<T: Display>(_: T) -> Self {
+        NotPostAsGet
+    }
+}
+
+type Rejected = Impossible<(), NotPostAsGet>;
+
+impl Serializer for Probe {
+    type Ok = ();
+    type Error = NotPostAsGet;
+    type SerializeSeq = Rejected;
+    type SerializeTuple = Rejected;
+    type SerializeTupleStruct = Rejected;
+    type SerializeTupleVariant = Rejected;
+    type SerializeMap = Rejected;
+    type SerializeStruct = Rejected;
+    type SerializeStructVariant = Rejected;
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), NotPostAsGet> {
+        match name {
+            POST_AS_GET => Ok(()),
+            _ => Err(NotPostAsGet),
+        }
+    }
+
+    fn serialize_bool(self, _: bool) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_char(self, _: char) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_str(self, _: &str) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_none(self) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_unit(self) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Rejected, NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Rejected, NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Rejected, NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Rejected, NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Rejected, NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Rejected, NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Rejected, NotPostAsGet> {
+        Err(NotPostAsGet)
+    }
+}
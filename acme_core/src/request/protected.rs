@@ -13,56 +13,48 @@ pub trait Protected<K: KeyType, N: NonceType>: Send + Sync {
     fn url(&self) -> &Uri;
 }
 
-trait JwkKey: Send + Sync + 'static {
-    fn crv(&self) -> &str;
-    fn kty(&self) -> &str;
-    fn x(&self) -> &str;
-    fn y(&self) -> &str;
+// todo: add sealed back
+pub trait KeyType: Serialize + Send + Sync + 'static {}
+
+// public key of the account, only used to register an account and in the inner jws of a key
+// change, every other request references the account by its kid
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Jwk {
+    crv: String,
+    kty: String,
+    x: String,
+    y: String,
 }
 
-// todo: remove this
-impl JwkKey for () {
-    fn crv(&self) -> &str {
-        todo!()
+impl Jwk {
+    // x and y are base64url encoded
+    pub fn new<T: Into<String>>(crv: T, kty: T, x: T, y: T) -> Self {
+        Jwk {
+            crv: crv.into(),
+            kty: kty.into(),
+            x: x.into(),
+            y: y.into(),
+        }
     }
+}
 
-    fn kty(&self) -> &str {
-        todo!()
-    }
+impl KeyType for Jwk {}
 
-    fn x(&self) -> &str {
-        todo!()
-    }
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Kid(String);
 
-    fn y(&self) -> &str {
-        todo!()
+impl Kid {
+    pub fn new(kid: String) -> Self {
+        Kid(kid)
     }
 }
 
-// todo: add sealed back
-pub trait KeyType: Serialize + Send + Sync + 'static {}
-
-pub struct Jwk<T>(T);
-
-impl<T: JwkKey> KeyType for Jwk<T> {}
-
-impl<T: JwkKey> Serialize for Jwk<T> {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut jwk = serializer.serialize_struct("Jwk", 4)?;
-        jwk.serialize_field("crv", self.0.crv())?;
-        jwk.serialize_field("kty", self.0.kty())?;
-        jwk.serialize_field("x", self.0.x())?;
-        jwk.serialize_field("y", self.0.y())?;
-
-        jwk.end()
+impl From<&Uri> for Kid {
+    fn from(kid: &Uri) -> Self {
+        Kid(kid.to_string())
     }
 }
 
-pub struct Kid(String);
-
 impl KeyType for Kid {}
 
 impl serde::Serialize for Kid {
@@ -76,10 +68,12 @@ impl serde::Serialize for Kid {
 
 pub trait NonceType: Send + Sync + 'static {}
 
+#[derive(Clone, Copy, Debug)]
 pub struct NoNonce;
 
 impl NonceType for NoNonce {}
 
+#[derive(Clone, Debug)]
 pub struct Nonce(pub String);
 
 impl NonceType for Nonce {}
@@ -0,0 +1,241 @@
+use http::header::{LINK, LOCATION, RETRY_AFTER};
+use http::HeaderMap;
+use std::convert::{TryFrom, TryInto};
+use std::time::Duration;
+use time::format_description;
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+use crate::dto::Uri;
+
+const HTTP_DATE: &str =
+    "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT";
+
+#[derive(Clone, Debug)]
+pub struct AcmeResponse<T> {
+    pub body: T,
+    pub location: Option<Uri>,
+    pub retry_after: Option<RetryAfter>,
+    pub links: Vec<Link>,
+}
+
+impl<T> AcmeResponse<T> {
+    pub fn new(body: T) -> Self {
+        Self {
+            body,
+            location: None,
+            retry_after: None,
+            links: Vec::new(),
+        }
+    }
+
+    // invalid header values are ignored
+    pub fn from_headers(body: T, headers: &HeaderMap) -> Self {
+        let location = headers
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| Uri::try_from(location).ok());
+
+        let retry_after = headers
+            .get(RETRY_AFTER)
+            .and_then(|retry_after| retry_after.to_str().ok())
+            .and_then(RetryAfter::parse);
+
+        let links = headers
+            .get_all(LINK)
+            .iter()
+            .filter_map(|link| link.to_str().ok())
+            .flat_map(Link::parse)
+            .collect();
+
+        Self {
+            body,
+            location,
+            retry_after,
+            links,
+        }
+    }
+
+    pub fn into_body(self) -> T {
+        self.body
+    }
+
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> AcmeResponse<U> {
+        AcmeResponse {
+            body: f(self.body),
+            location: self.location,
+            retry_after: self.retry_after,
+            links: self.links,
+        }
+    }
+
+    pub fn links<'a>(&'a self, rel: &'a str) -> impl Iterator<Item = &'a Uri> + 'a {
+        self.links
+            .iter()
+            .filter(move |link| link.rel == rel)
+            .map(|link| &link.uri)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RetryAfter {
+    Delay(Duration),
+    Date(OffsetDateTime),
+}
+
+impl RetryAfter {
+    // either delay-seconds or an IMF-fixdate
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Ok(seconds) = value.parse() {
+            return Some(RetryAfter::Delay(Duration::from_secs(seconds)));
+        }
+
+        let format = format_description::parse(HTTP_DATE).ok()?;
+        let date = PrimitiveDateTime::parse(value, &format).ok()?;
+
+        Some(RetryAfter::Date(date.assume_utc()))
+    }
+
+    pub fn delay(&self, now: OffsetDateTime) -> Duration {
+        match self {
+            RetryAfter::Delay(delay) => *delay,
+            // a date in the past means no delay
+            RetryAfter::Date(date) => (*date - now).try_into().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link {
+    pub uri: Uri,
+    pub rel: String,
+}
+
+impl Link {
+    // parses a single Link header value which can contain multiple links
+    pub fn parse(value: &str) -> Vec<Link> {
+        let mut links = Vec::new();
+        let mut rest = value;
+
+        while let Some(start) = rest.find('<') {
+            let end = match rest[start..].find('>') {
+                Some(end) => start + end,
+                None => break,
+            };
+            let uri = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+
+            // parameters run until the next link starts
+            let params_end = rest.find('<').unwrap_or(rest.len());
+            let params = &rest[..params_end];
+            rest = &rest[params_end..];
+
+            let rel = params.split(';').find_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                match key.trim().eq_ignore_ascii_case("rel") {
+                    true => Some(value.trim().trim_end_matches(',').trim().trim_matches('"')),
+                    false => None,
+                }
+            });
+
+            if let (Ok(uri), Some(rel)) = (Uri::try_from(uri), rel) {
+                links.push(Link {
+                    uri,
+                    rel: rel.to_string(),
+                });
+            }
+        }
+
+        links
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn retry_after_seconds() {
+        assert_eq!(
+            RetryAfter::parse("120"),
+            Some(RetryAfter::Delay(Duration::from_secs(120)))
+        );
+    }
+
+    #[test]
+    fn retry_after_date() {
+        let retry_after = RetryAfter::parse("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(
+            retry_after,
+            RetryAfter::Date(datetime!(2015-10-21 07:28:00 UTC))
+        );
+
+        let now = datetime!(2015-10-21 07:27:00 UTC);
+        assert_eq!(retry_after.delay(now), Duration::from_secs(60));
+
+        let now = datetime!(2015-10-21 07:29:00 UTC);
+        assert_eq!(retry_after.delay(now), Duration::ZERO);
+    }
+
+    #[test]
+    fn retry_after_invalid() {
+        assert_eq!(RetryAfter::parse("soon"), None);
+    }
+
+    #[test]
+    fn link_header() {
+        let links = Link::parse(
+            r#"<https://example.com/acme/directory>;rel="index", <https://example.com/acme/cert/1/1>; rel="alternate""#,
+        );
+
+        assert_eq!(
+            links,
+            vec![
+                Link {
+                    uri: Uri::try_from("https://example.com/acme/directory").unwrap(),
+                    rel: "index".to_string(),
+                },
+                Link {
+                    uri: Uri::try_from("https://example.com/acme/cert/1/1").unwrap(),
+                    rel: "alternate".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn response_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            LOCATION,
+            HeaderValue::from_static("https://example.com/order/1"),
+        );
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("10"));
+        headers.append(
+            LINK,
+            HeaderValue::from_static(r#"<https://example.com/cert/1>;rel="alternate""#),
+        );
+        headers.append(
+            LINK,
+            HeaderValue::from_static(r#"<https://example.com/directory>;rel="index""#),
+        );
+
+        let response = AcmeResponse::from_headers((), &headers);
+
+        assert_eq!(
+            response.location,
+            Some(Uri::try_from("https://example.com/order/1").unwrap())
+        );
+        assert_eq!(
+            response.retry_after,
+            Some(RetryAfter::Delay(Duration::from_secs(10)))
+        );
+        assert_eq!(
+            response.links("alternate").collect::<Vec<_>>(),
+            vec![&Uri::try_from("https://example.com/cert/1").unwrap()]
+        );
+    }
+}
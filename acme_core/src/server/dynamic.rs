@@ -1,9 +1,11 @@
-use super::AcmeServer;
+use super::{AcmeServer, ByteStream, ErrInto};
 use crate::dto::{
-    ApiAccount, ApiAuthorization, ApiChallenge, ApiDirectory, ApiKeyChange, ApiNewOrder, ApiOrder,
-    ApiOrderFinalization, PostAsGet, Uri,
+    ApiAccount, ApiAuthorization, ApiChallenge, ApiChallengeResponse, ApiDirectory, ApiError,
+    ApiKeyChange, ApiNewOrder, ApiOrder, ApiOrderFinalization, ApiRenewalInfo, ApiRevocation,
+    PostAsGet, Uri,
 };
-use crate::request::{DynRequest, Jwk, Request, RequestImpl};
+use crate::request::{DynRequest, Jwk, Kid, NoNonce, Request, RequestImpl};
+use crate::response::AcmeResponse;
 use async_trait::async_trait;
 use std::any::Any;
 use std::convert::Infallible;
@@ -16,6 +18,29 @@ type DynError = Box<dyn Error + Send + Sync + 'static>;
 
 pub struct ErrorWrapper(pub DynError);
 
+impl ErrorWrapper {
+    // the concrete error of the server behind the DynAcmeServer
+    pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+
+    pub fn into_inner(self) -> DynError {
+        self.0
+    }
+
+    // the problem document of the CA if the server error or one of its sources is an ApiError
+    pub fn api_error(&self) -> Option<&ApiError> {
+        let mut err: Option<&(dyn Error + 'static)> = Some(&*self.0);
+        while let Some(current) = err {
+            if let Some(api_error) = current.downcast_ref::<ApiError>() {
+                return Some(api_error);
+            }
+            err = current.source();
+        }
+        None
+    }
+}
+
 impl Display for ErrorWrapper {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -61,9 +86,9 @@ pub trait DynAcmeServer: Send + Sync + 'static {
     #[doc(hidden)]
     async fn new_account_dyn(
         &self,
-        req: DynRequest<'_, ApiAccount, Jwk<()>>,
+        req: DynRequest<'_, ApiAccount<DynRequest<'_, Jwk, Kid, NoNonce>>, Jwk>,
         _: &dyn Private,
-    ) -> Result<(ApiAccount, Uri), DynError>;
+    ) -> Result<AcmeResponse<(ApiAccount, Uri)>, DynError>;
 
     #[doc(hidden)]
     async fn get_account_dyn(
@@ -71,7 +96,7 @@ pub trait DynAcmeServer: Send + Sync + 'static {
         uri: &Uri,
         req: DynRequest<'_, PostAsGet>,
         _: &dyn Private,
-    ) -> Result<ApiAccount, DynError>;
+    ) -> Result<AcmeResponse<ApiAccount>, DynError>;
 
     #[doc(hidden)]
     async fn update_account_dyn(
@@ -79,21 +104,21 @@ pub trait DynAcmeServer: Send + Sync + 'static {
         uri: &Uri,
         req: DynRequest<'_, ApiAccount>,
         _: &dyn Private,
-    ) -> Result<ApiAccount, DynError>;
+    ) -> Result<AcmeResponse<ApiAccount>, DynError>;
 
     // use erased serde serialize type here
     async fn change_key_dyn(
         &self,
-        req: DynRequest<'_, DynRequest<ApiKeyChange<()>>>,
+        req: DynRequest<'_, DynRequest<'_, ApiKeyChange<Jwk>, Jwk, NoNonce>>,
         _: &dyn Private,
-    ) -> Result<(), DynError>;
+    ) -> Result<AcmeResponse<()>, DynError>;
 
     #[doc(hidden)]
     async fn new_order_dyn(
         &self,
         req: DynRequest<'_, ApiNewOrder>,
         _: &dyn Private,
-    ) -> Result<(ApiOrder, Uri), DynError>;
+    ) -> Result<AcmeResponse<(ApiOrder, Uri)>, DynError>;
 
     #[doc(hidden)]
     async fn get_order_dyn(
@@ -101,7 +126,7 @@ pub trait DynAcmeServer: Send + Sync + 'static {
         uri: &Uri,
         req: DynRequest<'_, PostAsGet>,
         _: &dyn Private,
-    ) -> Result<ApiOrder, DynError>;
+    ) -> Result<AcmeResponse<ApiOrder>, DynError>;
 
     #[doc(hidden)]
     async fn get_authorization_dyn(
@@ -109,15 +134,15 @@ pub trait DynAcmeServer: Send + Sync + 'static {
         uri: &Uri,
         req: DynRequest<'_, PostAsGet>,
         _: &dyn Private,
-    ) -> Result<ApiAuthorization, DynError>;
+    ) -> Result<AcmeResponse<ApiAuthorization>, DynError>;
 
     #[doc(hidden)]
     async fn validate_challenge_dyn(
         &self,
         uri: &Uri,
-        req: DynRequest<'_, PostAsGet>,
+        req: DynRequest<'_, ApiChallengeResponse>,
         _: &dyn Private,
-    ) -> Result<ApiChallenge, DynError>;
+    ) -> Result<AcmeResponse<ApiChallenge>, DynError>;
 
     #[doc(hidden)]
     async fn finalize_dyn(
@@ -125,7 +150,7 @@ pub trait DynAcmeServer: Send + Sync + 'static {
         uri: &Uri,
         req: DynRequest<'_, ApiOrderFinalization>,
         _: &dyn Private,
-    ) -> Result<ApiOrder, DynError>;
+    ) -> Result<AcmeResponse<ApiOrder>, DynError>;
 
     #[doc(hidden)]
     async fn download_certificate_dyn(
@@ -133,7 +158,29 @@ pub trait DynAcmeServer: Send + Sync + 'static {
         uri: &Uri,
         req: DynRequest<'_, PostAsGet>,
         _: &dyn Private,
-    ) -> Result<Vec<u8>, DynError>;
+    ) -> Result<AcmeResponse<Vec<u8>>, DynError>;
+
+    #[doc(hidden)]
+    async fn download_certificate_stream_dyn(
+        &self,
+        uri: &Uri,
+        req: DynRequest<'_, PostAsGet>,
+        _: &dyn Private,
+    ) -> Result<AcmeResponse<ByteStream<DynError>>, DynError>;
+
+    #[doc(hidden)]
+    async fn revoke_certificate_dyn(
+        &self,
+        req: DynRequest<'_, ApiRevocation>,
+        _: &dyn Private,
+    ) -> Result<AcmeResponse<()>, DynError>;
+
+    #[doc(hidden)]
+    async fn renewal_info_dyn(
+        &self,
+        uri: &Uri,
+        _: &dyn Private,
+    ) -> Result<AcmeResponse<ApiRenewalInfo>, DynError>;
 
     #[doc(hidden)]
     fn box_clone(&self, _: &dyn Private) -> Box<dyn DynAcmeServer>;
@@ -162,9 +209,9 @@ impl<T: AcmeServer + Clone + Debug + Send + Sync + 'static> DynAcmeServer for T
 
     async fn new_account_dyn(
         &self,
-        req: DynRequest<'_, ApiAccount, Jwk<()>>,
+        req: DynRequest<'_, ApiAccount<DynRequest<'_, Jwk, Kid, NoNonce>>, Jwk>,
         _: &dyn Private,
-    ) -> Result<(ApiAccount, Uri), DynError> {
+    ) -> Result<AcmeResponse<(ApiAccount, Uri)>, DynError> {
         Ok(self.new_account(req).await?)
     }
 
@@ -173,7 +220,7 @@ impl<T: AcmeServer + Clone + Debug + Send + Sync + 'static> DynAcmeServer for T
         uri: &Uri,
         req: DynRequest<'_, PostAsGet>,
         _: &dyn Private,
-    ) -> Result<ApiAccount, DynError> {
+    ) -> Result<AcmeResponse<ApiAccount>, DynError> {
         Ok(self.get_account(uri, req).await?)
     }
 
@@ -182,16 +229,16 @@ impl<T: AcmeServer + Clone + Debug + Send + Sync + 'static> DynAcmeServer for T
         uri: &Uri,
         req: DynRequest<'_, ApiAccount>,
         _: &dyn Private,
-    ) -> Result<ApiAccount, DynError> {
+    ) -> Result<AcmeResponse<ApiAccount>, DynError> {
         Ok(self.update_account(uri, req).await?)
     }
 
     // todo: figure this out
     async fn change_key_dyn(
         &self,
-        req: DynRequest<'_, DynRequest<ApiKeyChange<()>>>,
+        req: DynRequest<'_, DynRequest<'_, ApiKeyChange<Jwk>, Jwk, NoNonce>>,
         _: &dyn Private,
-    ) -> Result<(), DynError> {
+    ) -> Result<AcmeResponse<()>, DynError> {
         Ok(self.change_key(req).await?)
     }
 
@@ -199,7 +246,7 @@ impl<T: AcmeServer + Clone + Debug + Send + Sync + 'static> DynAcmeServer for T
         &self,
         req: DynRequest<'_, ApiNewOrder>,
         _: &dyn Private,
-    ) -> Result<(ApiOrder, Uri), DynError> {
+    ) -> Result<AcmeResponse<(ApiOrder, Uri)>, DynError> {
         Ok(self.new_order(req).await?)
     }
 
@@ -208,7 +255,7 @@ impl<T: AcmeServer + Clone + Debug + Send + Sync + 'static> DynAcmeServer for T
         uri: &Uri,
         req: DynRequest<'_, PostAsGet>,
         _: &dyn Private,
-    ) -> Result<ApiOrder, DynError> {
+    ) -> Result<AcmeResponse<ApiOrder>, DynError> {
         Ok(self.get_order(uri, req).await?)
     }
 
@@ -217,16 +264,16 @@ impl<T: AcmeServer + Clone + Debug + Send + Sync + 'static> DynAcmeServer for T
         uri: &Uri,
         req: DynRequest<'_, PostAsGet>,
         _: &dyn Private,
-    ) -> Result<ApiAuthorization, DynError> {
+    ) -> Result<AcmeResponse<ApiAuthorization>, DynError> {
         Ok(self.get_authorization(uri, req).await?)
     }
 
     async fn validate_challenge_dyn(
         &self,
         uri: &Uri,
-        req: DynRequest<'_, PostAsGet>,
+        req: DynRequest<'_, ApiChallengeResponse>,
         _: &dyn Private,
-    ) -> Result<ApiChallenge, DynError> {
+    ) -> Result<AcmeResponse<ApiChallenge>, DynError> {
         Ok(self.validate_challenge(uri, req).await?)
     }
 
@@ -235,7 +282,7 @@ impl<T: AcmeServer + Clone + Debug + Send + Sync + 'static> DynAcmeServer for T
         uri: &Uri,
         req: DynRequest<'_, ApiOrderFinalization>,
         _: &dyn Private,
-    ) -> Result<ApiOrder, DynError> {
+    ) -> Result<AcmeResponse<ApiOrder>, DynError> {
         Ok(self.finalize(uri, req).await?)
     }
 
@@ -244,10 +291,36 @@ impl<T: AcmeServer + Clone + Debug + Send + Sync + 'static> DynAcmeServer for T
         uri: &Uri,
         req: DynRequest<'_, PostAsGet>,
         _: &dyn Private,
-    ) -> Result<Vec<u8>, DynError> {
+    ) -> Result<AcmeResponse<Vec<u8>>, DynError> {
         Ok(self.download_certificate(uri, req).await?)
     }
 
+    async fn download_certificate_stream_dyn(
+        &self,
+        uri: &Uri,
+        req: DynRequest<'_, PostAsGet>,
+        _: &dyn Private,
+    ) -> Result<AcmeResponse<ByteStream<DynError>>, DynError> {
+        let res = self.download_certificate_stream(uri, req).await?;
+        Ok(res.map(|body| Box::pin(ErrInto::new(body)) as ByteStream<DynError>))
+    }
+
+    async fn revoke_certificate_dyn(
+        &self,
+        req: DynRequest<'_, ApiRevocation>,
+        _: &dyn Private,
+    ) -> Result<AcmeResponse<()>, DynError> {
+        Ok(self.revoke_certificate(req).await?)
+    }
+
+    async fn renewal_info_dyn(
+        &self,
+        uri: &Uri,
+        _: &dyn Private,
+    ) -> Result<AcmeResponse<ApiRenewalInfo>, DynError> {
+        Ok(self.renewal_info(uri).await?)
+    }
+
     fn box_clone(&self, _: &dyn Private) -> Box<dyn DynAcmeServer> {
         Box::new(self.clone())
     }
@@ -282,20 +355,49 @@ impl AcmeServer for dyn DynAcmeServer {
         self.directory_dyn(&PrivateImpl)
     }
 
-    async fn new_account(
+    async fn new_account<E: Request<Jwk, Kid, NoNonce>>(
         &self,
-        req: impl Request<ApiAccount, Jwk<()>>,
-    ) -> Result<(ApiAccount, Uri), Self::Error> {
-        Ok(self
-            .new_account_dyn(req.as_dyn_request(), &PrivateImpl)
-            .await?)
+        req: impl Request<ApiAccount<E>, Jwk>,
+    ) -> Result<AcmeResponse<(ApiAccount, Uri)>, Self::Error> {
+        let DynRequest {
+            inner,
+            protected_any,
+            signer_any,
+        } = req.as_dyn_request();
+
+        // the external account binding is a jws itself and has to be erased as well
+        let account = inner.payload;
+        let payload = ApiAccount {
+            status: account.status.clone(),
+            contact: account.contact.clone(),
+            terms_of_service_agreed: account.terms_of_service_agreed,
+            external_account_binding: account
+                .external_account_binding
+                .as_ref()
+                .map(|eab| eab.as_dyn_request()),
+            orders: account.orders.clone(),
+            only_return_existing: account.only_return_existing,
+        };
+
+        let req = DynRequest {
+            inner: RequestImpl {
+                phantom: PhantomData,
+                protected: inner.protected,
+                payload: &payload,
+                signer: inner.signer,
+            },
+            protected_any,
+            signer_any,
+        };
+
+        Ok(self.new_account_dyn(req, &PrivateImpl).await?)
     }
 
     async fn get_account(
         &self,
         uri: &Uri,
         req: impl Request<PostAsGet>,
-    ) -> Result<ApiAccount, Self::Error> {
+    ) -> Result<AcmeResponse<ApiAccount>, Self::Error> {
         Ok(self
             .get_account_dyn(uri, req.as_dyn_request(), &PrivateImpl)
             .await?)
@@ -305,16 +407,16 @@ impl AcmeServer for dyn DynAcmeServer {
         &self,
         uri: &Uri,
         req: impl Request<ApiAccount>,
-    ) -> Result<ApiAccount, Self::Error> {
+    ) -> Result<AcmeResponse<ApiAccount>, Self::Error> {
         Ok(self
             .update_account_dyn(uri, req.as_dyn_request(), &PrivateImpl)
             .await?)
     }
 
-    async fn change_key<R: Request<ApiKeyChange<()>>>(
+    async fn change_key<R: Request<ApiKeyChange<Jwk>, Jwk, NoNonce>>(
         &self,
         req: impl Request<R>,
-    ) -> Result<(), Self::Error> {
+    ) -> Result<AcmeResponse<()>, Self::Error> {
         let DynRequest {
             inner,
             protected_any,
@@ -340,7 +442,7 @@ impl AcmeServer for dyn DynAcmeServer {
     async fn new_order(
         &self,
         req: impl Request<ApiNewOrder>,
-    ) -> Result<(ApiOrder, Uri), Self::Error> {
+    ) -> Result<AcmeResponse<(ApiOrder, Uri)>, Self::Error> {
         Ok(self
             .new_order_dyn(req.as_dyn_request(), &PrivateImpl)
             .await?)
@@ -350,7 +452,7 @@ impl AcmeServer for dyn DynAcmeServer {
         &self,
         uri: &Uri,
         req: impl Request<PostAsGet>,
-    ) -> Result<ApiOrder, Self::Error> {
+    ) -> Result<AcmeResponse<ApiOrder>, Self::Error> {
         Ok(self
             .get_order_dyn(uri, req.as_dyn_request(), &PrivateImpl)
             .await?)
@@ -360,7 +462,7 @@ impl AcmeServer for dyn DynAcmeServer {
         &self,
         uri: &Uri,
         req: impl Request<PostAsGet>,
-    ) -> Result<ApiAuthorization, Self::Error> {
+    ) -> Result<AcmeResponse<ApiAuthorization>, Self::Error> {
         Ok(self
             .get_authorization_dyn(uri, req.as_dyn_request(), &PrivateImpl)
             .await?)
@@ -369,8 +471,8 @@ impl AcmeServer for dyn DynAcmeServer {
     async fn validate_challenge(
         &self,
         uri: &Uri,
-        req: impl Request<PostAsGet>,
-    ) -> Result<ApiChallenge, Self::Error> {
+        req: impl Request<ApiChallengeResponse>,
+    ) -> Result<AcmeResponse<ApiChallenge>, Self::Error> {
         Ok(self
             .validate_challenge_dyn(uri, req.as_dyn_request(), &PrivateImpl)
             .await?)
@@ -380,7 +482,7 @@ impl AcmeServer for dyn DynAcmeServer {
         &self,
         uri: &Uri,
         req: impl Request<ApiOrderFinalization>,
-    ) -> Result<ApiOrder, Self::Error> {
+    ) -> Result<AcmeResponse<ApiOrder>, Self::Error> {
         Ok(self
             .finalize_dyn(uri, req.as_dyn_request(), &PrivateImpl)
             .await?)
@@ -390,11 +492,35 @@ impl AcmeServer for dyn DynAcmeServer {
         &self,
         uri: &Uri,
         req: impl Request<PostAsGet>,
-    ) -> Result<Vec<u8>, Self::Error> {
+    ) -> Result<AcmeResponse<Vec<u8>>, Self::Error> {
         Ok(self
             .download_certificate_dyn(uri, req.as_dyn_request(), &PrivateImpl)
             .await?)
     }
+
+    async fn download_certificate_stream(
+        &self,
+        uri: &Uri,
+        req: impl Request<PostAsGet>,
+    ) -> Result<AcmeResponse<ByteStream<Self::Error>>, Self::Error> {
+        let res = self
+            .download_certificate_stream_dyn(uri, req.as_dyn_request(), &PrivateImpl)
+            .await?;
+        Ok(res.map(|body| Box::pin(ErrInto::new(body)) as ByteStream<Self::Error>))
+    }
+
+    async fn revoke_certificate(
+        &self,
+        req: impl Request<ApiRevocation>,
+    ) -> Result<AcmeResponse<()>, Self::Error> {
+        Ok(self
+            .revoke_certificate_dyn(req.as_dyn_request(), &PrivateImpl)
+            .await?)
+    }
+
+    async fn renewal_info(&self, uri: &Uri) -> Result<AcmeResponse<ApiRenewalInfo>, Self::Error> {
+        Ok(self.renewal_info_dyn(uri, &PrivateImpl).await?)
+    }
 }
 
 impl Clone for Box<dyn DynAcmeServer> {
@@ -418,6 +544,7 @@ mod tests {
     use std::ptr;
 
     use super::*;
+    use crate::dto::ApiErrorType;
 
     // Type can't be zero sized for ptr equality test to work.
     #[derive(Clone, Debug, Default)]
@@ -436,10 +563,10 @@ mod tests {
             todo!()
         }
 
-        async fn new_account(
+        async fn new_account<E: Request<Jwk, Kid, NoNonce>>(
             &self,
-            _req: impl Request<ApiAccount, Jwk<()>>,
-        ) -> Result<(ApiAccount, Uri), Self::Error> {
+            _req: impl Request<ApiAccount<E>, Jwk>,
+        ) -> Result<AcmeResponse<(ApiAccount, Uri)>, Self::Error> {
             todo!()
         }
 
@@ -447,7 +574,7 @@ mod tests {
             &self,
             _uri: &Uri,
             _req: impl Request<PostAsGet>,
-        ) -> Result<ApiAccount, Self::Error> {
+        ) -> Result<AcmeResponse<ApiAccount>, Self::Error> {
             todo!()
         }
 
@@ -455,21 +582,21 @@ mod tests {
             &self,
             _uri: &Uri,
             _req: impl Request<ApiAccount>,
-        ) -> Result<ApiAccount, Self::Error> {
+        ) -> Result<AcmeResponse<ApiAccount>, Self::Error> {
             todo!()
         }
 
-        async fn change_key<R: Request<ApiKeyChange<()>>>(
+        async fn change_key<R: Request<ApiKeyChange<Jwk>, Jwk, NoNonce>>(
             &self,
             _req: impl Request<R>,
-        ) -> Result<(), Self::Error> {
+        ) -> Result<AcmeResponse<()>, Self::Error> {
             todo!()
         }
 
         async fn new_order(
             &self,
             _req: impl Request<ApiNewOrder>,
-        ) -> Result<(ApiOrder, Uri), Self::Error> {
+        ) -> Result<AcmeResponse<(ApiOrder, Uri)>, Self::Error> {
             todo!()
         }
 
@@ -477,7 +604,7 @@ mod tests {
             &self,
             _uri: &Uri,
             _req: impl Request<PostAsGet>,
-        ) -> Result<ApiOrder, Self::Error> {
+        ) -> Result<AcmeResponse<ApiOrder>, Self::Error> {
             todo!()
         }
 
@@ -485,15 +612,15 @@ mod tests {
             &self,
             _uri: &Uri,
             _req: impl Request<PostAsGet>,
-        ) -> Result<ApiAuthorization, Self::Error> {
+        ) -> Result<AcmeResponse<ApiAuthorization>, Self::Error> {
             todo!()
         }
 
         async fn validate_challenge(
             &self,
             _uri: &Uri,
-            _req: impl Request<PostAsGet>,
-        ) -> Result<ApiChallenge, Self::Error> {
+            _req: impl Request<ApiChallengeResponse>,
+        ) -> Result<AcmeResponse<ApiChallenge>, Self::Error> {
             todo!()
         }
 
@@ -501,7 +628,7 @@ mod tests {
             &self,
             _uri: &Uri,
             _req: impl Request<ApiOrderFinalization>,
-        ) -> Result<ApiOrder, Self::Error> {
+        ) -> Result<AcmeResponse<ApiOrder>, Self::Error> {
             todo!()
         }
 
@@ -509,7 +636,21 @@ mod tests {
             &self,
             _uri: &Uri,
             _req: impl Request<PostAsGet>,
-        ) -> Result<Vec<u8>, Self::Error> {
+        ) -> Result<AcmeResponse<Vec<u8>>, Self::Error> {
+            todo!()
+        }
+
+        async fn revoke_certificate(
+            &self,
+            _req: impl Request<ApiRevocation>,
+        ) -> Result<AcmeResponse<()>, Self::Error> {
+            todo!()
+        }
+
+        async fn renewal_info(
+            &self,
+            _uri: &Uri,
+        ) -> Result<AcmeResponse<ApiRenewalInfo>, Self::Error> {
             todo!()
         }
     }
@@ -520,6 +661,35 @@ mod tests {
         let _server: ServerImpl = *server.into_any().downcast::<ServerImpl>().unwrap();
     }
 
+    #[derive(Debug)]
+    struct ServerError(ApiError);
+
+    impl Display for ServerError {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            write!(f, "server error")
+        }
+    }
+
+    impl Error for ServerError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn error_wrapper_keeps_inner_error() {
+        let api_error = ApiError {
+            type_val: ApiErrorType::BadCSR,
+            detail: "bad csr".to_owned(),
+            subproblems: Vec::new(),
+        };
+        let err = ErrorWrapper(Box::new(ServerError(api_error)));
+
+        assert!(err.downcast_ref::<ServerError>().is_some());
+        assert_eq!(err.api_error().unwrap().type_val, ApiErrorType::BadCSR);
+        assert!(ErrorWrapper("other".into()).api_error().is_none());
+    }
+
     #[tokio::test]
     async fn debug_works() {
         let server: Box<dyn DynAcmeServer> = Box::new(ServerImpl::default());
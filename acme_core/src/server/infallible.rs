@@ -1,9 +1,10 @@
 use super::{AcmeServer, AcmeServerBuilder};
 use crate::dto::{
-    ApiAccount, ApiAuthorization, ApiChallenge, ApiDirectory, ApiKeyChange, ApiNewOrder, ApiOrder,
-    ApiOrderFinalization, PostAsGet, Uri,
+    ApiAccount, ApiAuthorization, ApiChallenge, ApiChallengeResponse, ApiDirectory, ApiKeyChange,
+    ApiNewOrder, ApiOrder, ApiOrderFinalization, ApiRenewalInfo, ApiRevocation, PostAsGet, Uri,
 };
-use crate::request::{Jwk, Request};
+use crate::request::{Jwk, Kid, NoNonce, Request};
+use crate::response::AcmeResponse;
 use async_trait::async_trait;
 use std::convert::Infallible;
 
@@ -29,10 +30,10 @@ impl AcmeServer for Infallible {
         match *self {}
     }
 
-    async fn new_account(
+    async fn new_account<E: Request<Jwk, Kid, NoNonce>>(
         &self,
-        _req: impl Request<ApiAccount, Jwk<()>>,
-    ) -> Result<(ApiAccount, Uri), Self::Error> {
+        _req: impl Request<ApiAccount<E>, Jwk>,
+    ) -> Result<AcmeResponse<(ApiAccount, Uri)>, Self::Error> {
         match *self {}
     }
 
@@ -40,7 +41,7 @@ impl AcmeServer for Infallible {
         &self,
         _uri: &Uri,
         _req: impl Request<PostAsGet>,
-    ) -> Result<ApiAccount, Self::Error> {
+    ) -> Result<AcmeResponse<ApiAccount>, Self::Error> {
         match *self {}
     }
 
@@ -48,21 +49,21 @@ impl AcmeServer for Infallible {
         &self,
         _uri: &Uri,
         _req: impl Request<ApiAccount>,
-    ) -> Result<ApiAccount, Self::Error> {
+    ) -> Result<AcmeResponse<ApiAccount>, Self::Error> {
         match *self {}
     }
 
-    async fn change_key<R: Request<ApiKeyChange<()>>>(
+    async fn change_key<R: Request<ApiKeyChange<Jwk>, Jwk, NoNonce>>(
         &self,
         _req: impl Request<R>,
-    ) -> Result<(), Self::Error> {
+    ) -> Result<AcmeResponse<()>, Self::Error> {
         match *self {}
     }
 
     async fn new_order(
         &self,
         _req: impl Request<ApiNewOrder>,
-    ) -> Result<(ApiOrder, Uri), Self::Error> {
+    ) -> Result<AcmeResponse<(ApiOrder, Uri)>, Self::Error> {
         match *self {}
     }
 
@@ -70,7 +71,7 @@ impl AcmeServer for Infallible {
         &self,
         _uri: &Uri,
         _req: impl Request<PostAsGet>,
-    ) -> Result<ApiOrder, Self::Error> {
+    ) -> Result<AcmeResponse<ApiOrder>, Self::Error> {
         match *self {}
     }
 
@@ -78,15 +79,15 @@ impl AcmeServer for Infallible {
         &self,
         _uri: &Uri,
         _req: impl Request<PostAsGet>,
-    ) -> Result<ApiAuthorization, Self::Error> {
+    ) -> Result<AcmeResponse<ApiAuthorization>, Self::Error> {
         match *self {}
     }
 
     async fn validate_challenge(
         &self,
         _uri: &Uri,
-        _req: impl Request<PostAsGet>,
-    ) -> Result<ApiChallenge, Self::Error> {
+        _req: impl Request<ApiChallengeResponse>,
+    ) -> Result<AcmeResponse<ApiChallenge>, Self::Error> {
         match *self {}
     }
 
@@ -94,7 +95,7 @@ impl AcmeServer for Infallible {
         &self,
         _uri: &Uri,
         _req: impl Request<ApiOrderFinalization>,
-    ) -> Result<ApiOrder, Self::Error> {
+    ) -> Result<AcmeResponse<ApiOrder>, Self::Error> {
         match *self {}
     }
 
@@ -102,7 +103,18 @@ impl AcmeServer for Infallible {
         &self,
         _uri: &Uri,
         _req: impl Request<PostAsGet>,
-    ) -> Result<Vec<u8>, Self::Error> {
+    ) -> Result<AcmeResponse<Vec<u8>>, Self::Error> {
+        match *self {}
+    }
+
+    async fn revoke_certificate(
+        &self,
+        _req: impl Request<ApiRevocation>,
+    ) -> Result<AcmeResponse<()>, Self::Error> {
+        match *self {}
+    }
+
+    async fn renewal_info(&self, _uri: &Uri) -> Result<AcmeResponse<ApiRenewalInfo>, Self::Error> {
         match *self {}
     }
 }
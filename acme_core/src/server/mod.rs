@@ -1,18 +1,71 @@
 use crate::dto::{
-    ApiAccount, ApiAuthorization, ApiChallenge, ApiDirectory, ApiKeyChange, ApiNewOrder, ApiOrder,
-    ApiOrderFinalization, NoExternalAccountBinding, PostAsGet, Uri,
+    ApiAccount, ApiAuthorization, ApiChallenge, ApiChallengeResponse, ApiDirectory, ApiKeyChange,
+    ApiNewOrder, ApiOrder, ApiOrderFinalization, ApiRenewalInfo, ApiRevocation,
+    NoExternalAccountBinding, PostAsGet, Uri,
 };
-use crate::request::{Jwk, Request};
+use crate::request::{Jwk, Kid, NoNonce, Request};
+use crate::response::AcmeResponse;
 use async_trait::async_trait;
+use futures_core::Stream;
 use std::error::Error;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
 pub mod dynamic;
 mod infallible;
 
+// chunks of a response body as they arrive
+pub type ByteStream<E> = Pin<Box<dyn Stream<Item = Result<Vec<u8>, E>> + Send>>;
+
+// a body that was already received completely
+struct Once<E> {
+    body: Option<Vec<u8>>,
+    phantom: PhantomData<fn() -> E>,
+}
+
+impl<E> Stream for Once<E> {
+    type Item = Result<Vec<u8>, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.body.take().map(Ok))
+    }
+}
+
+// converts the error of every chunk, e.g. to box it for DynAcmeServer
+pub(crate) struct ErrInto<E, T> {
+    inner: ByteStream<E>,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<E, T> ErrInto<E, T> {
+    pub(crate) fn new(inner: ByteStream<E>) -> Self {
+        Self {
+            inner,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, T: From<E>> Stream for ErrInto<E, T> {
+    type Item = Result<Vec<u8>, T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner
+            .as_mut()
+            .poll_next(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map_err(T::from)))
+    }
+}
+
 #[async_trait]
 pub trait AcmeServerBuilder: Send + Sync + 'static {
     type Server: AcmeServer;
     async fn build(&mut self) -> Result<Self::Server, <Self::Server as AcmeServer>::Error>;
+
+    fn directory_url(&self) -> Option<&str> {
+        None
+    }
 }
 
 pub trait AcmeServerExt: AcmeServer {
@@ -38,60 +91,84 @@ pub trait AcmeServer: Send + Sync {
 
     fn directory(&self) -> &ApiDirectory;
 
-    async fn new_account(
+    async fn new_account<E: Request<Jwk, Kid, NoNonce>>(
         &self,
-        req: impl Request<ApiAccount, Jwk<()>>,
-    ) -> Result<(ApiAccount, Uri), Self::Error>;
+        req: impl Request<ApiAccount<E>, Jwk>,
+    ) -> Result<AcmeResponse<(ApiAccount, Uri)>, Self::Error>;
 
     async fn get_account(
         &self,
         uri: &Uri,
         req: impl Request<PostAsGet>,
-    ) -> Result<ApiAccount, Self::Error>;
+    ) -> Result<AcmeResponse<ApiAccount>, Self::Error>;
 
     async fn update_account(
         &self,
         uri: &Uri,
         req: impl Request<ApiAccount<NoExternalAccountBinding>>,
-    ) -> Result<ApiAccount, Self::Error>;
+    ) -> Result<AcmeResponse<ApiAccount>, Self::Error>;
 
-    async fn change_key<R: Request<ApiKeyChange<()>>>(
+    async fn change_key<R: Request<ApiKeyChange<Jwk>, Jwk, NoNonce>>(
         &self,
         req: impl Request<R>,
-    ) -> Result<(), Self::Error>;
+    ) -> Result<AcmeResponse<()>, Self::Error>;
 
     async fn new_order(
         &self,
         req: impl Request<ApiNewOrder>,
-    ) -> Result<(ApiOrder, Uri), Self::Error>;
+    ) -> Result<AcmeResponse<(ApiOrder, Uri)>, Self::Error>;
 
     async fn get_order(
         &self,
         uri: &Uri,
         req: impl Request<PostAsGet>,
-    ) -> Result<ApiOrder, Self::Error>;
+    ) -> Result<AcmeResponse<ApiOrder>, Self::Error>;
 
     async fn get_authorization(
         &self,
         uri: &Uri,
         req: impl Request<PostAsGet>,
-    ) -> Result<ApiAuthorization, Self::Error>;
+    ) -> Result<AcmeResponse<ApiAuthorization>, Self::Error>;
 
     async fn validate_challenge(
         &self,
         uri: &Uri,
-        req: impl Request<PostAsGet>,
-    ) -> Result<ApiChallenge, Self::Error>;
+        req: impl Request<ApiChallengeResponse>,
+    ) -> Result<AcmeResponse<ApiChallenge>, Self::Error>;
 
     async fn finalize(
         &self,
         uri: &Uri,
         req: impl Request<ApiOrderFinalization>,
-    ) -> Result<ApiOrder, Self::Error>;
+    ) -> Result<AcmeResponse<ApiOrder>, Self::Error>;
 
     async fn download_certificate(
         &self,
         uri: &Uri,
         req: impl Request<PostAsGet>,
-    ) -> Result<Vec<u8>, Self::Error>;
+    ) -> Result<AcmeResponse<Vec<u8>>, Self::Error>;
+
+    // the pem chain while it is still being received, servers which can't stream yield the
+    // whole body of download_certificate as a single chunk
+    async fn download_certificate_stream(
+        &self,
+        uri: &Uri,
+        req: impl Request<PostAsGet>,
+    ) -> Result<AcmeResponse<ByteStream<Self::Error>>, Self::Error> {
+        let res = self.download_certificate(uri, req).await?;
+        Ok(res.map(|body| {
+            Box::pin(Once {
+                body: Some(body),
+                phantom: PhantomData,
+            }) as ByteStream<Self::Error>
+        }))
+    }
+
+    async fn revoke_certificate(
+        &self,
+        req: impl Request<ApiRevocation>,
+    ) -> Result<AcmeResponse<()>, Self::Error>;
+
+    // unauthenticated get, uri is the renewal info url of the directory joined with the cert id
+    async fn renewal_info(&self, uri: &Uri) -> Result<AcmeResponse<ApiRenewalInfo>, Self::Error>;
 }
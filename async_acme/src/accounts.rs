@@ -0,0 +1,150 @@
+use acme_core::Uri;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use tracing::info;
+
+use crate::crypto::KeyPair;
+use crate::{Account, AccountBuilder, DataType, Directory, ManagerError, Persist};
+
+// used as persist key if the directory url is unknown
+const DIRECTORY_KEY: &str = "directory";
+
+// one account per directory and label, e.g. per tenant of a platform issuing on behalf of
+// others. accounts are loaded from persist or registered on first use and cached afterwards
+#[derive(Debug)]
+pub struct AccountManager<P> {
+    persist: P,
+    accounts: Mutex<HashMap<(String, String), Account<'static>>>,
+    // registrations are serialized so a label never ends up with two accounts
+    register: tokio::sync::Mutex<()>,
+}
+
+impl<P: Persist + Send + Sync> AccountManager<P> {
+    pub fn new(persist: P) -> Self {
+        Self {
+            persist,
+            accounts: Mutex::new(HashMap::new()),
+            register: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    // the mail is used as label and contact of the account
+    pub async fn account(
+        &self,
+        directory: &Directory,
+        mail: &str,
+    ) -> Result<Account<'static>, ManagerError> {
+        self.account_with(directory, mail, |builder| {
+            builder.mail(mail).agree_terms_of_service()
+        })
+        .await
+    }
+
+    // register configures the account if none is stored for the label yet,
+    // e.g. to add an external account binding
+    pub async fn account_with<F>(
+        &self,
+        directory: &Directory,
+        label: &str,
+        register: F,
+    ) -> Result<Account<'static>, ManagerError>
+    where
+        F: FnOnce(AccountBuilder<'_>) -> AccountBuilder<'_>,
+    {
+        let key = (
+            directory.url().unwrap_or(DIRECTORY_KEY).to_owned(),
+            label.to_owned(),
+        );
+        if let Some(account) = self.accounts.lock().get(&key).cloned() {
+            return Ok(account);
+        }
+
+        let _register = self.register.lock().await;
+        // another task might have loaded the account while waiting for the lock
+        if let Some(account) = self.accounts.lock().get(&key).cloned() {
+            return Ok(account);
+        }
+
+        let persist_key = persist_key(&key.0, &key.1);
+        let kid = self.get(DataType::Kid, &persist_key).await?;
+        let private_key = self.get(DataType::PrivateKey, &persist_key).await?;
+
+        let account = match (kid, private_key) {
+            (Some(kid), Some(private_key)) => {
+                let kid = Uri::try_from(String::from_utf8_lossy(&kid).into_owned())?;
+                let mut account = directory.account_from_parts(kid, private_key)?;
+                account.update().await?;
+                account.into_owned()
+            }
+            _ => {
+                let account = register(directory.account_builder()).register().await?;
+                let kid = hyper::Uri::from(account.kid()).to_string();
+                info!(label, "registered account");
+
+                self.put(
+                    DataType::PrivateKey,
+                    &persist_key,
+                    account.key_pair().as_der().to_vec(),
+                )
+                .await?;
+                self.put(DataType::Kid, &persist_key, kid.into_bytes())
+                    .await?;
+                account.into_owned()
+            }
+        };
+
+        self.accounts.lock().insert(key, account.clone());
+        Ok(account)
+    }
+
+    // drops the cached account, e.g. after its key was changed elsewhere
+    pub fn forget(&self, directory: &Directory, label: &str) {
+        let key = (
+            directory.url().unwrap_or(DIRECTORY_KEY).to_owned(),
+            label.to_owned(),
+        );
+        self.accounts.lock().remove(&key);
+    }
+
+    async fn get(&self, data_type: DataType, key: &str) -> Result<Option<Vec<u8>>, ManagerError> {
+        self.persist
+            .get(data_type, key)
+            .await
+            .map_err(|err| ManagerError::Persist(err.into()))
+    }
+
+    async fn put(
+        &self,
+        data_type: DataType,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ManagerError> {
+        self.persist
+            .put(data_type, key, value)
+            .await
+            .map_err(|err| ManagerError::Persist(err.into()))
+    }
+}
+
+// labels can't collide with the keys of CertificateManager which only uses the directory url
+fn persist_key(directory: &str, label: &str) -> String {
+    format!("{}#{}", directory, label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persist_keys() {
+        assert_eq!(
+            persist_key("https://ca/directory", "tenant@example.com"),
+            "https://ca/directory#tenant@example.com"
+        );
+        assert_ne!(
+            persist_key("https://ca/directory", "a"),
+            persist_key("https://ca/directory", "b")
+        );
+    }
+}
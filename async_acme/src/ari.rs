@@ -0,0 +1,88 @@
+use acme_core::ApiSuggestedWindow;
+use ring::rand::{generate, SystemRandom};
+use time::OffsetDateTime;
+use x509_parser::extensions::ParsedExtension;
+
+use crate::chain::leaf_certificate;
+
+// cert id of RFC 9773, None if the leaf has no authority key identifier
+pub(crate) fn cert_id(chain: &[u8]) -> Option<String> {
+    leaf_certificate(chain, |leaf| {
+        let key_id =
+            leaf.extensions()
+                .iter()
+                .find_map(|extension| match extension.parsed_extension() {
+                    ParsedExtension::AuthorityKeyIdentifier(aki) => aki.key_identifier.as_ref(),
+                    _ => None,
+                })?;
+
+        Some(encode(key_id.0, leaf.raw_serial()))
+    })
+}
+
+// the serial keeps its leading zero byte
+fn encode(key_id: &[u8], serial: &[u8]) -> String {
+    format!(
+        "{}.{}",
+        base64::encode_config(key_id, base64::URL_SAFE_NO_PAD),
+        base64::encode_config(serial, base64::URL_SAFE_NO_PAD)
+    )
+}
+
+// uniformly random within [start, end) so clients don't renew all at once as RFC 9773 suggests
+pub(crate) fn renewal_time(window: &ApiSuggestedWindow) -> OffsetDateTime {
+    let length = window.end - window.start;
+    if length <= time::Duration::ZERO {
+        return window.start;
+    }
+    let random = match generate::<[u8; 4]>(&SystemRandom::new()) {
+        Ok(random) => u32::from_be_bytes(random.expose()),
+        Err(_) => return window.start,
+    };
+
+    // divided by 2^32 so the end itself is never picked
+    window.start + length * (random as f64 / (u32::MAX as f64 + 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc_example() {
+        let key_id = [
+            0x69, 0x88, 0x5b, 0x6b, 0x87, 0x46, 0x40, 0x41, 0xe1, 0xb3, 0x7b, 0x84, 0x7b, 0xa0,
+            0xae, 0x2c, 0xde, 0x01, 0xc8, 0xd4,
+        ];
+        let serial = [0x00, 0x87, 0x65, 0x43, 0x21];
+
+        assert_eq!(
+            encode(&key_id, &serial),
+            "aYhba4dGQEHhs3uEe6CuLN4ByNQ.AIdlQyE"
+        );
+    }
+
+    #[test]
+    fn self_signed_has_no_id() {
+        let cert = rcgen::generate_simple_self_signed(vec!["example.com".to_string()]).unwrap();
+        let chain = cert.serialize_pem().unwrap();
+
+        assert_eq!(cert_id(chain.as_bytes()), None);
+    }
+
+    #[test]
+    fn renewal_time_within_window() {
+        let start = OffsetDateTime::now_utc();
+        let window = ApiSuggestedWindow {
+            start,
+            end: start + time::Duration::days(2),
+        };
+        for _ in 0..100 {
+            let time = renewal_time(&window);
+            assert!(time >= window.start && time < window.end);
+        }
+
+        let empty = ApiSuggestedWindow { start, end: start };
+        assert_eq!(renewal_time(&empty), start);
+    }
+}
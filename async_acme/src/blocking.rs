@@ -0,0 +1,181 @@
+use acme_core::{AcmeServer, AcmeServerBuilder, ApiRevocationReason, Uri};
+use std::error::Error;
+use std::fmt::Debug;
+use std::future::Future;
+use std::io;
+use thiserror::Error;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{CertificateBundle, DirectoryBuilder, DirectoryError, Finished, Solver, SolverChain};
+
+#[derive(Debug, Error)]
+pub enum BlockingError<E: Error + 'static> {
+    #[error("Runtime could not be started: {0}")]
+    Runtime(io::Error),
+    #[error(transparent)]
+    Build(E),
+}
+
+// synchronous wrapper around crate::Directory driving it on a private current thread runtime,
+// must not be used from within an async context as block_on panics there
+#[derive(Debug)]
+pub struct Directory {
+    // dropped before the runtime so the connections of the client are closed first
+    inner: crate::Directory,
+    runtime: Runtime,
+}
+
+#[derive(Debug)]
+pub struct Account<'a> {
+    inner: crate::Account<'a>,
+    runtime: &'a Runtime,
+}
+
+impl Directory {
+    pub fn new<S: AcmeServerBuilder + Clone>(
+        builder: DirectoryBuilder<Finished, S>,
+    ) -> Result<Self, BlockingError<<S::Server as AcmeServer>::Error>>
+    where
+        S::Server: Clone + Debug,
+    {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(BlockingError::Runtime)?;
+        let inner = runtime
+            .block_on(builder.build())
+            .map_err(BlockingError::Build)?;
+
+        Ok(Self { inner, runtime })
+    }
+
+    pub fn new_account<T: AsRef<str>>(&self, mail: T) -> Result<Account<'_>, DirectoryError> {
+        let inner = self.block_on(self.inner.new_account(mail))?;
+        Ok(self.account(inner))
+    }
+
+    pub fn import_credentials<T: AsRef<[u8]>>(
+        &self,
+        credentials: T,
+    ) -> Result<Account<'_>, DirectoryError> {
+        let inner = self.block_on(self.inner.import_credentials(credentials))?;
+        Ok(self.account(inner))
+    }
+
+    pub fn import_private_key<T: AsRef<[u8]>>(
+        &self,
+        kid: Uri,
+        private_key: T,
+    ) -> Result<Account<'_>, DirectoryError> {
+        let inner = self.block_on(self.inner.import_private_key(kid, private_key))?;
+        Ok(self.account(inner))
+    }
+
+    pub fn account_from_parts<T: AsRef<[u8]>>(
+        &self,
+        kid: Uri,
+        private_key: T,
+    ) -> Result<Account<'_>, DirectoryError> {
+        let inner = self.inner.account_from_parts(kid, private_key)?;
+        Ok(self.account(inner))
+    }
+
+    pub fn issue<I, T, S>(
+        &self,
+        domains: I,
+        solver: &S,
+    ) -> Result<CertificateBundle, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+        S: Solver + ?Sized,
+    {
+        self.block_on(self.inner.issue(domains, solver))
+    }
+
+    pub fn issue_with_chain<I, T>(
+        &self,
+        domains: I,
+        chain: &SolverChain,
+    ) -> Result<CertificateBundle, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.block_on(self.inner.issue_with_chain(domains, chain))
+    }
+
+    // the async directory, futures of it have to be run with block_on
+    pub fn inner(&self) -> &crate::Directory {
+        &self.inner
+    }
+
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    fn account<'a>(&'a self, inner: crate::Account<'a>) -> Account<'a> {
+        Account {
+            inner,
+            runtime: &self.runtime,
+        }
+    }
+}
+
+impl<'a> Account<'a> {
+    pub fn kid(&self) -> &Uri {
+        self.inner.kid()
+    }
+
+    pub fn export_credentials(&self) -> Result<String, DirectoryError> {
+        self.inner.export_credentials()
+    }
+
+    pub fn change_mail<T: AsRef<str>>(&mut self, mail: T) -> Result<(), DirectoryError> {
+        self.runtime.block_on(self.inner.change_mail(mail))?;
+        Ok(())
+    }
+
+    pub fn change_key(&mut self) -> Result<(), DirectoryError> {
+        self.runtime.block_on(self.inner.change_key())?;
+        Ok(())
+    }
+
+    pub fn issue<I, T, S>(
+        &self,
+        domains: I,
+        solver: &S,
+    ) -> Result<CertificateBundle, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+        S: Solver + ?Sized,
+    {
+        self.runtime.block_on(self.inner.issue(domains, solver))
+    }
+
+    pub fn issue_with_chain<I, T>(
+        &self,
+        domains: I,
+        chain: &SolverChain,
+    ) -> Result<CertificateBundle, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.runtime
+            .block_on(self.inner.issue_with_chain(domains, chain))
+    }
+
+    pub fn revoke(
+        &self,
+        chain: &[u8],
+        reason: Option<ApiRevocationReason>,
+    ) -> Result<(), DirectoryError> {
+        self.runtime.block_on(self.inner.revoke(chain, reason))
+    }
+
+    pub fn inner(&self) -> &crate::Account<'a> {
+        &self.inner
+    }
+}
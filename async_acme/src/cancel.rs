@@ -0,0 +1,88 @@
+use futures_util::future::{self, Either};
+use futures_util::pin_mut;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::Runtime;
+
+// shared by all clones, once cancelled waiting on the CA stops with DirectoryError::Cancelled.
+// requests in flight are finished and presented challenges still get cleaned up
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    sender: Arc<watch::Sender<bool>>,
+    receiver: watch::Receiver<bool>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        let (sender, receiver) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+            receiver,
+        }
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        // can't fail as self holds a receiver
+        let _ = self.sender.send(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    pub async fn cancelled(&self) {
+        let mut receiver = self.receiver.clone();
+        while !*receiver.borrow_and_update() {
+            // the sender lives as long as self
+            if receiver.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    // cancels the token once the future, e.g. a shutdown signal, completes
+    pub fn cancel_on<F: Future>(&self, signal: F) -> impl Future<Output = ()> {
+        let token = self.clone();
+        async move {
+            signal.await;
+            token.cancel();
+        }
+    }
+
+    // false if cancelled before the duration elapsed
+    pub(crate) async fn sleep(&self, runtime: &dyn Runtime, duration: Duration) -> bool {
+        let sleep = runtime.sleep(duration);
+        let cancelled = self.cancelled();
+        pin_mut!(sleep, cancelled);
+
+        matches!(future::select(sleep, cancelled).await, Either::Left(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokioRuntime;
+
+    #[tokio::test]
+    async fn cancels_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        assert!(clone.sleep(&TokioRuntime, Duration::from_millis(1)).await);
+
+        token.cancel();
+        assert!(clone.is_cancelled());
+        clone.cancelled().await;
+        assert!(!clone.sleep(&TokioRuntime, Duration::from_secs(60)).await);
+    }
+}
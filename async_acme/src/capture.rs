@@ -0,0 +1,195 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+const REDACTED: &str = "[redacted]";
+// signatures, account keys and the nested jws of key changes and external account bindings
+const REDACTED_FIELDS: [&str; 3] = ["signature", "jwk", "oldKey"];
+
+// a signed request and the answer of the CA, see HyperAcmeServerBuilder::capture
+#[derive(Debug, Clone, Serialize)]
+pub struct CapturedExchange {
+    pub url: String,
+    // decoded from base64url, None if it isn't json
+    pub protected: Option<Value>,
+    // None for post-as-get
+    pub payload: Option<Value>,
+    pub status: Option<u16>,
+    // lossy utf8, certificate chains are captured as pem
+    pub response: Option<String>,
+    pub error: Option<String>,
+}
+
+impl CapturedExchange {
+    // body is the flattened jws sent to the CA
+    pub(crate) fn new(url: String, body: &[u8]) -> Self {
+        let jws = serde_json::from_slice::<Value>(body).ok();
+        let decode = |field| {
+            let field = jws.as_ref()?.get(field)?.as_str()?;
+            let field = base64::decode_config(field, base64::URL_SAFE_NO_PAD).ok()?;
+            let mut field = serde_json::from_slice(&field).ok()?;
+            redact(&mut field);
+            Some(field)
+        };
+
+        Self {
+            protected: decode("protected"),
+            payload: decode("payload"),
+            url,
+            status: None,
+            response: None,
+            error: None,
+        }
+    }
+
+    // body is None for streamed certificate chains
+    pub(crate) fn response(mut self, status: u16, body: Option<&[u8]>) -> Self {
+        self.status = Some(status);
+        self.response = body.map(|body| String::from_utf8_lossy(body).into_owned());
+        self
+    }
+
+    pub(crate) fn error<E: ToString>(mut self, error: &E) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match REDACTED_FIELDS.contains(&key.as_str()) {
+                    true => *value = Value::String(REDACTED.to_owned()),
+                    false => redact(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+// receives every signed request, nonce and directory requests are not captured
+pub trait CaptureSink: Debug + Send + Sync {
+    fn capture(&self, exchange: CapturedExchange);
+}
+
+// keeps the last exchanges in memory, clones share the buffer
+#[derive(Debug, Clone)]
+pub struct RingBufferCapture {
+    capacity: usize,
+    exchanges: Arc<Mutex<VecDeque<CapturedExchange>>>,
+}
+
+impl RingBufferCapture {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            exchanges: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    // oldest first
+    pub fn exchanges(&self) -> Vec<CapturedExchange> {
+        self.exchanges.lock().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.exchanges.lock().clear();
+    }
+}
+
+impl CaptureSink for RingBufferCapture {
+    fn capture(&self, exchange: CapturedExchange) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut exchanges = self.exchanges.lock();
+        if exchanges.len() == self.capacity {
+            exchanges.pop_front();
+        }
+        exchanges.push_back(exchange);
+    }
+}
+
+// appends one json object per line
+#[derive(Debug)]
+pub struct FileCapture {
+    file: Mutex<File>,
+}
+
+impl FileCapture {
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl CaptureSink for FileCapture {
+    // todo: writes block the executor, fine for debugging
+    fn capture(&self, exchange: CapturedExchange) {
+        let mut line = match serde_json::to_vec(&exchange) {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        line.push(b'\n');
+        let _ = self.file.lock().write_all(&line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn encode(value: Value) -> String {
+        base64::encode_config(value.to_string(), base64::URL_SAFE_NO_PAD)
+    }
+
+    #[test]
+    fn redacts_keys_and_signatures() {
+        let body = json!({
+            "protected": encode(json!({"alg": "ES384", "jwk": {"x": "secret"}, "url": "u"})),
+            "payload": encode(json!({"externalAccountBinding": {"signature": "mac"}})),
+            "signature": "sig",
+        });
+        let exchange = CapturedExchange::new("u".to_owned(), body.to_string().as_bytes());
+
+        assert_eq!(
+            exchange.protected,
+            Some(json!({"alg": "ES384", "jwk": REDACTED, "url": "u"}))
+        );
+        assert_eq!(
+            exchange.payload,
+            Some(json!({"externalAccountBinding": {"signature": REDACTED}}))
+        );
+
+        let body = json!({"protected": encode(json!({})), "payload": "", "signature": "sig"});
+        let exchange = CapturedExchange::new("u".to_owned(), body.to_string().as_bytes());
+        assert_eq!(exchange.payload, None);
+    }
+
+    #[test]
+    fn ring_buffer_drops_oldest() {
+        let capture = RingBufferCapture::new(2);
+        for url in ["a", "b", "c"] {
+            capture.capture(CapturedExchange::new(url.to_owned(), b""));
+        }
+
+        let urls: Vec<_> = capture
+            .exchanges()
+            .into_iter()
+            .map(|exchange| exchange.url)
+            .collect();
+        assert_eq!(urls, ["b", "c"]);
+    }
+}
@@ -0,0 +1,48 @@
+use x509_parser::certificate::X509Certificate;
+use x509_parser::parse_x509_certificate;
+
+// der of the first certificate of a pem chain
+pub(crate) fn leaf_der(chain: &[u8]) -> Option<Vec<u8>> {
+    rustls_pemfile::certs(&mut &*chain).ok()?.into_iter().next()
+}
+
+// the parsed leaf borrows its der so it is only lent to f, None if the chain doesn't start
+// with a parsable certificate
+pub(crate) fn leaf_certificate<R, F>(chain: &[u8], f: F) -> Option<R>
+where
+    F: FnOnce(&X509Certificate<'_>) -> Option<R>,
+{
+    let leaf = leaf_der(chain)?;
+    let (_, leaf) = parse_x509_certificate(&leaf).ok()?;
+
+    f(&leaf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pem(der: &[u8]) -> String {
+        format!(
+            "-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n",
+            base64::encode(der)
+        )
+    }
+
+    #[test]
+    fn leaf_of_chain() {
+        let der = |name: &str| {
+            let cert = rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+            cert.serialize_der().unwrap()
+        };
+        let leaf = der("leaf.example.com");
+        let chain = pem(&leaf) + &pem(&der("issuer.example.com"));
+
+        assert_eq!(leaf_der(chain.as_bytes()), Some(leaf));
+        let subject = leaf_certificate(chain.as_bytes(), |leaf| Some(leaf.subject().to_string()));
+        assert_eq!(subject.as_deref(), Some("CN=rcgen self signed cert"));
+
+        assert_eq!(leaf_der(b"not a chain"), None);
+        assert_eq!(leaf_certificate(b"not a chain", |_| Some(())), None);
+    }
+}
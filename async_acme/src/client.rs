@@ -0,0 +1,204 @@
+use acme_core::HttpClient;
+use async_trait::async_trait;
+use hyper::body::{Bytes, BytesMut, HttpBody};
+use hyper::{Body, Client, Request, Response, Uri};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::util::BoxCloneService;
+use tower::{Service, ServiceExt};
+
+use crate::runtime::timeout;
+use crate::server::Connect;
+use crate::service::{BoxLayer, SharedService};
+use crate::{BoxError, CertificateStream, HyperAcmeServerError, Runtime};
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timeouts {
+    // until the response headers arrive, includes connecting
+    pub(crate) response: Duration,
+    pub(crate) read: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            response: Duration::from_secs(30),
+            read: Duration::from_secs(30),
+        }
+    }
+}
+
+// hyper only knows the connect timeout of its HttpConnector, this covers any connector
+#[derive(Debug, Clone)]
+pub struct ConnectTimeout<C> {
+    inner: C,
+    timeout: Duration,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl<C> ConnectTimeout<C> {
+    pub(crate) fn new(inner: C, timeout: Duration, runtime: Arc<dyn Runtime>) -> Self {
+        Self {
+            inner,
+            timeout,
+            runtime,
+        }
+    }
+}
+
+impl<C> Service<Uri> for ConnectTimeout<C>
+where
+    C: Service<Uri>,
+    C::Error: Into<BoxError>,
+    C::Future: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<C::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connecting = timeout(&*self.runtime, self.timeout, self.inner.call(uri));
+        Box::pin(async move {
+            match connecting.await {
+                Ok(res) => res.map_err(Into::into),
+                Err(elapsed) => Err(elapsed.into()),
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HyperClient<C> {
+    service: SharedService,
+    connector: PhantomData<C>,
+    timeouts: Timeouts,
+    max_response_size: usize,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl<C: Connect> HyperClient<C> {
+    pub(crate) fn new(
+        connector: C::Timeout,
+        layers: &[BoxLayer],
+        timeouts: Timeouts,
+        max_response_size: usize,
+        runtime: Arc<dyn Runtime>,
+    ) -> Self {
+        let client = Client::builder().build(connector);
+        let service = BoxCloneService::new(client.map_err(BoxError::from));
+        // the first added layer is the outermost like with tower::ServiceBuilder
+        let service = layers
+            .iter()
+            .rev()
+            .fold(service, |service, layer| layer(service));
+
+        Self {
+            service: SharedService::new(service),
+            connector: PhantomData,
+            timeouts,
+            max_response_size,
+            runtime,
+        }
+    }
+}
+
+#[async_trait]
+impl<C: Connect> HttpClient for HyperClient<C> {
+    type Error = HyperAcmeServerError;
+
+    async fn request(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Self::Error> {
+        let (parts, body) = self.send(req).await?.into_parts();
+        let body = self.read(body).await?;
+
+        Ok(Response::from_parts(parts, body))
+    }
+}
+
+impl<C> HyperClient<C> {
+    // only waits for the response headers, the body is left to the caller
+    pub(crate) async fn send(
+        &self,
+        req: Request<Vec<u8>>,
+    ) -> Result<Response<Body>, HyperAcmeServerError> {
+        timeout(
+            &*self.runtime,
+            self.timeouts.response,
+            self.service.call(req.map(Body::from)),
+        )
+        .await?
+        .map_err(HyperAcmeServerError::from_service)
+    }
+
+    pub(crate) async fn read(&self, mut body: Body) -> Result<Vec<u8>, HyperAcmeServerError> {
+        let body = read_body(&mut body, self.max_response_size);
+        let body = timeout(&*self.runtime, self.timeouts.read, body).await??;
+
+        Ok(body.to_vec())
+    }
+
+    pub(crate) fn certificate_stream(&self, body: Body) -> CertificateStream {
+        CertificateStream::new(
+            body,
+            self.max_response_size,
+            self.timeouts.read,
+            self.runtime.clone(),
+        )
+    }
+}
+
+async fn read_body(body: &mut Body, limit: usize) -> Result<Bytes, HyperAcmeServerError> {
+    // fail early if the server announces a larger body
+    let size_hint = body.size_hint().lower() as usize;
+    if size_hint > limit {
+        return Err(HyperAcmeServerError::BodyTooLarge(limit));
+    }
+
+    let mut buf = BytesMut::with_capacity(size_hint);
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if buf.len() + chunk.len() > limit {
+            return Err(HyperAcmeServerError::BodyTooLarge(limit));
+        }
+        buf.extend_from_slice(&chunk);
+    }
+
+    Ok(buf.freeze())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Elapsed, TokioRuntime};
+
+    #[tokio::test]
+    async fn connect_timeout() {
+        let connector = tower::service_fn(|_: Uri| std::future::pending::<Result<(), BoxError>>());
+        let mut connector =
+            ConnectTimeout::new(connector, Duration::from_millis(10), Arc::new(TokioRuntime));
+        let err = connector
+            .call(Uri::from_static("https://ca"))
+            .await
+            .unwrap_err();
+        assert!(err.is::<Elapsed>());
+    }
+
+    #[tokio::test]
+    async fn read_body_respects_limit() {
+        let mut body = Body::from("a".repeat(16));
+        assert_eq!(read_body(&mut body, 16).await.unwrap().len(), 16);
+
+        let mut body = Body::from("a".repeat(17));
+        assert!(matches!(
+            read_body(&mut body, 16).await,
+            Err(HyperAcmeServerError::BodyTooLarge(16))
+        ));
+    }
+}
@@ -1,23 +1,22 @@
-use rcgen::DistinguishedName;
+use acme_core::Jwk;
+use rcgen::{DistinguishedName, RcgenError, SignatureAlgorithm};
 use ring::digest::{digest, Digest, SHA256};
 use ring::error::{KeyRejected, Unspecified};
 use ring::rand::SystemRandom;
 use ring::signature::{EcdsaKeyPair, Signature, ECDSA_P384_SHA384_FIXED_SIGNING};
-use rustls::PrivateKey;
-use serde::ser;
-use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
-use std::str;
 use thiserror::Error;
 
+mod pkcs8;
+
 pub trait Crypto: Sized {
     type Error: Error + 'static;
     type KeyPair: KeyPair<Error = Self::Error>;
     type Signature;
     type Thumbprint: AsRef<[u8]>;
-    type Certificate: Certificate<KeyPair = Self::KeyPair>;
+    type Certificate: Certificate<Error = Self::Error>;
 
     fn sign<T: AsRef<[u8]>>(
         &self,
@@ -28,7 +27,13 @@ pub trait Crypto: Sized {
 
     fn private_key(&self) -> Result<Self::KeyPair, Self::Error>;
 
-    fn certificate(&self, domain: String) -> Result<Self::Certificate, Self::Error>;
+    fn private_key_from_der(&self, der: &[u8]) -> Result<Self::KeyPair, Self::Error>;
+
+    fn certificate(
+        &self,
+        domains: Vec<String>,
+        algorithm: KeyAlgorithm,
+    ) -> Result<Self::Certificate, Self::Error>;
 }
 
 pub trait KeyPair {
@@ -45,10 +50,32 @@ pub trait KeyPair {
 pub trait Certificate: Sized {
     type Error: Error + 'static;
     type CSR: AsRef<[u8]>;
-    type KeyPair: KeyPair<Error = Self::Error>;
 
     fn csr_der(&self) -> Result<Self::CSR, Self::Error>;
-    fn key_pair(&self) -> &Self::KeyPair;
+    // pkcs8 der of the key the csr is signed with
+    fn private_key_der(&self) -> Vec<u8>;
+}
+
+// key of the issued certificate, the account key is always P-384
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    EcdsaP256,
+    EcdsaP384,
+}
+
+impl Default for KeyAlgorithm {
+    fn default() -> Self {
+        KeyAlgorithm::EcdsaP384
+    }
+}
+
+impl KeyAlgorithm {
+    fn rcgen(self) -> &'static SignatureAlgorithm {
+        match self {
+            KeyAlgorithm::EcdsaP256 => &rcgen::PKCS_ECDSA_P256_SHA256,
+            KeyAlgorithm::EcdsaP384 => &rcgen::PKCS_ECDSA_P384_SHA384,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -78,6 +105,14 @@ pub enum RingCryptoError {
     WrongCompressionFormat(u8),
     #[error("Invalid Base64 length {1} on public key part {0}")]
     InvalidBase64Len(XY, usize),
+    #[error("Private key has invalid length of {0}")]
+    InvalidPrivateKeyLength(usize),
+    #[error("Malformed private key")]
+    MalformedKey,
+    #[error("Unsupported key {0}")]
+    UnsupportedKey(String),
+    #[error(transparent)]
+    Rcgen(#[from] RcgenError),
 }
 
 impl From<Unspecified> for RingCryptoError {
@@ -129,35 +164,30 @@ impl<'a> Crypto for RingCrypto {
     fn private_key(&self) -> Result<Self::KeyPair, Self::Error> {
         let private_der =
             EcdsaKeyPair::generate_pkcs8(&ECDSA_P384_SHA384_FIXED_SIGNING, &self.random)?;
-        let inner =
-            EcdsaKeyPair::from_pkcs8(&ECDSA_P384_SHA384_FIXED_SIGNING, private_der.as_ref())?;
-        let public_key = RingKeyPair::export_public_key(&inner)?;
-
-        Ok(RingKeyPair {
-            private_der: PrivateKey(Vec::from(private_der.as_ref())),
-            inner,
-            public_key,
-        })
+        RingKeyPair::from_der(Vec::from(private_der.as_ref()))
     }
 
-    fn certificate(&self, domain: String) -> Result<Self::Certificate, Self::Error> {
-        let key_pair = self.private_key()?;
-        // todo: remove unwrap
-        let rcgen_key_pair = rcgen::KeyPair::from_der(key_pair.private_der.0.as_ref()).unwrap();
+    fn private_key_from_der(&self, der: &[u8]) -> Result<Self::KeyPair, Self::Error> {
+        RingKeyPair::from_der(der.to_vec())
+    }
 
-        let mut params = rcgen::CertificateParams::new([domain]);
+    fn certificate(
+        &self,
+        domains: Vec<String>,
+        algorithm: KeyAlgorithm,
+    ) -> Result<Self::Certificate, Self::Error> {
+        let mut params = rcgen::CertificateParams::new(domains);
         params.distinguished_name = DistinguishedName::new();
-        params.alg = &rcgen::PKCS_ECDSA_P384_SHA384;
-        params.key_pair = Some(rcgen_key_pair);
+        // rcgen generates a new key pair for the algorithm
+        params.alg = algorithm.rcgen();
 
-        // todo: remove unwrap
-        let cert = rcgen::Certificate::from_params(params).unwrap();
-        Ok(RingCertificate { key_pair, cert })
+        let cert = rcgen::Certificate::from_params(params)?;
+        Ok(RingCertificate { cert })
     }
 }
 
 pub struct RingKeyPair {
-    private_der: PrivateKey,
+    private_der: Vec<u8>,
     inner: EcdsaKeyPair,
     public_key: RingPublicKey,
 }
@@ -171,6 +201,35 @@ impl Debug for RingKeyPair {
 }
 
 impl RingKeyPair {
+    pub(crate) fn from_der(private_der: Vec<u8>) -> Result<Self, RingCryptoError> {
+        let inner = EcdsaKeyPair::from_pkcs8(&ECDSA_P384_SHA384_FIXED_SIGNING, &private_der)?;
+        let public_key = RingKeyPair::export_public_key(&inner)?;
+
+        Ok(RingKeyPair {
+            private_der,
+            inner,
+            public_key,
+        })
+    }
+
+    // takes the raw private scalar and uncompressed public point of a P-384 key
+    pub(crate) fn from_parts(private: &[u8], public: &[u8]) -> Result<Self, RingCryptoError> {
+        RingKeyPair::from_der(pkcs8::p384_from_parts(private, public)?)
+    }
+
+    pub(crate) fn from_sec1(der: &[u8]) -> Result<Self, RingCryptoError> {
+        RingKeyPair::from_der(pkcs8::p384_from_sec1(der)?)
+    }
+
+    pub(crate) fn private_scalar(&self) -> Result<&[u8], RingCryptoError> {
+        pkcs8::p384_private_scalar(self.private_der.as_ref())
+    }
+
+    // uncompressed point including the leading compression format byte
+    pub(crate) fn public_point(&self) -> &[u8] {
+        <EcdsaKeyPair as ring::signature::KeyPair>::public_key(&self.inner).as_ref()
+    }
+
     fn export_public_key(key_pair: &EcdsaKeyPair) -> Result<RingPublicKey, RingCryptoError> {
         let public = <EcdsaKeyPair as ring::signature::KeyPair>::public_key(&key_pair).as_ref();
         match public.len() {
@@ -220,7 +279,7 @@ impl KeyPair for RingKeyPair {
     }
 
     fn as_der(&self) -> &[u8] {
-        self.private_der.0.as_ref()
+        self.private_der.as_ref()
     }
 }
 
@@ -230,43 +289,35 @@ pub struct RingPublicKey {
     y: [u8; 64],
 }
 
+impl RingPublicKey {
+    // x and y are stored base64url encoded
+    pub(crate) fn jwk(&self) -> Jwk {
+        let x = String::from_utf8_lossy(&self.x);
+        let y = String::from_utf8_lossy(&self.y);
+        Jwk::new("P-384", "EC", &*x, &*y)
+    }
+}
+
 impl Serialize for RingPublicKey {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut serializer = serializer.serialize_struct("RingKeyPair", 4)?;
-
-        serializer.serialize_field("crv", "P-384")?;
-        serializer.serialize_field("kty", "EC")?;
-
-        match str::from_utf8(&self.x) {
-            Ok(x) => serializer.serialize_field("x", x)?,
-            Err(e) => return Err(ser::Error::custom(e)),
-        };
-        match str::from_utf8(&self.y) {
-            Ok(y) => serializer.serialize_field("y", y)?,
-            Err(e) => return Err(ser::Error::custom(e)),
-        };
-
-        serializer.end()
+        self.jwk().serialize(serializer)
     }
 }
 
 pub struct RingCertificate {
     cert: rcgen::Certificate,
-    key_pair: RingKeyPair,
 }
 
 impl Certificate for RingCertificate {
     type Error = RingCryptoError;
     type CSR = Vec<u8>;
-    type KeyPair = RingKeyPair;
 
     fn csr_der(&self) -> Result<Self::CSR, Self::Error> {
-        // todo: remove unwrap
-        Ok(self.cert.serialize_request_der().unwrap())
+        Ok(self.cert.serialize_request_der()?)
     }
 
-    fn key_pair(&self) -> &Self::KeyPair {
-        &self.key_pair
+    fn private_key_der(&self) -> Vec<u8> {
+        self.cert.serialize_private_key_der()
     }
 }
 
@@ -281,4 +332,32 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn should_load_private_key_from_der() -> Result<(), RingCryptoError> {
+        let ring_crypto = RingCrypto::new();
+        let key_pair = ring_crypto.private_key()?;
+        let loaded = ring_crypto.private_key_from_der(key_pair.as_der())?;
+
+        assert_eq!(key_pair.public_key().x, loaded.public_key().x);
+        assert_eq!(key_pair.public_key().y, loaded.public_key().y);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_generate_certificate_key() -> Result<(), RingCryptoError> {
+        let ring_crypto = RingCrypto::new();
+        let domains = vec!["example.com".to_string()];
+
+        let p256 = ring_crypto.certificate(domains.clone(), KeyAlgorithm::EcdsaP256)?;
+        let p256 = rcgen::KeyPair::from_der(&p256.private_key_der())?;
+        assert!(p256.is_compatible(&rcgen::PKCS_ECDSA_P256_SHA256));
+
+        let p384 = ring_crypto.certificate(domains, KeyAlgorithm::default())?;
+        let p384 = rcgen::KeyPair::from_der(&p384.private_key_der())?;
+        assert!(p384.is_compatible(&rcgen::PKCS_ECDSA_P384_SHA384));
+
+        Ok(())
+    }
 }
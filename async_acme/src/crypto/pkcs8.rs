@@ -0,0 +1,156 @@
+use super::RingCryptoError;
+
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const BIT_STRING: u8 = 0x03;
+const OID: u8 = 0x06;
+const PARAMETERS: u8 = 0xa0;
+const PUBLIC_KEY: u8 = 0xa1;
+
+const SECP384R1: [u8; 5] = [0x2b, 0x81, 0x04, 0x00, 0x22];
+
+// same PKCS#8 v1 layout ring generates for P-384 keys
+// private scalar follows the prefix, the public point follows the public prefix
+const P384_PREFIX: [u8; 35] = [
+    0x30, 0x81, 0xb6, 0x02, 0x01, 0x00, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02,
+    0x01, 0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22, 0x04, 0x81, 0x9e, 0x30, 0x81, 0x9b, 0x02, 0x01,
+    0x01, 0x04, 0x30,
+];
+const P384_PUBLIC_PREFIX: [u8; 5] = [0xa1, 0x64, 0x03, 0x62, 0x00];
+
+pub(super) const P384_SCALAR_LEN: usize = 48;
+pub(super) const P384_PUBLIC_LEN: usize = 97;
+
+pub(super) fn p384_from_parts(private: &[u8], public: &[u8]) -> Result<Vec<u8>, RingCryptoError> {
+    if private.len() != P384_SCALAR_LEN {
+        return Err(RingCryptoError::InvalidPrivateKeyLength(private.len()));
+    }
+    if public.len() != P384_PUBLIC_LEN {
+        return Err(RingCryptoError::InvalidPublicKeyLength(public.len()));
+    }
+    match public[0] {
+        4 => {}
+        compression_format => {
+            return Err(RingCryptoError::WrongCompressionFormat(compression_format))
+        }
+    }
+
+    let mut der = Vec::with_capacity(
+        P384_PREFIX.len() + P384_SCALAR_LEN + P384_PUBLIC_PREFIX.len() + P384_PUBLIC_LEN,
+    );
+    der.extend_from_slice(&P384_PREFIX);
+    der.extend_from_slice(private);
+    der.extend_from_slice(&P384_PUBLIC_PREFIX);
+    der.extend_from_slice(public);
+
+    Ok(der)
+}
+
+// converts a SEC1 ECPrivateKey (openssl "EC PRIVATE KEY") into PKCS#8
+pub(super) fn p384_from_sec1(der: &[u8]) -> Result<Vec<u8>, RingCryptoError> {
+    let (ec_private_key, _) = read(der, SEQUENCE)?;
+    let (version, rest) = read(ec_private_key, INTEGER)?;
+    if version != [1] {
+        return Err(RingCryptoError::MalformedKey);
+    }
+    let (private, mut rest) = read(rest, OCTET_STRING)?;
+
+    let mut public = None;
+    while !rest.is_empty() {
+        let (tag, value, next) = read_any(rest)?;
+        match tag {
+            PARAMETERS => {
+                let (oid, _) = read(value, OID)?;
+                if oid != SECP384R1 {
+                    return Err(RingCryptoError::UnsupportedKey("EC curve".into()));
+                }
+            }
+            PUBLIC_KEY => match read(value, BIT_STRING)?.0.split_first() {
+                // no unused bits
+                Some((0, point)) => public = Some(point),
+                _ => return Err(RingCryptoError::MalformedKey),
+            },
+            _ => return Err(RingCryptoError::MalformedKey),
+        }
+        rest = next;
+    }
+
+    let public = public.ok_or(RingCryptoError::MalformedKey)?;
+    p384_from_parts(private, public)
+}
+
+pub(super) fn p384_private_scalar(der: &[u8]) -> Result<&[u8], RingCryptoError> {
+    let (private_key_info, _) = read(der, SEQUENCE)?;
+    let (_, rest) = read(private_key_info, INTEGER)?;
+    // algorithm identifier
+    let (_, rest) = read(rest, SEQUENCE)?;
+    let (ec_private_key, _) = read(rest, OCTET_STRING)?;
+
+    let (ec_private_key, _) = read(ec_private_key, SEQUENCE)?;
+    let (_, rest) = read(ec_private_key, INTEGER)?;
+    let (private, _) = read(rest, OCTET_STRING)?;
+
+    Ok(private)
+}
+
+fn read(input: &[u8], expected: u8) -> Result<(&[u8], &[u8]), RingCryptoError> {
+    match read_any(input)? {
+        (tag, value, rest) if tag == expected => Ok((value, rest)),
+        _ => Err(RingCryptoError::MalformedKey),
+    }
+}
+
+// only supports the definite length forms which are used by keys
+fn read_any(input: &[u8]) -> Result<(u8, &[u8], &[u8]), RingCryptoError> {
+    let (&tag, input) = input.split_first().ok_or(RingCryptoError::MalformedKey)?;
+    let (&len, input) = input.split_first().ok_or(RingCryptoError::MalformedKey)?;
+
+    let (len, input) = match len {
+        len if len < 0x80 => (len as usize, input),
+        0x81 => match input.split_first() {
+            Some((&len, input)) => (len as usize, input),
+            None => return Err(RingCryptoError::MalformedKey),
+        },
+        0x82 if input.len() >= 2 => ((input[0] as usize) << 8 | input[1] as usize, &input[2..]),
+        _ => return Err(RingCryptoError::MalformedKey),
+    };
+
+    if input.len() < len {
+        return Err(RingCryptoError::MalformedKey);
+    }
+    let (value, rest) = input.split_at(len);
+
+    Ok((tag, value, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Crypto, KeyPair, RingCrypto};
+
+    #[test]
+    fn should_roundtrip_parts_and_sec1() -> Result<(), RingCryptoError> {
+        let key_pair = RingCrypto::new().private_key()?;
+        let der = key_pair.as_der();
+
+        let private = &der[P384_PREFIX.len()..P384_PREFIX.len() + P384_SCALAR_LEN];
+        let public = &der[der.len() - P384_PUBLIC_LEN..];
+        assert_eq!(der, p384_from_parts(private, public)?.as_slice());
+        assert_eq!(private, p384_private_scalar(der)?);
+
+        // the ECPrivateKey is embedded in the PKCS#8 octet string
+        let sec1 = &der[27..];
+        assert_eq!(der, p384_from_sec1(sec1)?.as_slice());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_truncated_key() {
+        assert!(matches!(
+            p384_from_sec1(&[0x30, 0x05, 0x02, 0x01]),
+            Err(RingCryptoError::MalformedKey)
+        ));
+    }
+}
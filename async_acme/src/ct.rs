@@ -0,0 +1,264 @@
+use futures_util::future::AbortHandle;
+use hyper::client::HttpConnector;
+use hyper::http::uri::InvalidUri;
+use hyper::{body, Client, Uri};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::runtime::{default_runtime, spawn_abortable, timeout};
+use crate::{CertificateStore, Persist, Runtime, StoreError};
+
+const CRT_SH: &str = "https://crt.sh";
+const CT_TIMEOUT: Duration = Duration::from_secs(60);
+const INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Error)]
+pub enum CtError {
+    #[error(transparent)]
+    Hyper(#[from] hyper::Error),
+    #[error(transparent)]
+    InvalidUri(#[from] InvalidUri),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error("Certificate search timed out")]
+    Timeout,
+    #[error("Certificate search responded with {0}")]
+    Status(u16),
+}
+
+// entry of the crt.sh json api
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CtCertificate {
+    pub id: u64,
+    pub issuer_name: String,
+    // newline separated
+    pub name_value: String,
+    pub serial_number: String,
+    pub not_before: String,
+    pub not_after: String,
+}
+
+// a logged certificate for domain that wasn't issued through the store
+#[derive(Debug, Clone)]
+pub struct CtAlert {
+    pub domain: String,
+    pub certificate: CtCertificate,
+}
+
+// polls crt.sh for unexpired certificates of the domains, every certificate whose serial isn't in
+// the history of a stored SAN set containing the domain and whose issuer isn't allowed is reported
+// once
+#[derive(Debug)]
+pub struct CtMonitor<P> {
+    client: Client<HttpsConnector<HttpConnector>>,
+    url: String,
+    store: CertificateStore<P>,
+    domains: Vec<String>,
+    allowed_issuers: Vec<String>,
+    interval: Duration,
+    reported: Mutex<HashSet<u64>>,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl<P: Persist + Send + Sync> CtMonitor<P> {
+    pub fn new(store: CertificateStore<P>) -> Self {
+        let connector = HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_only()
+            .enable_http1()
+            .build();
+
+        Self {
+            client: Client::builder().build(connector),
+            url: CRT_SH.to_owned(),
+            store,
+            domains: Vec::new(),
+            allowed_issuers: Vec::new(),
+            interval: INTERVAL,
+            reported: Mutex::new(HashSet::new()),
+            runtime: default_runtime(),
+        }
+    }
+
+    // e.g. a self hosted crt.sh
+    pub fn with_url<T: Into<String>>(mut self, url: T) -> Self {
+        self.url = url.into().trim_end_matches('/').to_owned();
+        self
+    }
+
+    pub fn domain<T: Into<String>>(mut self, domain: T) -> Self {
+        self.domains.push(domain.into());
+        self
+    }
+
+    // certificates of issuers whose name contains issuer are expected, e.g. another CA used
+    // outside of this crate
+    pub fn allowed_issuer<T: Into<String>>(mut self, issuer: T) -> Self {
+        self.allowed_issuers.push(issuer.into());
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    // runs the search timeout and spawn_ct_monitor, the hyper client still needs tokio
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.runtime = Arc::new(runtime);
+        self
+    }
+
+    // returns the certificates not reported by earlier checks
+    pub async fn check(&self) -> Result<Vec<CtAlert>, CtError> {
+        let mut alerts = Vec::new();
+        for domain in &self.domains {
+            let known: HashSet<String> = self
+                .store
+                .domain_history(domain)
+                .await?
+                .into_iter()
+                .map(|issuance| normalize_serial(&issuance.serial))
+                .collect();
+
+            for certificate in self.search(domain).await? {
+                let expected = known.contains(&normalize_serial(&certificate.serial_number))
+                    || self
+                        .allowed_issuers
+                        .iter()
+                        .any(|issuer| certificate.issuer_name.contains(issuer.as_str()));
+                if expected || !self.reported.lock().insert(certificate.id) {
+                    continue;
+                }
+
+                alerts.push(CtAlert {
+                    domain: domain.clone(),
+                    certificate,
+                });
+            }
+        }
+
+        Ok(alerts)
+    }
+
+    async fn search(&self, domain: &str) -> Result<Vec<CtCertificate>, CtError> {
+        let uri: Uri = format!("{}/?q={}&output=json&exclude=expired", self.url, domain).parse()?;
+
+        let (status, body) = timeout(&*self.runtime, CT_TIMEOUT, async {
+            let res = self.client.get(uri).await?;
+            let status = res.status();
+            Ok::<_, hyper::Error>((status, body::to_bytes(res.into_body()).await?))
+        })
+        .await
+        .map_err(|_| CtError::Timeout)??;
+
+        if !status.is_success() {
+            return Err(CtError::Status(status.as_u16()));
+        }
+        let mut certificates: Vec<CtCertificate> = serde_json::from_slice(&body)?;
+
+        // the precertificate and the certificate share a serial
+        let mut serials = HashSet::new();
+        certificates
+            .retain(|certificate| serials.insert(normalize_serial(&certificate.serial_number)));
+        Ok(certificates)
+    }
+}
+
+#[derive(Debug)]
+pub struct CtMonitorTask {
+    handle: AbortHandle,
+}
+
+impl CtMonitorTask {
+    // dropping the handle keeps the task running
+    pub fn abort(&self) {
+        self.handle.abort();
+    }
+}
+
+// checks every interval on the runtime of monitor, failed checks are retried after the
+// interval
+pub fn spawn_ct_monitor<P>(
+    monitor: Arc<CtMonitor<P>>,
+) -> (CtMonitorTask, mpsc::UnboundedReceiver<CtAlert>)
+where
+    P: Persist + Send + Sync + 'static,
+{
+    let (alerts, rx) = mpsc::unbounded_channel();
+
+    let runtime = monitor.runtime.clone();
+    let handle = spawn_abortable(&*runtime, async move {
+        loop {
+            match monitor.check().await {
+                Ok(found) => {
+                    for alert in found {
+                        warn!(domain = %alert.domain, id = alert.certificate.id, issuer = %alert.certificate.issuer_name, "unexpected certificate logged");
+                        let _ = alerts.send(alert);
+                    }
+                }
+                Err(err) => warn!(error = %err, "certificate transparency check failed"),
+            }
+            monitor.runtime.sleep(monitor.interval).await;
+        }
+    });
+
+    (CtMonitorTask { handle }, rx)
+}
+
+// crt.sh uses plain hex while the store keeps the colon separated form of x509-parser
+fn normalize_serial(serial: &str) -> String {
+    let serial: String = serial
+        .chars()
+        .filter(|c| *c != ':')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    match serial.trim_start_matches('0') {
+        "" => "0".to_owned(),
+        serial => serial.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serial_formats() {
+        assert_eq!(normalize_serial("00:a1:B2:03"), normalize_serial("A1B203"));
+        assert_eq!(normalize_serial("00"), "0");
+    }
+
+    #[test]
+    fn crt_sh_entry() {
+        let json = r#"[{
+            "issuer_ca_id": 183267,
+            "issuer_name": "C=US, O=Let's Encrypt, CN=R3",
+            "common_name": "example.com",
+            "name_value": "example.com\nwww.example.com",
+            "id": 8009834711,
+            "entry_timestamp": "2022-11-20T12:00:00.123",
+            "not_before": "2022-11-20T11:00:00",
+            "not_after": "2023-02-18T11:00:00",
+            "serial_number": "03a1b2c3d4",
+            "result_count": 3
+        }]"#;
+
+        let certificates: Vec<CtCertificate> = serde_json::from_str(json).unwrap();
+        assert_eq!(certificates[0].id, 8009834711);
+        assert_eq!(
+            normalize_serial(&certificates[0].serial_number),
+            "3a1b2c3d4"
+        );
+    }
+}
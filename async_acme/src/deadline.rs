@@ -0,0 +1,63 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::runtime::{timeout, Timeout};
+use crate::{DirectoryError, Runtime};
+
+// bounds a whole flow like Account::issue including polling, e.g.
+// account.issue(domains, &solver).with_deadline(&TokioRuntime, Duration::from_secs(300)).
+// the flow is dropped once the deadline hits so presented challenges aren't cleaned up,
+// use a CancellationToken on the account to abort cleanly
+pub trait DeadlineExt: Future + Sized {
+    fn with_deadline(self, runtime: &dyn Runtime, deadline: Duration) -> Deadline<Self>;
+}
+
+impl<F: Future> DeadlineExt for F {
+    fn with_deadline(self, runtime: &dyn Runtime, deadline: Duration) -> Deadline<Self> {
+        Deadline {
+            inner: timeout(runtime, deadline, self),
+            deadline,
+        }
+    }
+}
+
+pub struct Deadline<F> {
+    inner: Timeout<F>,
+    deadline: Duration,
+}
+
+impl<F, T> Future for Deadline<F>
+where
+    F: Future<Output = Result<T, DirectoryError>>,
+{
+    type Output = Result<T, DirectoryError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let deadline = self.deadline;
+        Pin::new(&mut self.inner)
+            .poll(cx)
+            .map(|res| res.unwrap_or_else(|_| Err(DirectoryError::TimedOut(deadline))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokioRuntime;
+    use std::future;
+
+    #[tokio::test]
+    async fn times_out() {
+        let res = future::pending::<Result<(), DirectoryError>>()
+            .with_deadline(&TokioRuntime, Duration::from_millis(1))
+            .await;
+        assert!(matches!(res, Err(DirectoryError::TimedOut(_))));
+
+        let res = future::ready(Ok::<_, DirectoryError>(()))
+            .with_deadline(&TokioRuntime, Duration::from_millis(1))
+            .await;
+        assert!(res.is_ok());
+    }
+}
@@ -0,0 +1,133 @@
+use acme_core::ApiChallengeType;
+use async_trait::async_trait;
+use hyper::http::Method;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug, Formatter};
+
+use crate::rest::RestClient;
+use crate::solver::TxtRecords;
+use crate::{BoxError, ChallengeProof, Runtime, Solver};
+
+const DESEC_API: &str = "https://desec.io/api/v1";
+const TTL: u32 = 60;
+
+#[derive(Deserialize)]
+struct ApiDomain {
+    name: String,
+    minimum_ttl: u32,
+}
+
+#[derive(Serialize)]
+struct ApiRRSet<'a> {
+    subname: &'a str,
+    #[serde(rename = "type")]
+    type_val: &'static str,
+    ttl: u32,
+    records: Vec<String>,
+}
+
+// dns-01 solver for domains hosted on desec.io
+pub struct DesecSolver {
+    client: RestClient,
+    records: TxtRecords,
+}
+
+impl DesecSolver {
+    pub fn new(token: &str) -> Result<Self, BoxError> {
+        Self::with_url(DESEC_API, token)
+    }
+
+    pub fn with_url(url: &str, token: &str) -> Result<Self, BoxError> {
+        Ok(Self {
+            client: RestClient::new(url, "authorization", &format!("Token {}", token))?,
+            records: TxtRecords::default(),
+        })
+    }
+
+    // runs the request timeouts, the hyper client still needs tokio
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.client.runtime(runtime);
+        self
+    }
+
+    async fn domain(&self, name: &str) -> Result<ApiDomain, BoxError> {
+        let domains: Vec<ApiDomain> = self
+            .client
+            .get(&format!("/domains/?owns_qname={}", name))
+            .await?;
+
+        domains
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("no domain found for {}", name).into())
+    }
+
+    // an empty rrset deletes it
+    async fn put(&self, name: &str, values: Vec<String>) -> Result<(), BoxError> {
+        let domain = self.domain(name).await?;
+        let rrset = ApiRRSet {
+            subname: subname(name, &domain.name),
+            type_val: "TXT",
+            // desec rejects ttls below the minimum of the domain
+            ttl: TTL.max(domain.minimum_ttl),
+            records: values
+                .into_iter()
+                .map(|value| format!("\"{}\"", value))
+                .collect(),
+        };
+
+        let path = format!("/domains/{}/rrsets/", domain.name);
+        self.client.send(Method::PUT, &path, Some(&[rrset])).await?;
+        Ok(())
+    }
+}
+
+impl Debug for DesecSolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DesecSolver").finish()
+    }
+}
+
+fn subname<'a>(name: &'a str, domain: &str) -> &'a str {
+    name.strip_suffix(domain)
+        .map(|subname| subname.trim_end_matches('.'))
+        .unwrap_or(name)
+}
+
+#[async_trait]
+impl Solver for DesecSolver {
+    fn challenge_type(&self) -> ApiChallengeType {
+        ApiChallengeType::DNS
+    }
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let name = proof.dns_name();
+        let values = self.records.add(&name, proof.dns_value());
+
+        self.put(&name, values).await
+    }
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let name = proof.dns_name();
+        let values = self.records.remove(&name, &proof.dns_value());
+
+        self.put(&name, values).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subname_of_domain() {
+        assert_eq!(
+            subname("_acme-challenge.www.example.com", "example.com"),
+            "_acme-challenge.www"
+        );
+        assert_eq!(
+            subname("_acme-challenge.example.com", "example.com"),
+            "_acme-challenge"
+        );
+    }
+}
@@ -1,27 +1,62 @@
 use acme_core::{
-    AcmeServer, AcmeServerBuilder, AcmeServerExt, ApiAccount, ApiAuthorization, ApiChallenge,
-    ApiChallengeType, ApiIdentifier, ApiIdentifierType, ApiNewOrder, ApiOrder,
-    ApiOrderFinalization, DynAcmeServer, ErrorWrapper, Payload, SignedRequest, Uri,
+    AcmeResponse, AcmeServer, AcmeServerBuilder, AcmeServerExt, ApiAccount, ApiAccountStatus,
+    ApiAuthorization, ApiAuthorizationStatus, ApiChallenge, ApiChallengeResponse,
+    ApiChallengeStatus, ApiChallengeType, ApiError, ApiErrorType, ApiIdentifier, ApiIdentifierType,
+    ApiKeyChange, ApiNewOrder, ApiOrder, ApiOrderFinalization, ApiOrderStatus, ApiRenewalInfo,
+    ApiRevocation, ApiRevocationReason, Contact, DynAcmeServer, ErrorWrapper, InvalidContact, Jwk,
+    KeyType, Kid, NoExternalAccountBinding, NoNonce, Nonce, NonceType, PostAsGet, Protected,
+    RequestImpl, SignError, Signer, Uri,
 };
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
 use hyper::client::HttpConnector;
+use hyper::Client;
+#[cfg(feature = "rustls-tls")]
 use hyper_rustls::HttpsConnectorBuilder;
-use serde::ser::SerializeStruct;
-use serde::{Serialize, Serializer};
+use ring::digest::{digest, SHA256};
+use ring::hmac;
+use serde::Serialize;
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt::Debug;
+use std::future::Future;
 use std::marker::PhantomData;
 use std::mem;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::sync::{Mutex, Semaphore, SemaphorePermit};
+use tracing::warn;
 
+use crate::ari;
+use crate::chain::leaf_der;
+use crate::credentials::{Credentials, PrivateJwk};
 use crate::crypto::{
-    Certificate, Crypto, KeyPair, RingCrypto, RingCryptoError, RingKeyPair, RingPublicKey,
+    Certificate, Crypto, KeyAlgorithm, KeyPair, RingCrypto, RingCryptoError, RingKeyPair,
 };
-use crate::{HyperAcmeServer, HyperAcmeServerBuilder};
+use crate::events::Events;
+use crate::runtime::default_runtime;
+use crate::server::CONNECT_TIMEOUT;
+use crate::server::HAPPY_EYEBALLS_TIMEOUT;
+#[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+use crate::tls::TlsConfig;
+use crate::{
+    AcmeEvents, BoxError, ByteStream, CancellationToken, ChallengeProof, ErrorClass,
+    ExponentialBackoff, FixedPoll, HyperAcmeServerBuilder, HyperAcmeServerError, ImportError,
+    PollStrategy, RateLimiter, Resolve, Resolver, RetryAfterPoll, RetryPolicy, Solver, SolverChain,
+};
+
+#[cfg(feature = "rustls-tls")]
+type HttpsConnector = hyper_rustls::HttpsConnector<HttpConnector<Resolver>>;
+#[cfg(feature = "native-tls")]
+type NativeTlsConnector = hyper_tls::HttpsConnector<HttpConnector<Resolver>>;
 
-type HttpsConnector = hyper_rustls::HttpsConnector<HttpConnector>;
+const CONCURRENCY: usize = 8;
+const VALIDATION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const VALIDATION_ATTEMPTS: usize = 30;
 
 mod private {
     use super::*;
@@ -31,6 +66,12 @@ mod private {
     impl Sealed for NeedsEndpoint {}
     impl Sealed for Finished {}
     impl Sealed for Http {}
+    impl Sealed for TlsAlpn {}
+    impl Sealed for Dns {}
+    impl Sealed for Any {}
+    impl Sealed for Pending {}
+    impl Sealed for Ready {}
+    impl Sealed for Valid {}
 }
 
 pub trait DirectoryBuilderConfigState: private::Sealed {}
@@ -48,56 +89,330 @@ impl DirectoryBuilderConfigState for NeedsEndpoint {}
 pub struct DirectoryBuilder<T: DirectoryBuilderConfigState, S = ()> {
     state: PhantomData<T>,
     builder: Option<S>,
+    limits: Limits,
+    events: Events,
+    // only used by the default server
+    connector: ConnectorConfig,
+}
+
+// a configuration can be cloned to build directories for several endpoints
+impl<T: DirectoryBuilderConfigState, S: Clone> Clone for DirectoryBuilder<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: PhantomData,
+            builder: self.builder.clone(),
+            limits: self.limits.clone(),
+            events: self.events.clone(),
+            connector: self.connector.clone(),
+        }
+    }
+}
+
+// read by the default servers and the plain http client of the directory
+#[derive(Clone, Default)]
+struct ConnectorConfig {
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    tls: TlsConfig,
+    resolver: Resolver,
+    local_address: Option<IpAddr>,
+    connect_timeout: Option<Duration>,
+}
+
+impl ConnectorConfig {
+    fn connect_timeout(&self) -> Duration {
+        self.connect_timeout.unwrap_or(CONNECT_TIMEOUT)
+    }
+
+    fn http_connector(&self) -> HttpConnector<Resolver> {
+        let mut http = HttpConnector::new_with_resolver(self.resolver.clone());
+        http.set_connect_timeout(Some(self.connect_timeout()));
+        http.set_happy_eyeballs_timeout(Some(HAPPY_EYEBALLS_TIMEOUT));
+        http.set_local_address(self.local_address);
+
+        http
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Limits {
+    new_order: Option<RateLimiter>,
+    new_account: Option<RateLimiter>,
+    // requests in flight when fetching all authorizations of an order
+    concurrency: usize,
+    poll: Arc<dyn PollStrategy>,
+    // signed requests in flight
+    requests: Option<Arc<Semaphore>>,
+    // orders issued at the same time by Account::issue and the CertificateManager
+    orders: Option<Arc<Semaphore>>,
+    // signed requests which failed transiently or with badNonce
+    retry: Arc<dyn RetryPolicy>,
+    // sleeps between polls and retries
+    runtime: Arc<dyn Runtime>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            new_order: None,
+            new_account: None,
+            concurrency: CONCURRENCY,
+            poll: Arc::new(RetryAfterPoll::new(FixedPoll::new(
+                VALIDATION_POLL_INTERVAL,
+            ))),
+            requests: None,
+            orders: None,
+            retry: Arc::new(ExponentialBackoff::default()),
+            runtime: default_runtime(),
+        }
+    }
+}
+
+impl<T: DirectoryBuilderConfigState, S> DirectoryBuilder<T, S> {
+    pub fn new_order_limit(mut self, limiter: RateLimiter) -> Self {
+        self.limits.new_order = Some(limiter);
+        self
+    }
+
+    pub fn new_account_limit(mut self, limiter: RateLimiter) -> Self {
+        self.limits.new_account = Some(limiter);
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.limits.concurrency = concurrency.max(1);
+        self
+    }
+
+    // shared by all clones of the directory and all accounts
+    pub fn request_concurrency(mut self, requests: usize) -> Self {
+        self.limits.requests = Some(Arc::new(Semaphore::new(requests.max(1))));
+        self
+    }
+
+    // orders waiting for a permit are only created once one is free
+    pub fn order_concurrency(mut self, orders: usize) -> Self {
+        self.limits.orders = Some(Arc::new(Semaphore::new(orders.max(1))));
+        self
+    }
+
+    pub fn events<E: AcmeEvents>(mut self, events: E) -> Self {
+        self.events = Events::new(events);
+        self
+    }
+
+    // retried signed requests are signed again with a fresh nonce
+    pub fn retry<R: RetryPolicy + 'static>(mut self, retry: R) -> Self {
+        self.limits.retry = Arc::new(retry);
+        self
+    }
+
+    // used for polling and retry delays, the hyper client of the default server still needs tokio
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.limits.runtime = Arc::new(runtime);
+        self
+    }
+
+    // delays between refetching orders and authorizations while waiting on the CA
+    pub fn poll_strategy<P: PollStrategy + 'static>(mut self, poll: P) -> Self {
+        self.limits.poll = Arc::new(poll);
+        self
+    }
 }
 
 impl DirectoryBuilder<NeedsServer, ()> {
+    // pem or der encoded, used by the default server
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    pub fn add_root_certificate<T: AsRef<[u8]>>(mut self, cert: T) -> Result<Self, DirectoryError> {
+        match self.connector.tls.add_root_certificate(cert.as_ref()) {
+            true => Ok(self),
+            false => Err(DirectoryError::InvalidRootCertificate),
+        }
+    }
+
+    // replaces the system resolver of the default server
+    pub fn resolver<R: Resolve>(mut self, resolver: R) -> Self {
+        self.connector.resolver = Resolver::new(resolver);
+        self
+    }
+
+    // source address of all connections, useful on multi homed hosts
+    pub fn local_address(mut self, local_address: IpAddr) -> Self {
+        self.connector.local_address = Some(local_address);
+        self
+    }
+
+    // includes the tls handshake, 10 seconds by default
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connector.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    // negotiated using ALPN, falls back to http1
+    #[cfg(feature = "rustls-tls")]
+    pub fn http2(mut self) -> Self {
+        self.connector.tls.http2 = true;
+        self
+    }
+
+    // http/1.1 and h2 are the only supported protocols, h2 is offered as well if http2 is enabled
+    #[cfg(feature = "rustls-tls")]
+    pub fn alpn_protocols(mut self, alpn_protocols: Vec<Vec<u8>>) -> Result<Self, DirectoryError> {
+        match self.connector.tls.alpn_protocols(alpn_protocols) {
+            true => Ok(self),
+            false => Err(DirectoryError::UnsupportedAlpnProtocol),
+        }
+    }
+
+    // writes the tls secrets to SSLKEYLOGFILE for wireshark
+    #[cfg(feature = "rustls-tls")]
+    pub fn key_log(mut self) -> Self {
+        self.connector.tls.key_log();
+        self
+    }
+
+    // only for pebble or other test CAs with self signed certificates
+    #[cfg(all(
+        feature = "danger-accept-invalid-certs",
+        any(feature = "rustls-tls", feature = "native-tls")
+    ))]
+    pub fn danger_accept_invalid_certs(mut self) -> Self {
+        self.connector.tls.danger_accept_invalid_certs();
+        self
+    }
+
     pub fn server<S: AcmeServerBuilder>(self, builder: S) -> DirectoryBuilder<NeedsEndpoint, S> {
         DirectoryBuilder {
             state: PhantomData,
             builder: Some(builder),
+            limits: self.limits,
+            events: self.events,
+            connector: self.connector,
         }
     }
 
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    fn http_connector(&self) -> HttpConnector<Resolver> {
+        let mut http = self.connector.http_connector();
+        // the https connector takes care of rejecting plain http
+        http.enforce_http(false);
+
+        http
+    }
+
+    #[cfg(feature = "rustls-tls")]
     pub fn default(
         self,
     ) -> DirectoryBuilder<NeedsEndpoint, HyperAcmeServerBuilder<HttpsConnector>> {
+        let http = self.http_connector();
+        let tls = &self.connector.tls;
         let connector = HttpsConnectorBuilder::new()
-            .with_webpki_roots()
-            .https_only()
-            .enable_http1()
-            .build();
+            .with_tls_config(tls.client_config())
+            .https_only();
+        let connector = match (tls.alpn_http1(), tls.alpn_h2()) {
+            (true, true) => connector.enable_http1().enable_http2().wrap_connector(http),
+            (true, false) => connector.enable_http1().wrap_connector(http),
+            (false, _) => connector.enable_http2().wrap_connector(http),
+        };
 
-        let mut builder = HyperAcmeServer::builder();
-        builder.connector(connector);
+        let mut builder = HyperAcmeServerBuilder::default();
+        builder
+            .connector(connector)
+            .connect_timeout(self.connector.connect_timeout());
 
         DirectoryBuilder {
             state: PhantomData,
             builder: Some(builder),
+            limits: self.limits,
+            events: self.events,
+            connector: self.connector,
         }
     }
+
+    // the tls stack of the os as rustls-tls is disabled
+    #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+    pub fn default(
+        self,
+    ) -> Result<
+        DirectoryBuilder<NeedsEndpoint, HyperAcmeServerBuilder<NativeTlsConnector>>,
+        DirectoryError,
+    > {
+        self.native_tls()
+    }
+
+    // same as default but uses the tls stack of the os, http2 is not negotiated
+    #[cfg(feature = "native-tls")]
+    pub fn native_tls(
+        self,
+    ) -> Result<
+        DirectoryBuilder<NeedsEndpoint, HyperAcmeServerBuilder<NativeTlsConnector>>,
+        DirectoryError,
+    > {
+        let http = self.http_connector();
+        let tls = tokio_native_tls::TlsConnector::from(self.connector.tls.native_connector()?);
+
+        let mut connector = NativeTlsConnector::from((http, tls));
+        connector.https_only(true);
+
+        let mut builder = HyperAcmeServerBuilder::default();
+        builder
+            .connector(connector)
+            .connect_timeout(self.connector.connect_timeout());
+
+        Ok(DirectoryBuilder {
+            state: PhantomData,
+            builder: Some(builder),
+            limits: self.limits,
+            events: self.events,
+            connector: self.connector,
+        })
+    }
 }
 
 impl<C> DirectoryBuilder<NeedsEndpoint, HyperAcmeServerBuilder<C>> {
     pub fn url<T: Into<Cow<'static, str>>>(
-        mut self,
+        self,
         url: T,
     ) -> DirectoryBuilder<Finished, HyperAcmeServerBuilder<C>> {
-        if let Some(builder) = &mut self.builder {
-            builder.url(url);
-        }
-        DirectoryBuilder {
-            state: PhantomData,
-            builder: self.builder,
-        }
+        self.endpoint(|builder| builder.url(url))
+    }
+
+    pub fn le_staging(self) -> DirectoryBuilder<Finished, HyperAcmeServerBuilder<C>> {
+        self.endpoint(HyperAcmeServerBuilder::le_staging)
+    }
+
+    pub fn buypass(self) -> DirectoryBuilder<Finished, HyperAcmeServerBuilder<C>> {
+        self.endpoint(HyperAcmeServerBuilder::buypass)
+    }
+
+    pub fn buypass_test(self) -> DirectoryBuilder<Finished, HyperAcmeServerBuilder<C>> {
+        self.endpoint(HyperAcmeServerBuilder::buypass_test)
+    }
+
+    pub fn ssl_com(self) -> DirectoryBuilder<Finished, HyperAcmeServerBuilder<C>> {
+        self.endpoint(HyperAcmeServerBuilder::ssl_com)
+    }
+
+    pub fn step_ca(
+        self,
+        host: &str,
+        provisioner: &str,
+    ) -> DirectoryBuilder<Finished, HyperAcmeServerBuilder<C>> {
+        self.endpoint(|builder| builder.step_ca(host, provisioner))
     }
 
-    pub fn le_staging(mut self) -> DirectoryBuilder<Finished, HyperAcmeServerBuilder<C>> {
+    fn endpoint<F>(mut self, f: F) -> DirectoryBuilder<Finished, HyperAcmeServerBuilder<C>>
+    where
+        F: FnOnce(&mut HyperAcmeServerBuilder<C>) -> &mut HyperAcmeServerBuilder<C>,
+    {
         if let Some(builder) = &mut self.builder {
-            builder.le_staging();
+            f(builder);
         }
         DirectoryBuilder {
             state: PhantomData,
             builder: self.builder,
+            limits: self.limits,
+            events: self.events,
+            connector: self.connector,
         }
     }
 }
@@ -107,19 +422,31 @@ impl<S: AcmeServerBuilder> DirectoryBuilder<NeedsEndpoint, S> {
         DirectoryBuilder {
             state: PhantomData,
             builder: self.builder,
+            limits: self.limits,
+            events: self.events,
+            connector: self.connector,
         }
     }
 }
 
-impl<S: AcmeServerBuilder> DirectoryBuilder<Finished, S>
+impl<S: AcmeServerBuilder + Clone> DirectoryBuilder<Finished, S>
 where
     S::Server: Clone + Debug,
 {
-    pub async fn build(self) -> Result<Directory, <S::Server as AcmeServer>::Error> {
-        let server = self.builder.unwrap().build().await?;
+    // the builder is left untouched so it can build further directories
+    pub async fn build(&self) -> Result<Directory, <S::Server as AcmeServer>::Error> {
+        let mut builder = self.builder.clone().unwrap();
+        let url = builder.directory_url().map(ToOwned::to_owned);
+        let server = builder.build().await?;
+
         Ok(Directory {
-            crypto: RingCrypto::new(),
-            server: Box::new(server),
+            crypto: Arc::new(RingCrypto::new()),
+            server: Arc::new(server),
+            url,
+            limits: self.limits.clone(),
+            events: self.events.clone(),
+            http: Client::builder().build(self.connector.http_connector()),
+            default_account: Default::default(),
         })
     }
 }
@@ -132,78 +459,175 @@ pub enum DirectoryError {
     RingCryptoError(#[from] RingCryptoError),
     #[error(transparent)]
     JsonError(#[from] serde_json::Error),
+    #[error(transparent)]
+    ImportError(#[from] ImportError),
+    #[error("Credentials belong to directory {0}")]
+    DirectoryMismatch(String),
+    #[error("Invalid root certificate")]
+    InvalidRootCertificate,
+    #[error("Only http/1.1 and h2 are supported as ALPN protocols")]
+    UnsupportedAlpnProtocol,
+    #[error("No supported challenge for {0}")]
+    MissingChallenge(String),
+    #[error("Authorization for {0} is invalid")]
+    InvalidAuthorization(String),
+    #[error("Challenge for {0} failed: {}", .1.detail)]
+    ChallengeFailed(String, ApiError),
+    #[error("Authorization for {0} is still pending")]
+    ValidationTimeout(String),
+    #[error("Order for {0} is invalid")]
+    InvalidOrder(String),
+    #[error("{0}: {1}")]
+    Identifier(String, #[source] Box<DirectoryError>),
+    #[error("Order for {0} failed: {}", .1.detail)]
+    OrderFailed(String, ApiError),
+    #[error("Order for {0} is still processing")]
+    OrderTimeout(String),
+    #[error("Order for {0} is not ready")]
+    OrderNotReady(String),
+    #[error("Order for {0} is already valid")]
+    OrderAlreadyValid(String),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("Timed out after {0:?}")]
+    TimedOut(Duration),
+    #[error("{0}")]
+    Solver(BoxError),
+    #[error("Certificate chain can't be parsed")]
+    InvalidCertificate,
+    #[error("The CA requires an external account binding")]
+    ExternalAccountRequired,
+    #[error("External account key is not valid base64url")]
+    InvalidExternalAccountKey,
+    #[error("Environment variable {0} is missing or not valid unicode")]
+    InvalidEnv(&'static str),
+    #[error(transparent)]
+    InvalidContact(#[from] InvalidContact),
+    #[cfg(feature = "native-tls")]
+    #[error(transparent)]
+    NativeTls(#[from] native_tls::Error),
+}
+
+// larger buffers are dropped instead of being kept around per thread
+const MAX_SIGNING_BUF: usize = 64 * 1024;
+
+thread_local! {
+    // scratch space for the signing input, reused across requests
+    static SIGNING_BUF: RefCell<Vec<u8>> = RefCell::new(Vec::new());
+}
+
+fn with_signing_buf<R>(f: impl FnOnce(&mut Vec<u8>) -> R) -> R {
+    SIGNING_BUF.with(|buf| {
+        let mut buf = buf.borrow_mut();
+        buf.clear();
+        let res = f(&mut buf);
+        if buf.capacity() > MAX_SIGNING_BUF {
+            *buf = Vec::new();
+        }
+        res
+    })
 }
 
+// cheap to clone, clones share the server and crypto
 #[derive(Debug, Clone)]
 pub struct Directory {
-    server: Box<dyn DynAcmeServer>,
-    crypto: RingCrypto,
+    pub(crate) server: Arc<dyn DynAcmeServer>,
+    crypto: Arc<RingCrypto>,
+    url: Option<String>,
+    limits: Limits,
+    events: Events,
+    // plain http, shares resolver and local address with the server
+    http: Client<HttpConnector<Resolver>>,
+    // kid and key of the account registered by issue
+    default_account: Arc<Mutex<Option<(Uri, Arc<RingKeyPair>)>>>,
 }
 
 impl Directory {
-    async fn protect<'a, T>(
+    // the permit is held until the response of the signed request arrived
+    pub(crate) async fn limit_request<F: Future>(&self, request: F) -> F::Output {
+        let _permit = match &self.limits.requests {
+            Some(requests) => requests.acquire().await.ok(),
+            None => None,
+        };
+        request.await
+    }
+
+    // protects, signs and sends a request, every attempt gets a fresh nonce as resending the
+    // same jws would fail with badNonce. transient failures and badNonce are retried.
+    // every signed request of an account, including Account::update, goes through here so the
+    // requests limit applies to all of them
+    pub(crate) async fn post<K, B, R, F, Fut>(
         &self,
         url: &Uri,
-        key_pair: &RingKeyPair,
-        kid: T,
-    ) -> Result<String, DirectoryError>
+        key_pair: &Arc<RingKeyPair>,
+        key: K,
+        payload: B,
+        send: F,
+    ) -> Result<R, DirectoryError>
     where
-        T: Into<Option<&'a Uri>>,
+        K: KeyType + Clone,
+        B: Serialize + Clone + Send + Sync,
+        F: Fn(AccountRequest<B, K>) -> Fut,
+        Fut: Future<Output = Result<R, ErrorWrapper>>,
     {
-        let alg = key_pair.algorithm();
-        let nonce = self.server.new_nonce().await?;
-        let jwk = match kid.into() {
-            Some(kid) => AccountKey::KID(kid),
-            None => AccountKey::JWK(key_pair.public_key()),
-        };
-
-        let protected = Protected {
-            nonce: Some(nonce),
-            alg,
-            url,
-            jwk,
-        };
-
-        self.serialize_and_base64_encode(&protected)
+        let signer = self.signer(key_pair);
+        let mut attempt = 0;
+        loop {
+            let protected = self.protect(url, key_pair, key.clone()).await?;
+            let request = RequestImpl::new(protected, payload.clone(), signer.clone());
+            let err = match self.limit_request(send(request)).await {
+                Ok(res) => return Ok(res),
+                Err(err) => err,
+            };
+
+            let bad_nonce = is_api_error(&err, ApiErrorType::BadNonce);
+            let err = DirectoryError::from(err);
+            let transient = bad_nonce || err.is_transient();
+            match self.limits.retry.retry(attempt, transient, &err) {
+                Some(delay) => {
+                    warn!(%url, attempt, ?delay, error = %err, "retrying signed request");
+                    self.limits.runtime.sleep(delay).await
+                }
+                None => return Err(err),
+            }
+
+            attempt += 1;
+        }
     }
 
-    fn serialize_and_base64_encode<T: Serialize>(
-        &self,
-        payload: &T,
-    ) -> Result<String, DirectoryError> {
-        let payload = serde_json::to_vec(payload)?;
-        Ok(base64::encode_config(payload, base64::URL_SAFE_NO_PAD))
+    // held for the whole order, from creating it to downloading the certificate
+    async fn order_permit(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.limits.orders {
+            Some(orders) => orders.acquire().await.ok(),
+            None => None,
+        }
     }
 
-    fn sign<T, P>(
+    async fn protect<K: KeyType>(
         &self,
+        url: &Uri,
         key_pair: &RingKeyPair,
-        protected: String,
-        payload: P,
-    ) -> Result<SignedRequest<T>, DirectoryError>
-    where
-        T: Serialize,
-        P: Into<Option<String>>,
-    {
-        let payload = payload.into().map(Payload::from).unwrap_or_default();
+        key: K,
+    ) -> Result<JwsProtected<K, Nonce>, DirectoryError> {
+        self.protect_with(url, key_pair.algorithm(), key).await
+    }
 
-        let mut buf = Vec::with_capacity(protected.len() + 1 + payload.len());
-        buf.extend_from_slice(protected.as_ref());
-        buf.push(b'.');
+    // fetches a fresh nonce, the header is only valid until the CA expires the nonce
+    pub(crate) async fn protect_with<K: KeyType>(
+        &self,
+        url: &Uri,
+        alg: &'static str,
+        key: K,
+    ) -> Result<JwsProtected<K, Nonce>, DirectoryError> {
+        let nonce = self.server.new_nonce().await?;
+        Ok(JwsProtected::new(alg, key, Nonce(nonce), url.clone()))
+    }
 
-        match &payload {
-            Payload::Post { inner, .. } => buf.extend_from_slice(inner.as_ref()),
-            Payload::Get => {}
+    fn signer(&self, key_pair: &Arc<RingKeyPair>) -> AccountSigner {
+        AccountSigner {
+            crypto: self.crypto.clone(),
+            key_pair: key_pair.clone(),
         }
-
-        let signature = self.crypto.sign(key_pair, buf)?;
-        let signature = base64::encode_config(signature, base64::URL_SAFE_NO_PAD);
-
-        Ok(SignedRequest {
-            payload,
-            signature,
-            protected,
-        })
     }
 }
 
@@ -212,36 +636,362 @@ impl Directory {
         DirectoryBuilder {
             state: PhantomData,
             builder: None,
+            limits: Default::default(),
+            events: Default::default(),
+            connector: Default::default(),
         }
     }
 
+    // registers a new account agreeing to the terms of service, see account_builder
     pub async fn new_account<T: AsRef<str>>(&self, mail: T) -> Result<Account<'_>, DirectoryError> {
-        let key_pair = self.crypto.private_key()?;
-        let uri = &self.server.directory().new_account;
-        let protected = self.protect(uri, &key_pair, None).await?;
+        self.account_builder()
+            .mail(mail)
+            .agree_terms_of_service()
+            .register()
+            .await
+    }
+
+    pub fn account_builder(&self) -> AccountBuilder<'_> {
+        AccountBuilder {
+            directory: self,
+            contact: Vec::new(),
+            terms_of_service_agreed: false,
+            external_account_binding: None,
+            private_key: None,
+            only_return_existing: false,
+        }
+    }
+
+    // runs the whole issuance with an account without contact which is registered on first use,
+    // use Account::issue to issue with an existing account
+    pub async fn issue<I, T, S>(
+        &self,
+        domains: I,
+        solver: &S,
+    ) -> Result<CertificateBundle, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+        S: Solver + ?Sized,
+    {
+        let account = self.default_account().await?;
+        account.issue(domains, solver).await
+    }
 
-        let mail = format!("mailto:{}", mail.as_ref());
-        let account = ApiAccount::new(mail, true);
-        let account = self.serialize_and_base64_encode(&account)?;
-        let signed = self.sign(&key_pair, protected, account)?;
+    pub async fn issue_with_chain<I, T>(
+        &self,
+        domains: I,
+        chain: &SolverChain,
+    ) -> Result<CertificateBundle, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let account = self.default_account().await?;
+        account.issue_with_chain(domains, chain).await
+    }
 
-        let (account, kid) = self.server.new_account(signed).await?;
+    async fn default_account(&self) -> Result<Account<'_>, DirectoryError> {
+        // the lock is held while registering so concurrent calls share one account
+        let mut cached = self.default_account.lock().await;
+        let (kid, key_pair) = match &*cached {
+            Some(parts) => parts.clone(),
+            None => {
+                let account = self
+                    .account_builder()
+                    .agree_terms_of_service()
+                    .register()
+                    .await?;
+                let parts = (account.kid, account.key_pair);
+                *cached = Some(parts.clone());
+                parts
+            }
+        };
 
         Ok(Account {
             directory: Cow::Borrowed(self),
-            inner: account,
+            inner: Default::default(),
+            kid,
+            key_pair,
+            cancellation: None,
+        })
+    }
+
+    pub async fn import_credentials<T: AsRef<[u8]>>(
+        &self,
+        credentials: T,
+    ) -> Result<Account<'_>, DirectoryError> {
+        let credentials: Credentials = serde_json::from_slice(credentials.as_ref())?;
+
+        if let (Some(directory), Some(url)) = (&credentials.directory, &self.url) {
+            if directory != url {
+                return Err(DirectoryError::DirectoryMismatch(directory.clone()));
+            }
+        }
+
+        let key_pair = credentials.key.into_key_pair()?;
+        let mut account = self.account_from_key_pair(credentials.kid, key_pair);
+
+        // also makes sure the key still belongs to the account
+        account.update().await?;
+
+        Ok(account)
+    }
+
+    // restores an account from its kid and pkcs8 der encoded key, see Account::private_key_der
+    pub async fn import_private_key<T: AsRef<[u8]>>(
+        &self,
+        kid: Uri,
+        private_key: T,
+    ) -> Result<Account<'_>, DirectoryError> {
+        let mut account = self.account_from_parts(kid, private_key)?;
+
+        // also makes sure the key still belongs to the account
+        account.update().await?;
+
+        Ok(account)
+    }
+
+    // like import_private_key but without contacting the CA, for credentials stored elsewhere
+    // or taken from another client. the account state is empty until update is called
+    pub fn account_from_parts<T: AsRef<[u8]>>(
+        &self,
+        kid: Uri,
+        private_key: T,
+    ) -> Result<Account<'_>, DirectoryError> {
+        let key_pair = RingKeyPair::from_der(private_key.as_ref().to_vec())?;
+        Ok(self.account_from_key_pair(kid, key_pair))
+    }
+
+    // the account state is empty until the account gets updated
+    pub(crate) fn account_from_key_pair(&self, kid: Uri, key_pair: RingKeyPair) -> Account<'_> {
+        Account {
+            directory: Cow::Borrowed(self),
+            inner: Default::default(),
             kid,
             key_pair: Arc::new(key_pair),
+            cancellation: None,
+        }
+    }
+
+    pub(crate) fn runtime(&self) -> &dyn Runtime {
+        &*self.limits.runtime
+    }
+
+    pub(crate) fn runtime_handle(&self) -> Arc<dyn Runtime> {
+        self.limits.runtime.clone()
+    }
+
+    pub(crate) fn http_client(&self) -> &Client<HttpConnector<Resolver>> {
+        &self.http
+    }
+
+    pub(crate) fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    // suggested renewal window of a pem chain, None if the CA doesn't offer renewal information
+    pub async fn renewal_info(
+        &self,
+        chain: &[u8],
+    ) -> Result<Option<ApiRenewalInfo>, DirectoryError> {
+        let base = match &self.server.directory().renewal_info {
+            Some(base) => hyper::Uri::from(base).to_string(),
+            None => return Ok(None),
+        };
+        let cert_id = ari::cert_id(chain).ok_or(DirectoryError::InvalidCertificate)?;
+        // the cert id is base64url so the uri is always valid
+        let uri = Uri::try_from(format!("{}/{}", base.trim_end_matches('/'), cert_id))
+            .map_err(|_| DirectoryError::InvalidCertificate)?;
+
+        Ok(Some(self.server.renewal_info(&uri).await?.body))
+    }
+}
+
+// all fields of a newAccount request, the key is generated unless one is set
+pub struct AccountBuilder<'a> {
+    directory: &'a Directory,
+    contact: Vec<String>,
+    terms_of_service_agreed: bool,
+    // key id and base64url encoded hmac key
+    external_account_binding: Option<(String, String)>,
+    private_key: Option<Vec<u8>>,
+    only_return_existing: bool,
+}
+
+impl<'a> AccountBuilder<'a> {
+    pub fn mail<T: AsRef<str>>(self, mail: T) -> Self {
+        let contact = format!("mailto:{}", mail.as_ref());
+        self.contact(contact)
+    }
+
+    // contact uri, e.g. mailto:admin@example.com, validated by register
+    pub fn contact<T: Into<String>>(mut self, contact: T) -> Self {
+        self.contact.push(contact.into());
+        self
+    }
+
+    pub fn agree_terms_of_service(mut self) -> Self {
+        self.terms_of_service_agreed = true;
+        self
+    }
+
+    // the key is base64url encoded as handed out by the CA
+    pub fn external_account_binding<K: Into<String>, T: Into<String>>(
+        mut self,
+        kid: K,
+        hmac_key: T,
+    ) -> Self {
+        self.external_account_binding = Some((kid.into(), hmac_key.into()));
+        self
+    }
+
+    // pkcs8 der encoded, see Account::private_key_der
+    pub fn private_key<T: Into<Vec<u8>>>(mut self, private_key: T) -> Self {
+        self.private_key = Some(private_key.into());
+        self
+    }
+
+    // looks up the account of the private key instead of creating a new one
+    pub fn only_return_existing(mut self) -> Self {
+        self.only_return_existing = true;
+        self
+    }
+
+    pub async fn register(self) -> Result<Account<'a>, DirectoryError> {
+        let directory = self.directory;
+        // malformed contacts would only be rejected by the CA with invalidContact
+        let contact = self
+            .contact
+            .iter()
+            .map(|contact| Contact::parse(contact))
+            .collect::<Result<_, _>>()?;
+        let eab_required = directory
+            .server
+            .directory()
+            .meta
+            .as_ref()
+            .map(|meta| meta.external_account_required)
+            .unwrap_or(false);
+        if eab_required && self.external_account_binding.is_none() && !self.only_return_existing {
+            return Err(DirectoryError::ExternalAccountRequired);
+        }
+
+        if let Some(limiter) = &directory.limits.new_account {
+            limiter.acquire().await;
+        }
+
+        let key_pair = match self.private_key {
+            Some(der) => RingKeyPair::from_der(der)?,
+            None => directory.crypto.private_key()?,
+        };
+        let key_pair = Arc::new(key_pair);
+        let uri = &directory.server.directory().new_account;
+
+        let external_account_binding = match &self.external_account_binding {
+            Some((kid, hmac_key)) => Some(external_account_binding(uri, &key_pair, kid, hmac_key)?),
+            None => None,
+        };
+        let account = ApiAccount {
+            status: None,
+            contact,
+            terms_of_service_agreed: Some(self.terms_of_service_agreed),
+            external_account_binding,
+            orders: None,
+            only_return_existing: match self.only_return_existing {
+                true => Some(true),
+                false => None,
+            },
+        };
+
+        let jwk = key_pair.public_key().jwk();
+        let (account, kid) = directory
+            .post(uri, &key_pair, jwk, account, |signed| {
+                directory.server.new_account(signed)
+            })
+            .await?
+            .body;
+
+        Ok(Account {
+            directory: Cow::Borrowed(directory),
+            inner: account,
+            kid,
+            key_pair,
+            cancellation: None,
         })
     }
 }
 
+// signs the external account binding with the hmac key of the CA
+#[derive(Clone)]
+pub(crate) struct HmacSigner(hmac::Key);
+
+impl Signer for HmacSigner {
+    fn sign(&self, protected: String, payload: String) -> Result<String, SignError> {
+        let signature = hmac::sign(&self.0, format!("{}.{}", protected, payload).as_bytes());
+        Ok(base64::encode_config(signature, base64::URL_SAFE_NO_PAD))
+    }
+}
+
+pub(crate) type ExternalAccountBinding =
+    RequestImpl<Kid, NoNonce, JwsProtected<Kid, NoNonce>, Jwk, HmacSigner>;
+
+// jws over the account key signed with the hmac key of the CA, see RFC 8555 7.3.4
+fn external_account_binding(
+    url: &Uri,
+    key_pair: &RingKeyPair,
+    kid: &str,
+    hmac_key: &str,
+) -> Result<ExternalAccountBinding, DirectoryError> {
+    let hmac_key = base64::decode_config(hmac_key.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+        .map_err(|_| DirectoryError::InvalidExternalAccountKey)?;
+    let hmac_key = hmac::Key::new(hmac::HMAC_SHA256, &hmac_key);
+
+    let protected = JwsProtected::new("HS256", Kid::new(kid.to_owned()), NoNonce, url.clone());
+    Ok(RequestImpl::new(
+        protected,
+        key_pair.public_key().jwk(),
+        HmacSigner(hmac_key),
+    ))
+}
+
+pub(crate) type KeyChange =
+    RequestImpl<Jwk, NoNonce, JwsProtected<Jwk, NoNonce>, ApiKeyChange<Jwk>, AccountSigner>;
+
+// RFC 8555 section 7.3.5, the inner jws is signed by the new key and carries no nonce
+fn key_change_jws(
+    crypto: &Arc<RingCrypto>,
+    url: &Uri,
+    account: &Uri,
+    old_key: &RingKeyPair,
+    new_key: &Arc<RingKeyPair>,
+) -> KeyChange {
+    let protected = JwsProtected::new(
+        new_key.algorithm(),
+        new_key.public_key().jwk(),
+        NoNonce,
+        url.clone(),
+    );
+    let payload = ApiKeyChange {
+        account: account.clone(),
+        old_key: old_key.public_key().jwk(),
+    };
+
+    let signer = AccountSigner {
+        crypto: crypto.clone(),
+        key_pair: new_key.clone(),
+    };
+    RequestImpl::new(protected, payload, signer)
+}
+
 #[derive(Debug, Clone)]
 pub struct Account<'a> {
     directory: Cow<'a, Directory>,
-    inner: ApiAccount<()>,
+    inner: ApiAccount,
     kid: Uri,
     key_pair: Arc<RingKeyPair>,
+    // inherited by orders and authorizations
+    cancellation: Option<CancellationToken>,
 }
 
 impl<'a> Account<'a> {
@@ -252,17 +1002,86 @@ impl<'a> Account<'a> {
             inner: self.inner,
             kid: self.kid,
             key_pair: self.key_pair,
+            cancellation: self.cancellation,
         }
     }
 
-    pub async fn update(&mut self) -> Result<&mut Account<'a>, DirectoryError> {
-        let protected = self
-            .directory
-            .protect(&self.kid, &self.key_pair, &self.kid)
-            .await?;
-        let signed: SignedRequest<()> = self.directory.sign(&self.key_pair, protected, None)?;
+    // stops polling orders and authorizations of this account once cancelled, a csr that has
+    // been submitted is still waited for so the certificate isn't lost
+    pub fn cancellation(&mut self, token: CancellationToken) -> &mut Account<'a> {
+        self.cancellation = Some(token);
+        self
+    }
+
+    // sleeps between polls, fails if cancelled
+    async fn wait(&self, duration: Duration) -> Result<(), DirectoryError> {
+        match &self.cancellation {
+            Some(token) => match token.sleep(self.directory.runtime(), duration).await {
+                true => Ok(()),
+                false => Err(DirectoryError::Cancelled),
+            },
+            None => {
+                self.directory.runtime().sleep(duration).await;
+                Ok(())
+            }
+        }
+    }
+
+    fn check_cancelled(&self) -> Result<(), DirectoryError> {
+        match &self.cancellation {
+            Some(token) if token.is_cancelled() => Err(DirectoryError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    // not every CA returns the status
+    pub fn status(&self) -> Option<&ApiAccountStatus> {
+        self.inner.status.as_ref()
+    }
+
+    pub fn contact(&self) -> &[Contact] {
+        &self.inner.contact
+    }
+
+    // the account url, together with the private key all that is needed to restore the account
+    pub fn kid(&self) -> &Uri {
+        &self.kid
+    }
+
+    // pkcs8 der encoded, see Directory::import_private_key
+    pub fn private_key_der(&self) -> &[u8] {
+        self.key_pair.as_der()
+    }
 
-        let account = self.directory.server.get_account(&self.kid, signed).await?;
+    // private jwk including the d parameter, the format certbot stores its keys in
+    pub fn private_jwk(&self) -> Result<String, DirectoryError> {
+        let jwk = PrivateJwk::from_key_pair(&self.key_pair)?;
+        Ok(serde_json::to_string(&jwk)?)
+    }
+
+    pub(crate) fn key_pair(&self) -> &RingKeyPair {
+        &self.key_pair
+    }
+
+    pub fn export_credentials(&self) -> Result<String, DirectoryError> {
+        let credentials = Credentials {
+            directory: self.directory.url.clone(),
+            kid: self.kid.clone(),
+            key: PrivateJwk::from_key_pair(&self.key_pair)?,
+        };
+
+        Ok(serde_json::to_string(&credentials)?)
+    }
+
+    pub async fn update(&mut self) -> Result<&mut Account<'a>, DirectoryError> {
+        let directory = &self.directory;
+        let kid = &self.kid;
+        let account = directory
+            .post(kid, &self.key_pair, Kid::from(kid), PostAsGet, |signed| {
+                directory.server.get_account(kid, signed)
+            })
+            .await?
+            .body;
         self.inner = account;
         Ok(self)
     }
@@ -276,162 +1095,957 @@ impl<'a> Account<'a> {
         let key_pair = &self.key_pair;
         let kid = &self.kid;
 
-        let protected = directory.protect(kid, key_pair, kid).await?;
-
         // copy of inner so in case of an error we still have the old object
-        let new_account = ApiAccount::<()> {
-            contact: vec![format!("mailto:{}", mail.as_ref())],
-            ..Default::default()
+        let new_account = ApiAccount::<NoExternalAccountBinding> {
+            status: None,
+            contact: vec![Contact::mailto(mail.as_ref())?],
+            terms_of_service_agreed: None,
+            external_account_binding: None,
+            orders: None,
+            only_return_existing: None,
         };
 
-        let account = directory.serialize_and_base64_encode(&new_account)?;
-        let signed = directory.sign(key_pair, protected, account)?;
-
-        let account = directory.server.update_account(kid, signed).await?;
+        let account = directory
+            .post(kid, key_pair, Kid::from(kid), new_account, |signed| {
+                directory.server.update_account(kid, signed)
+            })
+            .await?
+            .body;
 
         let _ = mem::replace(&mut self.inner, account);
 
         Ok(self)
     }
 
-    pub async fn new_order<T: Into<String>>(&self, domain: T) -> Result<Order<'_>, DirectoryError> {
-        let domain = domain.into();
-        let identifier = ApiIdentifier {
-            type_field: ApiIdentifierType::DNS,
-            value: domain.clone(),
-        };
+    // rolls the account over to a newly generated key
+    pub async fn change_key(&mut self) -> Result<&mut Account<'a>, DirectoryError> {
+        let key_pair = self.directory.crypto.private_key()?;
+        self.change_key_to(key_pair).await
+    }
+
+    // the old key stays in use if the CA rejects the rollover
+    pub(crate) async fn change_key_to(
+        &mut self,
+        key_pair: RingKeyPair,
+    ) -> Result<&mut Account<'a>, DirectoryError> {
+        let directory = &self.directory;
+        let url = &directory.server.directory().key_change;
+
+        let key_pair = Arc::new(key_pair);
+        let inner = key_change_jws(&directory.crypto, url, &self.kid, &self.key_pair, &key_pair);
+        directory
+            .post(url, &self.key_pair, Kid::from(&self.kid), inner, |signed| {
+                directory.server.change_key(signed)
+            })
+            .await?;
+
+        self.key_pair = key_pair;
+        Ok(self)
+    }
+
+    pub async fn new_order<T: Into<String>>(&self, domain: T) -> Result<Order<'_>, DirectoryError> {
+        self.new_order_for_domains(Some(domain)).await
+    }
+
+    // one certificate for all domains, the first one becomes the common name
+    pub async fn new_order_for_domains<I, T>(&self, domains: I) -> Result<Order<'_>, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.new_order_with_validity(domains, None, None).await
+    }
+
+    // asks for a certificate valid in the given window, most public CAs reject this
+    pub async fn new_order_with_validity<I, T>(
+        &self,
+        domains: I,
+        not_before: Option<OffsetDateTime>,
+        not_after: Option<OffsetDateTime>,
+    ) -> Result<Order<'_>, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let domains = domains.into_iter().map(Into::into).collect();
+        let res = self.create_order(domains, not_before, not_after).await;
+
+        let events = &self.directory.events;
+        match &res {
+            Ok(order) => events.on_order_created(&order.domains),
+            Err(err) => events.on_error(err),
+        }
+        res
+    }
+
+    async fn create_order(
+        &self,
+        domains: Vec<String>,
+        not_before: Option<OffsetDateTime>,
+        not_after: Option<OffsetDateTime>,
+    ) -> Result<Order<'_>, DirectoryError> {
+        let identifiers = domains
+            .iter()
+            .map(|domain| ApiIdentifier {
+                type_field: ApiIdentifierType::DNS,
+                value: domain.clone(),
+            })
+            .collect();
         let new_order = ApiNewOrder {
-            identifiers: vec![identifier],
-            not_after: None,
-            not_before: None,
+            identifiers,
+            not_after,
+            not_before,
         };
 
         let directory = &self.directory;
         let server = &directory.server;
 
-        let uri = &server.directory().new_order;
-        let protected = directory.protect(uri, &self.key_pair, &self.kid).await?;
+        if let Some(limiter) = &directory.limits.new_order {
+            limiter.acquire().await;
+        }
 
-        let new_order = directory.serialize_and_base64_encode(&new_order)?;
-        let signed = directory.sign(&self.key_pair, protected, new_order)?;
+        let uri = &server.directory().new_order;
+        let (order, location) = directory
+            .post(
+                uri,
+                &self.key_pair,
+                Kid::from(&self.kid),
+                new_order,
+                |signed| server.new_order(signed),
+            )
+            .await?
+            .body;
+        Ok(Order {
+            account: Cow::Borrowed(self),
+            inner: order,
+            location,
+            domains,
+            key_algorithm: KeyAlgorithm::default(),
+            private_key: Vec::new(),
+            state: PhantomData,
+        })
+    }
 
-        let (order, location) = server.new_order(signed).await?;
+    // refetches an order created earlier, e.g. before a restart, see Order::location.
+    // ready orders can be finalized again, valid ones need the persisted key, see into_valid
+    pub async fn order_from_location(&self, location: Uri) -> Result<Order<'_>, DirectoryError> {
+        let directory = &self.directory;
+        let order = directory
+            .post(
+                &location,
+                &self.key_pair,
+                Kid::from(&self.kid),
+                PostAsGet,
+                |signed| directory.server.get_order(&location, signed),
+            )
+            .await?
+            .body;
+
+        let domains = order
+            .identifiers
+            .iter()
+            .map(|identifier| identifier.value.clone())
+            .collect();
         Ok(Order {
-            account: self,
+            account: Cow::Borrowed(self),
             inner: order,
             location,
-            domain,
+            domains,
+            key_algorithm: KeyAlgorithm::default(),
+            private_key: Vec::new(),
+            state: PhantomData,
         })
     }
+
+    // orders, solves, finalizes and downloads the certificate in one go
+    pub async fn issue<I, T, S>(
+        &self,
+        domains: I,
+        solver: &S,
+    ) -> Result<CertificateBundle, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+        S: Solver + ?Sized,
+    {
+        let _permit = self.directory.order_permit().await;
+        let order = self.new_order_for_domains(domains).await?;
+        order.solve(solver).await?;
+
+        order.ready().await?.finalize().await?.download().await
+    }
+
+    // a failed validation invalidates the order, so the next solver is tried on a new one
+    pub async fn issue_with_chain<I, T>(
+        &self,
+        domains: I,
+        chain: &SolverChain,
+    ) -> Result<CertificateBundle, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let domains = domains.into_iter().map(Into::into).collect();
+        self.issue_with_key(domains, chain, KeyAlgorithm::default())
+            .await
+    }
+
+    pub(crate) async fn issue_with_key(
+        &self,
+        domains: Vec<String>,
+        chain: &SolverChain,
+        key_algorithm: KeyAlgorithm,
+    ) -> Result<CertificateBundle, DirectoryError> {
+        let _permit = self.directory.order_permit().await;
+        let mut skip = HashMap::new();
+        let mut last = None;
+
+        loop {
+            self.check_cancelled()?;
+            let mut order = self.new_order_for_domains(domains.clone()).await?;
+            order.key_algorithm(key_algorithm);
+            match order.solve_chain(chain, &skip).await {
+                Ok(()) => {}
+                Err((Some((domain, index)), err)) => {
+                    warn!(%domain, error = %err, "validation failed, trying next solver");
+                    skip.insert(domain, index + 1);
+                    last = Some(err);
+                    continue;
+                }
+                // all solvers are exhausted, the validation error is more useful
+                Err((None, DirectoryError::MissingChallenge(domain))) => {
+                    return Err(last.unwrap_or(DirectoryError::MissingChallenge(domain)))
+                }
+                Err((None, err)) => return Err(err),
+            }
+
+            return order.ready().await?.finalize().await?.download().await;
+        }
+    }
+
+    // revokes the leaf of a pem chain issued to this account
+    pub async fn revoke(
+        &self,
+        chain: &[u8],
+        reason: Option<ApiRevocationReason>,
+    ) -> Result<(), DirectoryError> {
+        let leaf = leaf_der(chain).ok_or(DirectoryError::InvalidCertificate)?;
+
+        let directory = &self.directory;
+        let server = &directory.server;
+        let revocation = ApiRevocation {
+            certificate: base64::encode_config(leaf, base64::URL_SAFE_NO_PAD),
+            reason,
+        };
+
+        let url = &server.directory().revoke_cert;
+        directory
+            .post(
+                url,
+                &self.key_pair,
+                Kid::from(&self.kid),
+                revocation,
+                |signed| server.revoke_certificate(signed),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_authorization(
+        &self,
+        location: &Uri,
+    ) -> Result<AcmeResponse<ApiAuthorization>, DirectoryError> {
+        let directory = &self.directory;
+        directory
+            .post(
+                location,
+                &self.key_pair,
+                Kid::from(&self.kid),
+                PostAsGet,
+                |signed| directory.server.get_authorization(location, signed),
+            )
+            .await
+    }
 }
 
-#[derive(Debug)]
-pub struct Order<'a> {
-    account: &'a Account<'a>,
-    inner: ApiOrder<()>,
+// borrows the account it was created with, use into_owned to move it into a spawned task.
+// the state tracks the status of the order so finalize and download can't be called too early
+#[derive(Debug, Clone)]
+pub struct Order<'a, S: OrderState = Pending> {
+    account: Cow<'a, Account<'a>>,
+    inner: ApiOrder,
     location: Uri,
-    domain: String,
+    domains: Vec<String>,
+    key_algorithm: KeyAlgorithm,
+    // set by finalize
+    private_key: Vec<u8>,
+    state: PhantomData<S>,
 }
 
-impl<'a> Order<'a> {
-    pub async fn update(&mut self) -> Result<&mut Order<'a>, DirectoryError> {
-        let account = self.account;
-        let directory = &account.directory;
+pub trait OrderState: private::Sealed {}
+impl OrderState for Pending {}
+impl OrderState for Ready {}
+impl OrderState for Valid {}
 
-        let protected = directory
-            .protect(&self.location, &account.key_pair, &account.kid)
-            .await?;
-        let signed: SignedRequest<()> = directory.sign(&account.key_pair, protected, None)?;
+// the authorizations still have to be validated
+#[derive(Debug, Clone)]
+pub struct Pending;
+// the csr can be submitted
+#[derive(Debug, Clone)]
+pub struct Ready;
+// the certificate has been issued
+#[derive(Debug, Clone)]
+pub struct Valid;
 
-        let order = directory.server.get_order(&self.location, signed).await?;
-        self.inner = order;
+impl<'a, S: OrderState> Order<'a, S> {
+    pub fn into_owned(self) -> Order<'static, S> {
+        Order {
+            account: Cow::Owned(self.account.into_owned().into_owned()),
+            inner: self.inner,
+            location: self.location,
+            domains: self.domains,
+            key_algorithm: self.key_algorithm,
+            private_key: self.private_key,
+            state: PhantomData,
+        }
+    }
+
+    // status as of the last request, see update and status_stream
+    pub fn status(&self) -> &ApiOrderStatus {
+        &self.inner.status
+    }
+
+    pub fn expires(&self) -> Option<OffsetDateTime> {
+        self.inner.expires
+    }
+
+    // the validity window the CA accepted, None if the order didn't ask for one
+    pub fn not_before(&self) -> Option<OffsetDateTime> {
+        self.inner.not_before
+    }
+
+    pub fn not_after(&self) -> Option<OffsetDateTime> {
+        self.inner.not_after
+    }
+
+    pub fn identifiers(&self) -> &[ApiIdentifier] {
+        &self.inner.identifiers
+    }
+
+    // only set once the order is valid
+    pub fn certificate_url(&self) -> Option<&Uri> {
+        self.inner.certificate.as_ref()
+    }
+
+    // why the CA invalidated the order, e.g. a rejected csr during finalization
+    pub fn error(&self) -> Option<&ApiError> {
+        self.inner.error.as_ref()
+    }
+
+    fn invalid(&self) -> DirectoryError {
+        let domains = self.domains.join(", ");
+        match &self.inner.error {
+            Some(error) => DirectoryError::OrderFailed(domains, error.clone()),
+            None => DirectoryError::InvalidOrder(domains),
+        }
+    }
+
+    pub fn location(&self) -> &Uri {
+        &self.location
+    }
+
+    pub async fn update(&mut self) -> Result<&mut Order<'a, S>, DirectoryError> {
+        self.inner = self.fetch().await?.body;
         Ok(self)
     }
 
-    pub async fn finalize(&mut self) -> Result<Vec<u8>, DirectoryError> {
-        // todo: remove unwrap
-        let inner = &mut self.inner;
-        let finalize = &inner.finalize;
+    // refetches until the order is no longer pending or processing, the last item is the final
+    // status or an error. self is not updated, call update afterwards
+    pub fn status_stream(&self) -> impl Stream<Item = Result<ApiOrderStatus, DirectoryError>> + '_ {
+        let poll = &self.account.directory.limits.poll;
+        stream::unfold(Some((0, Duration::ZERO)), move |state| async move {
+            let (attempt, delay) = state?;
+            self.account.directory.runtime().sleep(delay).await;
+            let res = match self.fetch().await {
+                Ok(res) => res,
+                Err(err) => return Some((Err(err), None)),
+            };
+
+            let next = match res.body.status.is_in_progress() {
+                true => Some((attempt + 1, poll.delay(attempt, res.retry_after.as_ref()))),
+                false => None,
+            };
+            Some((Ok(res.body.status), next))
+        })
+    }
 
-        let account = self.account;
+    async fn fetch(&self) -> Result<AcmeResponse<ApiOrder>, DirectoryError> {
+        let account = &*self.account;
         let directory = &account.directory;
 
-        let cert = directory.crypto.certificate(self.domain.clone())?;
-        let csr = cert.csr_der()?;
-        let csr = base64::encode_config(csr, base64::URL_SAFE_NO_PAD);
-        let order_finalization = ApiOrderFinalization { csr };
+        let location = &self.location;
+        directory
+            .post(
+                location,
+                &account.key_pair,
+                Kid::from(&account.kid),
+                PostAsGet,
+                |signed| directory.server.get_order(location, signed),
+            )
+            .await
+    }
 
-        let protected = directory
-            .protect(finalize, &account.key_pair, &account.kid)
-            .await?;
+    // waits until the order is no longer pending or processing
+    async fn poll(&mut self, cancellable: bool) -> Result<(), DirectoryError> {
+        let poll = self.account.directory.limits.poll.clone();
+        let mut retry_after = None;
+        for attempt in 0..VALIDATION_ATTEMPTS as u32 {
+            if self.inner.status.is_invalid() {
+                return Err(self.invalid());
+            }
+            if !self.inner.status.is_in_progress() {
+                return Ok(());
+            }
+
+            let delay = poll.delay(attempt, retry_after.as_ref());
+            match cancellable {
+                true => self.account.wait(delay).await?,
+                false => self.account.directory.runtime().sleep(delay).await,
+            }
+            let res = self.fetch().await?;
+            self.inner = res.body;
+            retry_after = res.retry_after;
+        }
 
-        let order_finalization = directory.serialize_and_base64_encode(&order_finalization)?;
-        let signed = directory.sign(&account.key_pair, protected, order_finalization)?;
+        Err(DirectoryError::OrderTimeout(self.domains.join(", ")))
+    }
 
-        let order = directory.server.finalize(finalize, signed).await?;
-        let _ = mem::replace(inner, order);
+    // the status has to be checked before
+    fn into_state<T: OrderState>(self) -> Order<'a, T> {
+        Order {
+            account: self.account,
+            inner: self.inner,
+            location: self.location,
+            domains: self.domains,
+            key_algorithm: self.key_algorithm,
+            private_key: self.private_key,
+            state: PhantomData,
+        }
+    }
+}
 
-        // todo: remove unwrap
-        let certificate = inner.certificate.as_ref().unwrap();
+impl<'a> Order<'a, Pending> {
+    // key of the certificate requested by finalize, defaults to P-384
+    pub fn key_algorithm(&mut self, key_algorithm: KeyAlgorithm) -> &mut Order<'a> {
+        self.key_algorithm = key_algorithm;
+        self
+    }
 
-        let protected = directory
-            .protect(certificate, &account.key_pair, &account.kid)
-            .await?;
-        let signed: SignedRequest<()> = directory.sign(&account.key_pair, protected, None)?;
+    // waits until the CA has processed the validated authorizations
+    pub async fn ready(mut self) -> Result<Order<'a, Ready>, DirectoryError> {
+        self.update().await?;
+        self.poll(true).await?;
+
+        match self.inner.status {
+            ApiOrderStatus::Ready => Ok(self.into_state()),
+            // finalized before, e.g. through a clone, only into_valid can resume it
+            ApiOrderStatus::Valid => {
+                Err(DirectoryError::OrderAlreadyValid(self.domains.join(", ")))
+            }
+            _ => Err(DirectoryError::OrderNotReady(self.domains.join(", "))),
+        }
+    }
 
-        let certificate = directory
-            .server
-            .download_certificate(certificate, signed)
-            .await?;
-        Ok(certificate)
+    // locations of the authorizations, fetch them one by one with authorization
+    pub fn authorization_urls(&self) -> &[Uri] {
+        &self.inner.authorizations
+    }
+
+    // fetches the authorizations one at a time when polled, in the order of authorization_urls
+    pub fn authorization_stream(
+        &self,
+    ) -> impl Stream<Item = Result<Authorization<'_>, DirectoryError>> + '_ {
+        stream::iter(&self.inner.authorizations).then(move |location| self.authorization(location))
     }
 
+    // fetches all authorizations upfront
     pub async fn authorizations(&self) -> Result<Vec<Authorization<'_>>, DirectoryError> {
-        let inner = &self.inner;
+        let concurrency = self.account.directory.limits.concurrency;
+
+        // buffered keeps the order of the authorizations
+        stream::iter(&self.inner.authorizations)
+            .map(|location| self.authorization(location))
+            .buffered(concurrency)
+            .try_collect()
+            .await
+    }
 
-        let mut authorizations = Vec::with_capacity(inner.authorizations.len());
+    // validates the http challenges of all authorizations concurrently and waits until they are done
+    pub async fn validate_http(&self) -> Result<Vec<Validation>, DirectoryError> {
+        let authorizations = self.authorizations().await?;
+        Ok(self
+            .validate_authorizations(authorizations, ApiChallengeType::HTTP)
+            .await)
+    }
 
-        for authorization in &self.inner.authorizations {
-            // todo: fix this unwrap
-            let authorization = self.authorization(authorization).await?;
-            authorizations.push(authorization);
+    pub(crate) async fn validate_authorizations(
+        &self,
+        authorizations: Vec<Authorization<'_>>,
+        challenge_type: ApiChallengeType,
+    ) -> Vec<Validation> {
+        let concurrency = self.account.directory.limits.concurrency;
+        let challenge_type = &challenge_type;
+
+        stream::iter(authorizations)
+            .map(|authorization| async move {
+                let identifier = authorization.inner.identifier.value.clone();
+                let result = authorization.validate(challenge_type).await;
+                Validation { identifier, result }
+            })
+            .buffer_unordered(concurrency)
+            .collect()
+            .await
+    }
+
+    // presents the challenges of the pending authorizations, validates them and cleans up
+    pub async fn solve<S: Solver + ?Sized>(&self, solver: &S) -> Result<(), DirectoryError> {
+        let challenge_type = solver.challenge_type();
+        let authorizations = self.authorizations().await?;
+
+        let mut proofs = Vec::with_capacity(authorizations.len());
+        let mut pending = Vec::with_capacity(authorizations.len());
+        let mut res = Ok(());
+        for authorization in authorizations {
+            // valid authorizations of earlier orders are reused by the CA
+            if authorization.inner.status.is_valid() {
+                continue;
+            }
+
+            let proof = match authorization.challenge_proof(&challenge_type) {
+                Ok(proof) => proof,
+                Err(err) => {
+                    res = Err(err);
+                    break;
+                }
+            };
+            // cleanup also runs for a failed present as it might have been partially applied
+            let presented = solver.present(&proof).await;
+            proofs.push(proof);
+            if let Err(err) = presented {
+                res = Err(DirectoryError::Solver(err));
+                break;
+            }
+            pending.push(authorization);
         }
 
-        Ok(authorizations)
+        if res.is_ok() {
+            res = self
+                .validate_authorizations(pending, challenge_type)
+                .await
+                .into_iter()
+                .map(|validation| validation.result)
+                .collect();
+        }
+
+        for proof in &proofs {
+            if let Err(err) = solver.cleanup(proof).await {
+                warn!(domain = %proof.domain, error = %err, "cleanup failed");
+            }
+        }
+
+        res
     }
 
-    async fn authorization(&self, location: &Uri) -> Result<Authorization<'_>, DirectoryError> {
-        let account = self.account;
-        let directory = &account.directory;
+    // skip holds the number of solvers to skip per domain, if a validation fails the domain
+    // and the index of its solver are returned
+    async fn solve_chain(
+        &self,
+        chain: &SolverChain,
+        skip: &HashMap<String, usize>,
+    ) -> Result<(), (Option<(String, usize)>, DirectoryError)> {
+        let authorizations = self.authorizations().await.map_err(|err| (None, err))?;
+
+        let mut presented = Vec::with_capacity(authorizations.len());
+        let mut pending = Vec::with_capacity(authorizations.len());
+        let mut res = Ok(());
+        'authorizations: for authorization in authorizations {
+            if authorization.inner.status.is_valid() {
+                continue;
+            }
+
+            let domain = authorization.domain();
+            let start = skip.get(&domain).copied().unwrap_or(0);
+            for (index, solver) in chain.solvers(&domain).iter().enumerate().skip(start) {
+                let challenge_type = solver.challenge_type();
+                // the CA doesn't offer this challenge type
+                let proof = match authorization.challenge_proof(&challenge_type) {
+                    Ok(proof) => proof,
+                    Err(_) => continue,
+                };
+
+                if let Err(err) = solver.present(&proof).await {
+                    warn!(%domain, error = %err, "present failed, trying next solver");
+                    if let Err(err) = solver.cleanup(&proof).await {
+                        warn!(%domain, error = %err, "cleanup failed");
+                    }
+                    continue;
+                }
+
+                presented.push((solver, proof));
+                pending.push((authorization, challenge_type, domain, index));
+                continue 'authorizations;
+            }
+
+            res = Err((None, DirectoryError::MissingChallenge(domain)));
+            break;
+        }
 
-        let protected = directory
-            .protect(location, &account.key_pair, &account.kid)
-            .await?;
+        if res.is_ok() {
+            let concurrency = self.account.directory.limits.concurrency;
+            let validations: Vec<_> = stream::iter(pending)
+                .map(
+                    |(authorization, challenge_type, domain, index)| async move {
+                        let result = authorization.validate(&challenge_type).await;
+                        (domain, index, result)
+                    },
+                )
+                .buffer_unordered(concurrency)
+                .collect()
+                .await;
+
+            for (domain, index, result) in validations {
+                if let Err(err) = result {
+                    res = Err((Some((domain, index)), err));
+                    break;
+                }
+            }
+        }
+
+        for (solver, proof) in &presented {
+            if let Err(err) = solver.cleanup(proof).await {
+                warn!(domain = %proof.domain, error = %err, "cleanup failed");
+            }
+        }
 
-        let signed: SignedRequest<()> = directory.sign(&account.key_pair, protected, None)?;
+        res
+    }
+
+    // for resumed orders which were finalized before, the private key is the one the csr was
+    // generated with and ends up in the downloaded CertificateBundle
+    pub fn into_valid<T: Into<Vec<u8>>>(
+        self,
+        private_key: T,
+    ) -> Result<Order<'a, Valid>, DirectoryError> {
+        match self.inner.status {
+            ApiOrderStatus::Valid => {}
+            ApiOrderStatus::Invalid => return Err(self.invalid()),
+            _ => return Err(DirectoryError::OrderNotReady(self.domains.join(", "))),
+        }
+
+        let mut order = self.into_state::<Valid>();
+        order.private_key = private_key.into();
+        Ok(order)
+    }
 
-        let authorization = directory.server.get_authorization(location, signed).await?;
+    pub async fn authorization(&self, location: &Uri) -> Result<Authorization<'_>, DirectoryError> {
+        let account = &*self.account;
         Ok(Authorization {
-            inner: authorization,
-            order: self,
+            inner: account.get_authorization(location).await?.body,
+            account: Cow::Borrowed(account),
             location: location.clone(),
         })
     }
 }
 
+impl<'a> Order<'a, Ready> {
+    // submits the csr and waits until the certificate is issued
+    pub async fn finalize(self) -> Result<Order<'a, Valid>, DirectoryError> {
+        let events = self.account.directory.events.clone();
+        let domains = self.domains.clone();
+        let res = self.submit_csr().await;
+
+        match &res {
+            Ok(_) => events.on_finalized(&domains),
+            Err(err) => events.on_error(err),
+        }
+        res
+    }
+
+    async fn submit_csr(mut self) -> Result<Order<'a, Valid>, DirectoryError> {
+        // the status is stale if the order was updated or finalized through a clone
+        match self.inner.status {
+            ApiOrderStatus::Ready => {}
+            ApiOrderStatus::Invalid => return Err(self.invalid()),
+            _ => return Err(DirectoryError::OrderNotReady(self.domains.join(", "))),
+        }
+        let finalize = &self.inner.finalize;
+
+        let account = &*self.account;
+        let directory = &account.directory;
+
+        let cert = directory
+            .crypto
+            .certificate(self.domains.clone(), self.key_algorithm)?;
+        let csr = cert.csr_der()?;
+        let csr = base64::encode_config(csr, base64::URL_SAFE_NO_PAD);
+        let order_finalization = ApiOrderFinalization { csr };
+
+        let finalized = directory.post(
+            finalize,
+            &account.key_pair,
+            Kid::from(&account.kid),
+            order_finalization,
+            |signed| directory.server.finalize(finalize, signed),
+        );
+        let order = match finalized.await {
+            Ok(res) => res.body,
+            Err(DirectoryError::ServerError(err))
+                if is_api_error(&err, ApiErrorType::OrderNotReady) =>
+            {
+                return Err(DirectoryError::OrderNotReady(self.domains.join(", ")))
+            }
+            Err(err) => return Err(err),
+        };
+        self.inner = order;
+
+        // the CA may still be processing the csr, the key would be lost if this was cancelled
+        self.poll(false).await?;
+        match self.inner.status {
+            ApiOrderStatus::Valid => {}
+            _ => return Err(DirectoryError::OrderNotReady(self.domains.join(", "))),
+        }
+
+        self.private_key = cert.private_key_der();
+        Ok(self.into_state())
+    }
+}
+
+impl<'a> Order<'a, Valid> {
+    // also returns the private key the csr was generated with
+    pub async fn download(&self) -> Result<CertificateBundle, DirectoryError> {
+        let account = &*self.account;
+        let directory = &account.directory;
+
+        let certificate = self
+            .inner
+            .certificate
+            .as_ref()
+            .ok_or_else(|| DirectoryError::InvalidOrder(self.domains.join(", ")))?;
+
+        let certificate = directory
+            .post(
+                certificate,
+                &account.key_pair,
+                Kid::from(&account.kid),
+                PostAsGet,
+                |signed| directory.server.download_certificate(certificate, signed),
+            )
+            .await?
+            .body;
+        Ok(CertificateBundle {
+            chain: certificate,
+            private_key: self.private_key.clone(),
+        })
+    }
+
+    // the pem chain while it is received, the hyper server yields one block per item.
+    // retries only happen until the response arrives, not while reading the body
+    pub async fn download_stream(&self) -> Result<ByteStream<DirectoryError>, DirectoryError> {
+        let account = &*self.account;
+        let directory = &account.directory;
+
+        let certificate = self
+            .inner
+            .certificate
+            .as_ref()
+            .ok_or_else(|| DirectoryError::InvalidOrder(self.domains.join(", ")))?;
+
+        let stream = directory
+            .post(
+                certificate,
+                &account.key_pair,
+                Kid::from(&account.kid),
+                PostAsGet,
+                |signed| {
+                    directory
+                        .server
+                        .download_certificate_stream(certificate, signed)
+                },
+            )
+            .await?
+            .body;
+        Ok(Box::pin(stream.map_err(DirectoryError::from)))
+    }
+
+    // der encoded private key of the csr, download includes it in the bundle
+    pub fn private_key_der(&self) -> &[u8] {
+        &self.private_key
+    }
+}
+
+impl DirectoryError {
+    // timeouts while waiting on the CA are transient as well,
+    // errors of other AcmeServer implementations are classified by their ApiError if any
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            DirectoryError::ServerError(err) => match err.downcast_ref::<HyperAcmeServerError>() {
+                Some(err) => err.class(),
+                None => err
+                    .api_error()
+                    .map(|err| ErrorClass::from(&err.type_val))
+                    .unwrap_or(ErrorClass::Permanent),
+            },
+            DirectoryError::ValidationTimeout(_)
+            | DirectoryError::OrderTimeout(_)
+            | DirectoryError::TimedOut(_) => ErrorClass::Transient,
+            DirectoryError::Identifier(_, err) => err.class(),
+            DirectoryError::InvalidAuthorization(_) => ErrorClass::Validation,
+            DirectoryError::ChallengeFailed(_, err) => match ErrorClass::from(&err.type_val) {
+                ErrorClass::Permanent => ErrorClass::Validation,
+                class => class,
+            },
+            _ => ErrorClass::Permanent,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.class().is_retryable()
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        self.class() == ErrorClass::RateLimited
+    }
+
+    pub(crate) fn is_transient(&self) -> bool {
+        self.class() == ErrorClass::Transient
+    }
+
+    // the error without the identifier it occurred for
+    pub fn without_context(&self) -> &DirectoryError {
+        match self {
+            DirectoryError::Identifier(_, err) => err.without_context(),
+            err => err,
+        }
+    }
+
+    // errors of the server don't name the identifier, the other variants already do
+    fn with_identifier(self, identifier: &str) -> Self {
+        match self {
+            DirectoryError::ServerError(_) | DirectoryError::Solver(_) => {
+                DirectoryError::Identifier(identifier.to_owned(), Box::new(self))
+            }
+            err => err,
+        }
+    }
+}
+
+fn is_api_error(err: &ErrorWrapper, type_val: ApiErrorType) -> bool {
+    match err.api_error() {
+        Some(err) => err.type_val == type_val,
+        None => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CertificateBundle {
+    // pem encoded, leaf first
+    pub chain: Vec<u8>,
+    // pkcs8 der encoded
+    pub private_key: Vec<u8>,
+}
+
 #[derive(Debug)]
+pub struct Validation {
+    pub identifier: String,
+    pub result: Result<(), DirectoryError>,
+}
+
+#[derive(Debug, Clone)]
 pub struct Authorization<'a> {
-    order: &'a Order<'a>,
+    account: Cow<'a, Account<'a>>,
     inner: ApiAuthorization,
     location: Uri,
 }
 
 impl<'a> Authorization<'a> {
+    pub fn into_owned(self) -> Authorization<'static> {
+        Authorization {
+            account: Cow::Owned(self.account.into_owned().into_owned()),
+            inner: self.inner,
+            location: self.location,
+        }
+    }
+
     pub fn http_challenge(&self) -> Option<Challenge<'_, Http>> {
+        self.challenge(ApiChallengeType::HTTP)
+    }
+
+    pub fn tls_alpn_challenge(&self) -> Option<Challenge<'_, TlsAlpn>> {
+        self.challenge(ApiChallengeType::TLS)
+    }
+
+    pub fn dns_challenge(&self) -> Option<Challenge<'_, Dns>> {
+        self.challenge(ApiChallengeType::DNS)
+    }
+
+    pub fn status(&self) -> &ApiAuthorizationStatus {
+        &self.inner.status
+    }
+
+    pub fn expires(&self) -> Option<OffsetDateTime> {
+        self.inner.expires
+    }
+
+    // an authorization without expiry is treated as usable
+    pub fn is_expired(&self) -> bool {
+        match self.inner.expires {
+            Some(expires) => expires <= OffsetDateTime::now_utc(),
+            None => false,
+        }
+    }
+
+    // the identifier of a wildcard authorization is the base domain, see is_wildcard
+    pub fn identifier(&self) -> &ApiIdentifier {
+        &self.inner.identifier
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.inner.wildcard
+    }
+
+    // wildcard authorizations are issued for the base domain
+    fn domain(&self) -> String {
+        match self.inner.wildcard {
+            true => format!("*.{}", self.inner.identifier.value),
+            false => self.inner.identifier.value.clone(),
+        }
+    }
+
+    fn challenge_proof(
+        &self,
+        challenge_type: &ApiChallengeType,
+    ) -> Result<ChallengeProof, DirectoryError> {
+        let domain = self.domain();
+        let challenge = match self.challenge::<Any>(challenge_type.clone()) {
+            Some(challenge) => challenge,
+            None => return Err(DirectoryError::MissingChallenge(domain)),
+        };
+
+        Ok(ChallengeProof {
+            token: challenge.token().to_owned(),
+            key_authorization: challenge.key_authorization()?,
+            domain,
+        })
+    }
+
+    fn challenge<T: ChallengeType>(
+        &self,
+        type_field: ApiChallengeType,
+    ) -> Option<Challenge<'_, T>> {
         self.inner
             .challenges
             .iter()
-            .find(|c| c.type_field == ApiChallengeType::HTTP)
+            .find(|c| c.type_field == type_field)
             .map(|c| Challenge {
                 inner: c,
                 authorization: self,
@@ -440,17 +2054,99 @@ impl<'a> Authorization<'a> {
     }
 
     pub async fn update(&mut self) -> Result<(), DirectoryError> {
-        let mut this = self.order.authorization(&self.location).await?;
-        mem::swap(self, &mut this);
-
+        self.inner = self.account.get_authorization(&self.location).await?.body;
         Ok(())
     }
+
+    // refetches until the authorization is no longer pending, the last item is the
+    // final status or an error. self is not updated, call update afterwards
+    pub fn status_stream(
+        &self,
+    ) -> impl Stream<Item = Result<ApiAuthorizationStatus, DirectoryError>> + '_ {
+        let poll = &self.account.directory.limits.poll;
+        stream::unfold(Some((0, Duration::ZERO)), move |state| async move {
+            let (attempt, delay) = state?;
+            self.account.directory.runtime().sleep(delay).await;
+            let res = match self.account.get_authorization(&self.location).await {
+                Ok(res) => res,
+                Err(err) => return Some((Err(err), None)),
+            };
+
+            let next = match res.body.status.is_terminal() {
+                true => None,
+                false => Some((attempt + 1, poll.delay(attempt, res.retry_after.as_ref()))),
+            };
+            Some((Ok(res.body.status), next))
+        })
+    }
+
+    async fn validate(self, challenge_type: &ApiChallengeType) -> Result<(), DirectoryError> {
+        // the events outlive self which is consumed by the validation
+        let events = self.account.directory.events.clone();
+        let identifier = self.inner.identifier.value.clone();
+        let res = self
+            .validate_and_poll(challenge_type)
+            .await
+            .map_err(|err| err.with_identifier(&identifier));
+
+        match &res {
+            Ok(()) => events.on_challenge_validated(&identifier, challenge_type),
+            Err(err) => events.on_error(err),
+        }
+        res
+    }
+
+    async fn validate_and_poll(
+        mut self,
+        challenge_type: &ApiChallengeType,
+    ) -> Result<(), DirectoryError> {
+        let identifier = &self.inner.identifier.value;
+        if self.inner.status.is_pending() {
+            let challenge = self
+                .challenge::<Any>(challenge_type.clone())
+                .ok_or_else(|| DirectoryError::MissingChallenge(identifier.clone()))?;
+            challenge.validate().await?;
+        }
+
+        let poll = self.account.directory.limits.poll.clone();
+        let mut retry_after = None;
+        for attempt in 0..VALIDATION_ATTEMPTS as u32 {
+            match self.inner.status {
+                ApiAuthorizationStatus::Valid => return Ok(()),
+                // deactivated, expired or revoked authorizations can't become valid either
+                ref status if status.is_terminal() => {
+                    return Err(DirectoryError::InvalidAuthorization(
+                        self.inner.identifier.value.clone(),
+                    ))
+                }
+                _ => {}
+            }
+
+            self.account
+                .wait(poll.delay(attempt, retry_after.as_ref()))
+                .await?;
+            let res = self.account.get_authorization(&self.location).await?;
+            self.inner = res.body;
+            retry_after = res.retry_after;
+        }
+
+        Err(DirectoryError::ValidationTimeout(
+            self.inner.identifier.value.clone(),
+        ))
+    }
 }
 
 pub trait ChallengeType: private::Sealed {}
 impl ChallengeType for Http {}
+impl ChallengeType for TlsAlpn {}
+impl ChallengeType for Dns {}
+impl ChallengeType for Any {}
 
 pub struct Http;
+pub struct TlsAlpn;
+pub struct Dns;
+// used where the challenge type is only known at runtime
+pub(crate) struct Any;
 
 #[derive(Debug)]
 pub struct Challenge<'a, T: ChallengeType> {
@@ -464,33 +2160,73 @@ impl<'a, T: ChallengeType> Challenge<'a, T> {
         &self.inner.token
     }
 
+    // as of the last fetch of the authorization
+    pub fn status(&self) -> &ApiChallengeStatus {
+        &self.inner.status
+    }
+
+    // set by the CA once validation failed
+    pub fn error(&self) -> Option<&ApiError> {
+        self.inner.error.as_ref()
+    }
+
     pub async fn validate(&self) -> Result<(), DirectoryError> {
-        let account = self.authorization.order.account;
+        let account = &*self.authorization.account;
         let directory = &account.directory;
         // todo: remove unwrap
         let uri = Uri::try_from(&*self.inner.url).unwrap();
 
-        let protected = directory
-            .protect(&uri, &account.key_pair, &account.kid)
+        // todo: maybe use return type
+        directory
+            .post(
+                &uri,
+                &account.key_pair,
+                Kid::from(&account.kid),
+                ApiChallengeResponse {},
+                |signed| directory.server.validate_challenge(&uri, signed),
+            )
             .await?;
+        Ok(())
+    }
 
-        let empty_object = HashMap::<(), ()>::new();
-        let empty_object = directory.serialize_and_base64_encode(&empty_object)?;
-
-        let signed = directory.sign(&account.key_pair, protected, empty_object)?;
+    // validates and polls the authorization until it is valid or invalid,
+    // the error reported by the CA for this challenge is returned as ChallengeFailed
+    pub async fn validate_and_wait(&self) -> Result<(), DirectoryError> {
+        let authorization = self.authorization;
+        let account = &*authorization.account;
+        let identifier = &authorization.inner.identifier.value;
+        self.validate().await?;
+
+        let poll = &account.directory.limits.poll;
+        let mut retry_after = None;
+        for attempt in 0..VALIDATION_ATTEMPTS as u32 {
+            account
+                .wait(poll.delay(attempt, retry_after.as_ref()))
+                .await?;
+            let res = account.get_authorization(&authorization.location).await?;
+            match res.body.status {
+                ApiAuthorizationStatus::Valid => return Ok(()),
+                ref status if status.is_terminal() => {
+                    return Err(challenge_error(identifier, &res.body, &self.inner.url))
+                }
+                _ => retry_after = res.retry_after,
+            }
+        }
 
-        // todo: maybe use return type
-        directory.server.validate_challenge(&uri, signed).await?;
-        Ok(())
+        Err(DirectoryError::ValidationTimeout(identifier.clone()))
     }
-}
 
-impl<'a> Challenge<'a, Http> {
+    #[deprecated(note = "use key_authorization")]
     pub fn proof(&self) -> Result<String, DirectoryError> {
+        self.key_authorization()
+    }
+
+    // served as is for http-01 and hashed for dns-01 and tls-alpn-01
+    pub fn key_authorization(&self) -> Result<String, DirectoryError> {
         let mut token = self.inner.token.clone();
         token.push('.');
 
-        let account = self.authorization.order.account;
+        let account = &*self.authorization.account;
 
         let public_key = account.key_pair.public_key();
         let public_key = serde_json::to_vec(&public_key)?;
@@ -502,51 +2238,107 @@ impl<'a> Challenge<'a, Http> {
     }
 }
 
-struct Protected<'a> {
+fn challenge_error(
+    identifier: &str,
+    authorization: &ApiAuthorization,
+    url: &str,
+) -> DirectoryError {
+    let error = authorization
+        .challenges
+        .iter()
+        .find(|challenge| challenge.url == url)
+        .and_then(|challenge| challenge.error.clone());
+
+    match error {
+        Some(error) => DirectoryError::ChallengeFailed(identifier.to_owned(), error),
+        None => DirectoryError::InvalidAuthorization(identifier.to_owned()),
+    }
+}
+
+impl<'a> Challenge<'a, Dns> {
+    // value of the _acme-challenge txt record
+    pub fn txt_value(&self) -> Result<String, DirectoryError> {
+        let digest = digest(&SHA256, self.key_authorization()?.as_bytes());
+        Ok(base64::encode_config(digest, base64::URL_SAFE_NO_PAD))
+    }
+}
+
+impl<'a> Challenge<'a, TlsAlpn> {
+    // sha256 of the key authorization, the value of the acmeIdentifier extension
+    pub fn acme_identifier_digest(&self) -> Result<Vec<u8>, DirectoryError> {
+        let digest = digest(&SHA256, self.key_authorization()?.as_bytes());
+        Ok(digest.as_ref().to_vec())
+    }
+}
+
+// the kid of the account or the jwk of a key which is not bound to an account yet.
+// only the inner jws of a key change and the external account binding carry no nonce
+#[derive(Debug, Clone)]
+pub struct JwsProtected<K, N> {
     alg: &'static str,
-    nonce: Option<String>,
-    url: &'a Uri,
-    jwk: AccountKey<'a>,
+    key: K,
+    nonce: N,
+    url: Uri,
 }
 
-impl Serialize for Protected<'_> {
-    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut serializer = match &self.nonce {
-            Some(_) => serializer.serialize_struct("Protected", 4)?,
-            None => serializer.serialize_struct("Protected", 3)?,
-        };
-        serializer.serialize_field("alg", &self.alg)?;
-        if let Some(nonce) = &self.nonce {
-            serializer.serialize_field("nonce", nonce)?;
+impl<K: KeyType, N: NonceType> JwsProtected<K, N> {
+    pub(crate) fn new(alg: &'static str, key: K, nonce: N, url: Uri) -> Self {
+        Self {
+            alg,
+            key,
+            nonce,
+            url,
         }
-        serializer.serialize_field("url", &self.url)?;
+    }
+}
 
-        match &self.jwk {
-            AccountKey::JWK(public_key) => serializer.serialize_field("jwk", public_key)?,
-            AccountKey::KID(kid) => serializer.serialize_field("kid", kid)?,
-        };
+impl<K: KeyType, N: NonceType> Protected<K, N> for JwsProtected<K, N> {
+    fn alg(&self) -> &str {
+        self.alg
+    }
+
+    fn key(&self) -> &K {
+        &self.key
+    }
+
+    fn nonce(&self) -> &N {
+        &self.nonce
+    }
 
-        serializer.end()
+    fn url(&self) -> &Uri {
+        &self.url
     }
 }
 
-enum AccountKey<'a> {
-    JWK(&'a RingPublicKey),
-    KID(&'a Uri),
+// signs with the account key, the signature is created each time the request is serialized
+#[derive(Debug, Clone)]
+pub(crate) struct AccountSigner {
+    crypto: Arc<RingCrypto>,
+    key_pair: Arc<RingKeyPair>,
 }
 
-impl Serialize for AccountKey<'_> {
-    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        match self {
-            AccountKey::KID(kid) => kid.serialize(serializer),
-            AccountKey::JWK(public_key) => public_key.serialize(serializer),
-        }
+impl Signer for AccountSigner {
+    fn sign(&self, protected: String, payload: String) -> Result<String, SignError> {
+        let signature = with_signing_buf(|buf| {
+            buf.reserve(protected.len() + 1 + payload.len());
+            buf.extend_from_slice(protected.as_ref());
+            buf.push(b'.');
+            buf.extend_from_slice(payload.as_ref());
+
+            self.crypto.sign(&self.key_pair, &buf[..])
+        })?;
+        Ok(base64::encode_config(signature, base64::URL_SAFE_NO_PAD))
     }
 }
 
+pub(crate) type AccountRequest<B, K = Kid> =
+    RequestImpl<K, Nonce, JwsProtected<K, Nonce>, B, AccountSigner>;
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::HyperAcmeServer;
+    use acme_core::RetryAfter;
     use std::error::Error;
     use testcontainers::clients::Cli;
 
@@ -554,6 +2346,245 @@ mod tests {
     use nginx_minio::WebserverWithApi;
     use stepca::Stepca;
 
+    #[test]
+    fn owned_flow_is_send() {
+        fn assert_send<T: Send + 'static>() {}
+        assert_send::<Account<'static>>();
+        assert_send::<Order<'static>>();
+        assert_send::<Order<'static, Valid>>();
+        assert_send::<Authorization<'static>>();
+    }
+
+    // base64url json member of a serialized jws
+    fn decode(
+        input: &serde_json::Value,
+    ) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
+        let input = input.as_str().unwrap_or_default();
+        let input = base64::decode_config(input, base64::URL_SAFE_NO_PAD)?;
+        Ok(serde_json::from_slice(&input)?)
+    }
+
+    #[test]
+    fn eab_signature() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+        let key_pair = RingCrypto::new().private_key()?;
+        let url = Uri::try_from("https://example.com/acme/new-account")?;
+        let hmac_key = base64::encode_config(b"secret", base64::URL_SAFE_NO_PAD);
+
+        let eab = external_account_binding(&url, &key_pair, "kid-1", &hmac_key)?;
+        let eab = serde_json::to_value(&eab)?;
+
+        let protected = decode(&eab["protected"])?;
+        assert_eq!(protected["alg"], "HS256");
+        assert_eq!(protected["kid"], "kid-1");
+        assert_eq!(protected["url"], "https://example.com/acme/new-account");
+        assert!(protected.get("nonce").is_none());
+        assert_eq!(
+            decode(&eab["payload"])?,
+            serde_json::to_value(key_pair.public_key())?
+        );
+
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let signature = eab["signature"].as_str().unwrap_or_default();
+        let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD)?;
+        let input = format!(
+            "{}.{}",
+            eab["protected"].as_str().unwrap_or_default(),
+            eab["payload"].as_str().unwrap_or_default()
+        );
+        hmac::verify(&key, input.as_bytes(), &signature).map_err(|_| "invalid signature")?;
+
+        assert!(external_account_binding(&url, &key_pair, "kid-1", "not base64!").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn key_change_jws_structure() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+        let crypto = Arc::new(RingCrypto::new());
+        let old_key = crypto.private_key()?;
+        let new_key = Arc::new(crypto.private_key()?);
+        let url = Uri::try_from("https://example.com/acme/key-change")?;
+        let account = Uri::try_from("https://example.com/acme/acct/evOfKhNU60wg")?;
+
+        let jws = key_change_jws(&crypto, &url, &account, &old_key, &new_key);
+        let jws = serde_json::to_value(&jws)?;
+
+        let protected = decode(&jws["protected"])?;
+        assert_eq!(protected["alg"], "ES384");
+        assert_eq!(protected["url"], "https://example.com/acme/key-change");
+        assert_eq!(
+            protected["jwk"],
+            serde_json::to_value(new_key.public_key())?
+        );
+        assert!(protected.get("nonce").is_none());
+        assert!(protected.get("kid").is_none());
+
+        assert_eq!(
+            decode(&jws["payload"])?,
+            serde_json::json!({
+                "account": "https://example.com/acme/acct/evOfKhNU60wg",
+                "oldKey": serde_json::to_value(old_key.public_key())?,
+            })
+        );
+
+        // raw r and s of P-384
+        let signature = jws["signature"].as_str().unwrap_or_default();
+        let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD)?;
+        assert_eq!(signature.len(), 96);
+        Ok(())
+    }
+
+    // the inner jws of the RFC 8555 section 7.3.5 example, encoded byte for byte with the keys
+    // filled in
+    #[test]
+    fn key_change_jws_fixture() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+        let crypto = Arc::new(RingCrypto::new());
+        let old_key = crypto.private_key()?;
+        let new_key = Arc::new(crypto.private_key()?);
+        let url = Uri::try_from("https://example.com/acme/key-change")?;
+        let account = Uri::try_from("https://example.com/acme/acct/evOfKhNU60wg")?;
+
+        let jwk = |key: &RingKeyPair| {
+            let jwk = serde_json::to_value(key.public_key()).unwrap();
+            format!(
+                r#"{{"crv":"P-384","kty":"EC","x":{},"y":{}}}"#,
+                jwk["x"], jwk["y"]
+            )
+        };
+        let protected = format!(
+            r#"{{"alg":"ES384","jwk":{},"url":"https://example.com/acme/key-change"}}"#,
+            jwk(&new_key)
+        );
+        let payload = format!(
+            r#"{{"account":"https://example.com/acme/acct/evOfKhNU60wg","oldKey":{}}}"#,
+            jwk(&old_key)
+        );
+
+        let jws = key_change_jws(&crypto, &url, &account, &old_key, &new_key);
+        let (encoded_protected, encoded_payload) = jws.signing_input()?;
+        assert_eq!(
+            encoded_protected,
+            base64::encode_config(protected, base64::URL_SAFE_NO_PAD)
+        );
+        assert_eq!(
+            encoded_payload,
+            base64::encode_config(payload, base64::URL_SAFE_NO_PAD)
+        );
+
+        // the signature covers exactly these encodings and verifies with the new key
+        let jws = serde_json::to_value(&jws)?;
+        assert_eq!(jws["protected"], encoded_protected);
+        assert_eq!(jws["payload"], encoded_payload);
+        let signature = jws["signature"].as_str().unwrap_or_default();
+        let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD)?;
+        let signing_input = format!("{}.{}", encoded_protected, encoded_payload);
+        ring::signature::UnparsedPublicKey::new(
+            &ring::signature::ECDSA_P384_SHA384_FIXED,
+            new_key.public_point(),
+        )
+        .verify(signing_input.as_bytes(), &signature)
+        .map_err(|_| "signature doesn't verify")?;
+        Ok(())
+    }
+
+    #[test]
+    fn api_error_type() {
+        let err = |type_val| {
+            ErrorWrapper(Box::new(HyperAcmeServerError::ApiError(ApiError {
+                type_val,
+                detail: String::new(),
+                subproblems: Vec::new(),
+            })))
+        };
+        assert!(is_api_error(
+            &err(ApiErrorType::OrderNotReady),
+            ApiErrorType::OrderNotReady
+        ));
+        assert!(!is_api_error(
+            &err(ApiErrorType::BadCSR),
+            ApiErrorType::OrderNotReady
+        ));
+        assert!(!is_api_error(
+            &ErrorWrapper("other".into()),
+            ApiErrorType::OrderNotReady
+        ));
+
+        let error = DirectoryError::ServerError(err(ApiErrorType::RateLimited));
+        assert!(error.is_rate_limited() && error.is_retryable());
+        let error = DirectoryError::ServerError(ErrorWrapper("other".into()));
+        assert_eq!(error.class(), ErrorClass::Permanent);
+        let error = DirectoryError::InvalidAuthorization("example.com".into());
+        assert_eq!(error.class(), ErrorClass::Validation);
+
+        let error = DirectoryError::ServerError(err(ApiErrorType::RateLimited))
+            .with_identifier("example.com");
+        assert!(matches!(error, DirectoryError::Identifier(..)));
+        assert!(error.is_rate_limited());
+        assert!(matches!(
+            error.without_context(),
+            DirectoryError::ServerError(_)
+        ));
+    }
+
+    #[test]
+    fn challenge_error_is_typed() {
+        let authorization: ApiAuthorization = serde_json::from_value(serde_json::json!({
+            "identifier": { "type": "dns", "value": "example.com" },
+            "status": "invalid",
+            "challenges": [{
+                "type": "http-01",
+                "url": "https://ca/chall/1",
+                "status": "invalid",
+                "token": "token",
+                "error": {
+                    "type": "urn:ietf:params:acme:error:unauthorized",
+                    "detail": "wrong key authorization"
+                }
+            }]
+        }))
+        .unwrap();
+
+        match challenge_error("example.com", &authorization, "https://ca/chall/1") {
+            DirectoryError::ChallengeFailed(identifier, error) => {
+                assert_eq!(identifier, "example.com");
+                assert_eq!(error.detail, "wrong key authorization");
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert!(matches!(
+            challenge_error("example.com", &authorization, "https://ca/chall/2"),
+            DirectoryError::InvalidAuthorization(_)
+        ));
+    }
+
+    #[test]
+    fn default_poll_respects_retry_after() {
+        let poll = Limits::default().poll;
+        assert_eq!(poll.delay(0, None), VALIDATION_POLL_INTERVAL);
+        let retry_after = RetryAfter::Delay(Duration::from_secs(30));
+        assert_eq!(poll.delay(0, Some(&retry_after)), Duration::from_secs(30));
+        // a date in the past polls immediately
+        let retry_after = RetryAfter::Date(OffsetDateTime::UNIX_EPOCH);
+        assert_eq!(poll.delay(0, Some(&retry_after)), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn build_with_alpn_protocols() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+        let docker = Cli::default();
+        let stepca = Stepca::run(&docker, "alpn");
+
+        let root = include_bytes!("../../stepca/smallstep/certs/root_ca.crt");
+        let builder = Directory::builder()
+            .add_root_certificate(root)?
+            .alpn_protocols(vec![b"h2".to_vec(), b"http/1.1".to_vec()])?
+            .default()
+            .url(stepca.endpoint("/directory"));
+        builder.build().await?;
+
+        let res = Directory::builder().alpn_protocols(vec![b"acme-tls/1".to_vec()]);
+        assert!(matches!(res, Err(DirectoryError::UnsupportedAlpnProtocol)));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
         let docker = Cli::default();
@@ -567,29 +2598,29 @@ mod tests {
         let mut server_builder = HyperAcmeServer::builder();
         server_builder.url(endpoint).connector(stepca.connector()?);
 
-        let directory = Directory::builder()
-            .server(server_builder)
-            .default()
-            .build()
-            .await?;
+        let builder = Directory::builder().server(server_builder).default();
+        let directory = builder.build().await?;
+        // the builder is reusable
+        builder.build().await?;
         let mut account = directory.new_account("test@test.com").await?;
         account.change_mail("test2@test.com").await?;
 
-        let mut order = account.new_order("nginx").await?;
+        let order = account.new_order("nginx").await?;
         let mut authorizations = order.authorizations().await?;
         let authorization = &mut authorizations[0];
         let challenge = authorization.http_challenge().unwrap();
 
         let webserver = WebserverWithApi::new(&docker, "directory")?;
         webserver
-            .put_text(challenge.token(), challenge.proof()?)
+            .put_text(challenge.token(), challenge.key_authorization()?)
             .await?;
 
         challenge.validate().await?;
         authorization.update().await?;
 
-        let res = order.finalize().await?;
-        let res = String::from_utf8(res)?;
+        let order = order.ready().await?.finalize().await?;
+        let res = order.download().await?;
+        let res = String::from_utf8(res.chain)?;
         println!("{}", res);
 
         panic!("{:?}", order.inner);
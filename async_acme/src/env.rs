@@ -0,0 +1,142 @@
+use std::env;
+use std::path::PathBuf;
+
+#[cfg(feature = "rustls-tls")]
+use acme_core::ErrorWrapper;
+
+use crate::{Account, Directory, DirectoryError};
+
+const DIRECTORY_URL: &str = "ACME_DIRECTORY_URL";
+const EMAIL: &str = "ACME_EMAIL";
+const EAB_KID: &str = "ACME_EAB_KID";
+const EAB_HMAC_KEY: &str = "ACME_EAB_HMAC_KEY";
+const PERSIST_PATH: &str = "ACME_PERSIST_PATH";
+
+// configuration read from ACME_DIRECTORY_URL, ACME_EMAIL, ACME_EAB_KID, ACME_EAB_HMAC_KEY
+// and ACME_PERSIST_PATH, unset or empty variables are None
+#[derive(Debug, Clone, Default)]
+pub struct EnvConfig {
+    // let's encrypt if unset
+    pub directory_url: Option<String>,
+    pub mail: Option<String>,
+    // both or neither are set, the hmac key is base64url encoded
+    pub eab_kid: Option<String>,
+    pub eab_hmac_key: Option<String>,
+    // not used by the directory, e.g. the directory of a persist implementation
+    pub persist_path: Option<PathBuf>,
+}
+
+impl EnvConfig {
+    pub fn from_env() -> Result<Self, DirectoryError> {
+        Self::from_vars(|name| env::var_os(name).map(|value| value.into_string()))
+    }
+
+    fn from_vars<F>(var: F) -> Result<Self, DirectoryError>
+    where
+        F: Fn(&'static str) -> Option<Result<String, std::ffi::OsString>>,
+    {
+        let string = |name| match var(name) {
+            Some(Ok(value)) if value.is_empty() => Ok(None),
+            Some(Ok(value)) => Ok(Some(value)),
+            Some(Err(_)) => Err(DirectoryError::InvalidEnv(name)),
+            None => Ok(None),
+        };
+
+        let config = Self {
+            directory_url: string(DIRECTORY_URL)?,
+            mail: string(EMAIL)?,
+            eab_kid: string(EAB_KID)?,
+            eab_hmac_key: string(EAB_HMAC_KEY)?,
+            persist_path: string(PERSIST_PATH)?.map(PathBuf::from),
+        };
+
+        match (&config.eab_kid, &config.eab_hmac_key) {
+            (Some(_), None) => Err(DirectoryError::InvalidEnv(EAB_HMAC_KEY)),
+            (None, Some(_)) => Err(DirectoryError::InvalidEnv(EAB_KID)),
+            _ => Ok(config),
+        }
+    }
+
+    // uses the default rustls server
+    #[cfg(feature = "rustls-tls")]
+    pub async fn directory(&self) -> Result<Directory, DirectoryError> {
+        let builder = Directory::builder().default();
+        let builder = match &self.directory_url {
+            Some(url) => builder.url(url.clone()),
+            None => builder.default(),
+        };
+
+        builder
+            .build()
+            .await
+            .map_err(|err| ErrorWrapper(Box::new(err)).into())
+    }
+
+    // registers an account with the configured mail and external account binding,
+    // an existing account is returned if the key was registered before
+    pub async fn register<'a>(
+        &self,
+        directory: &'a Directory,
+    ) -> Result<Account<'a>, DirectoryError> {
+        let mut builder = directory.account_builder().agree_terms_of_service();
+        if let Some(mail) = &self.mail {
+            builder = builder.mail(mail);
+        }
+        if let (Some(kid), Some(hmac_key)) = (&self.eab_kid, &self.eab_hmac_key) {
+            builder = builder.external_account_binding(kid.clone(), hmac_key.clone());
+        }
+
+        builder.register().await
+    }
+}
+
+impl Directory {
+    // reads the EnvConfig and builds a directory with the default rustls server,
+    // the config is returned to register an account, see EnvConfig::register
+    #[cfg(feature = "rustls-tls")]
+    pub async fn from_env() -> Result<(Directory, EnvConfig), DirectoryError> {
+        let config = EnvConfig::from_env()?;
+        let directory = config.directory().await?;
+
+        Ok((directory, config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&'static str, &str)]) -> Result<EnvConfig, DirectoryError> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        EnvConfig::from_vars(|name| vars.get(name).map(|value| Ok(value.to_string())))
+    }
+
+    #[test]
+    fn reads_vars() {
+        let config = config(&[
+            (DIRECTORY_URL, "https://ca/directory"),
+            (EMAIL, ""),
+            (EAB_KID, "kid"),
+            (EAB_HMAC_KEY, "key"),
+            (PERSIST_PATH, "/var/lib/acme"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            config.directory_url.as_deref(),
+            Some("https://ca/directory")
+        );
+        assert_eq!(config.mail, None);
+        assert_eq!(config.eab_kid.as_deref(), Some("kid"));
+        assert_eq!(config.persist_path, Some(PathBuf::from("/var/lib/acme")));
+    }
+
+    #[test]
+    fn incomplete_eab() {
+        assert!(matches!(
+            config(&[(EAB_KID, "kid")]),
+            Err(DirectoryError::InvalidEnv(EAB_HMAC_KEY))
+        ));
+    }
+}
@@ -0,0 +1,50 @@
+use acme_core::ApiChallengeType;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use crate::DirectoryError;
+
+// called by the directory during issuance, for metrics, logging or ui updates
+// the callbacks should return quickly as they run inline
+pub trait AcmeEvents: Send + Sync + 'static {
+    fn on_order_created(&self, _domains: &[String]) {}
+
+    fn on_challenge_validated(&self, _domain: &str, _challenge_type: &ApiChallengeType) {}
+
+    fn on_finalized(&self, _domains: &[String]) {}
+
+    fn on_error(&self, _error: &DirectoryError) {}
+}
+
+struct NoEvents;
+
+impl AcmeEvents for NoEvents {}
+
+#[derive(Clone)]
+pub(crate) struct Events(Arc<dyn AcmeEvents>);
+
+impl Events {
+    pub(crate) fn new<E: AcmeEvents>(events: E) -> Self {
+        Self(Arc::new(events))
+    }
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self::new(NoEvents)
+    }
+}
+
+impl std::ops::Deref for Events {
+    type Target = dyn AcmeEvents;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl Debug for Events {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Events").finish()
+    }
+}
@@ -0,0 +1,156 @@
+use acme_core::ApiChallengeType;
+use async_trait::async_trait;
+use hyper::http::Method;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::rest::RestClient;
+use crate::{BoxError, ChallengeProof, Runtime, Solver};
+
+const HETZNER_API: &str = "https://dns.hetzner.com/api/v1";
+const TTL: u32 = 60;
+
+#[derive(Deserialize)]
+struct ApiZones {
+    zones: Vec<ApiZone>,
+}
+
+#[derive(Deserialize)]
+struct ApiZone {
+    id: String,
+    name: String,
+}
+
+#[derive(Serialize)]
+struct ApiNewRecord<'a> {
+    zone_id: &'a str,
+    #[serde(rename = "type")]
+    type_val: &'static str,
+    name: &'a str,
+    value: &'a str,
+    ttl: u32,
+}
+
+#[derive(Deserialize)]
+struct ApiRecordWrapper {
+    record: ApiRecord,
+}
+
+#[derive(Deserialize)]
+struct ApiRecord {
+    id: String,
+}
+
+// dns-01 solver for zones hosted on hetzner dns
+pub struct HetznerSolver {
+    client: RestClient,
+    // record ids by name and value, hetzner manages every txt value as its own record
+    records: Mutex<HashMap<(String, String), String>>,
+}
+
+impl HetznerSolver {
+    pub fn new(token: &str) -> Result<Self, BoxError> {
+        Self::with_url(HETZNER_API, token)
+    }
+
+    pub fn with_url(url: &str, token: &str) -> Result<Self, BoxError> {
+        Ok(Self {
+            client: RestClient::new(url, "auth-api-token", token)?,
+            records: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // runs the request timeouts, the hyper client still needs tokio
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.client.runtime(runtime);
+        self
+    }
+
+    // walks up the labels until a zone matches
+    async fn zone(&self, name: &str) -> Result<ApiZone, BoxError> {
+        let mut candidate = name;
+        while let Some((_, parent)) = candidate.split_once('.') {
+            let zones: ApiZones = self.client.get(&format!("/zones?name={}", parent)).await?;
+            if let Some(zone) = zones.zones.into_iter().find(|zone| zone.name == parent) {
+                return Ok(zone);
+            }
+            candidate = parent;
+        }
+
+        Err(format!("no zone found for {}", name).into())
+    }
+}
+
+impl Debug for HetznerSolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HetznerSolver").finish()
+    }
+}
+
+// record names are relative to the zone
+fn relative_name<'a>(name: &'a str, zone: &str) -> &'a str {
+    match name.strip_suffix(zone) {
+        Some("") => "@",
+        Some(name) => name.trim_end_matches('.'),
+        None => name,
+    }
+}
+
+#[async_trait]
+impl Solver for HetznerSolver {
+    fn challenge_type(&self) -> ApiChallengeType {
+        ApiChallengeType::DNS
+    }
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let name = proof.dns_name();
+        let value = proof.dns_value();
+        let zone = self.zone(&name).await?;
+
+        let record = ApiNewRecord {
+            zone_id: &zone.id,
+            type_val: "TXT",
+            name: relative_name(&name, &zone.name),
+            value: &value,
+            ttl: TTL,
+        };
+        let res = self
+            .client
+            .send(Method::POST, "/records", Some(&record))
+            .await?;
+        let record: ApiRecordWrapper = serde_json::from_slice(&res)?;
+
+        self.records.lock().insert((name, value), record.record.id);
+        Ok(())
+    }
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let key = (proof.dns_name(), proof.dns_value());
+        let id = match self.records.lock().remove(&key) {
+            Some(id) => id,
+            // present failed before the record was created
+            None => return Ok(()),
+        };
+
+        self.client
+            .send::<()>(Method::DELETE, &format!("/records/{}", id), None)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_relative_to_zone() {
+        assert_eq!(
+            relative_name("_acme-challenge.www.example.com", "example.com"),
+            "_acme-challenge.www"
+        );
+        assert_eq!(relative_name("example.com", "example.com"), "@");
+    }
+}
@@ -0,0 +1,120 @@
+use acme_core::ApiChallengeType;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use tokio::process::Command;
+
+use crate::{BoxError, ChallengeProof, Solver};
+
+// runs the deploy and cleanup commands with the environment of certbots manual hooks
+// so existing --manual-auth-hook and --manual-cleanup-hook scripts can be reused
+#[derive(Debug)]
+pub struct HookSolver {
+    challenge_type: ApiChallengeType,
+    deploy: String,
+    cleanup: String,
+    // stdout of the deploy hook by token, passed to the cleanup hook
+    output: Mutex<HashMap<String, String>>,
+}
+
+impl HookSolver {
+    pub fn new<D: Into<String>, C: Into<String>>(
+        challenge_type: ApiChallengeType,
+        deploy: D,
+        cleanup: C,
+    ) -> Self {
+        Self {
+            challenge_type,
+            deploy: deploy.into(),
+            cleanup: cleanup.into(),
+            output: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn command(&self, command: &str, proof: &ChallengeProof) -> Command {
+        #[cfg(unix)]
+        let mut cmd = {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(command);
+            cmd
+        };
+        #[cfg(not(unix))]
+        let mut cmd = {
+            let mut cmd = Command::new("cmd");
+            cmd.arg("/C").arg(command);
+            cmd
+        };
+
+        let validation = match self.challenge_type {
+            ApiChallengeType::DNS => proof.dns_value(),
+            _ => proof.key_authorization.clone(),
+        };
+        cmd.env("CERTBOT_DOMAIN", &proof.domain)
+            .env("CERTBOT_VALIDATION", validation)
+            .env("CERTBOT_TOKEN", &proof.token)
+            .env("CERTBOT_KEY_AUTHORIZATION", &proof.key_authorization)
+            .kill_on_drop(true);
+
+        cmd
+    }
+
+    async fn run(mut cmd: Command) -> Result<String, BoxError> {
+        let output = cmd.output().await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("hook failed with {}: {}", output.status, stderr.trim()).into());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    }
+}
+
+#[async_trait]
+impl Solver for HookSolver {
+    fn challenge_type(&self) -> ApiChallengeType {
+        self.challenge_type.clone()
+    }
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let output = Self::run(self.command(&self.deploy, proof)).await?;
+        self.output.lock().insert(proof.token.clone(), output);
+
+        Ok(())
+    }
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let output = self.output.lock().remove(&proof.token).unwrap_or_default();
+        let mut cmd = self.command(&self.cleanup, proof);
+        cmd.env("CERTBOT_AUTH_OUTPUT", output);
+
+        Self::run(cmd).await?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn passes_environment() -> Result<(), BoxError> {
+        let solver = HookSolver::new(
+            ApiChallengeType::HTTP,
+            "echo $CERTBOT_DOMAIN $CERTBOT_VALIDATION",
+            "test \"$CERTBOT_AUTH_OUTPUT\" = \"example.com token.thumbprint\"",
+        );
+        let proof = ChallengeProof {
+            domain: "example.com".into(),
+            token: "token".into(),
+            key_authorization: "token.thumbprint".into(),
+        };
+
+        solver.present(&proof).await?;
+        solver.cleanup(&proof).await?;
+
+        let failing = HookSolver::new(ApiChallengeType::HTTP, "exit 1", "true");
+        assert!(failing.present(&proof).await.is_err());
+
+        Ok(())
+    }
+}
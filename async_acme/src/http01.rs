@@ -0,0 +1,110 @@
+use hyper::http::header::CONTENT_TYPE;
+use hyper::http::{HeaderValue, StatusCode};
+use hyper::{Body, Request, Response};
+use std::convert::Infallible;
+use std::future::{self, Ready};
+use std::task::{Context, Poll};
+use tower::Service;
+
+use crate::tokens::CHALLENGE_PATH;
+use crate::ChallengeTokenStore;
+
+// answers http-01 validations, route /.well-known/acme-challenge/ to it or nest it there, e.g.
+// with nest_service of axum
+#[derive(Debug, Clone)]
+pub struct Http01Service {
+    tokens: ChallengeTokenStore,
+}
+
+impl Http01Service {
+    pub fn new(tokens: ChallengeTokenStore) -> Self {
+        Self { tokens }
+    }
+
+    // nested services only see the path after the prefix, tokens never contain a slash
+    fn respond(&self, path: &str) -> Response<Body> {
+        let token = path
+            .strip_prefix(CHALLENGE_PATH)
+            .or_else(|| path.strip_prefix('/'));
+        match token.and_then(|token| self.tokens.get(token)) {
+            Some(key_authorization) => {
+                let mut res = Response::new(Body::from(key_authorization));
+                res.headers_mut().insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("application/octet-stream"),
+                );
+                res
+            }
+            None => {
+                let mut res = Response::new(Body::empty());
+                *res.status_mut() = StatusCode::NOT_FOUND;
+                res
+            }
+        }
+    }
+}
+
+impl<B> Service<Request<B>> for Http01Service {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        future::ready(Ok(self.respond(req.uri().path())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn get(service: &Http01Service, path: &str) -> (StatusCode, String) {
+        let req = Request::get(path).body(Body::empty()).unwrap();
+        let res = service.clone().oneshot(req).await.unwrap();
+        let status = res.status();
+        let body = body::to_bytes(res.into_body()).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn serves_tokens() {
+        let tokens = ChallengeTokenStore::new();
+        tokens.insert("token", "token.thumbprint");
+        let service = Http01Service::new(tokens);
+
+        assert_eq!(
+            get(&service, "/.well-known/acme-challenge/token").await,
+            (StatusCode::OK, "token.thumbprint".into())
+        );
+        assert_eq!(
+            get(&service, "/.well-known/acme-challenge/other").await.0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            get(&service, "/.well-known/other/token").await.0,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_nested_tokens() {
+        let tokens = ChallengeTokenStore::new();
+        tokens.insert("token", "token.thumbprint");
+        let service = Http01Service::new(tokens);
+
+        // nest_service of axum strips /.well-known/acme-challenge
+        assert_eq!(
+            get(&service, "/token").await,
+            (StatusCode::OK, "token.thumbprint".into())
+        );
+        assert_eq!(get(&service, "/other").await.0, StatusCode::NOT_FOUND);
+    }
+}
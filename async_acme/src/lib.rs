@@ -1,8 +1,120 @@
+mod accounts;
+mod ari;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod cancel;
+mod capture;
+mod chain;
+mod client;
+mod credentials;
 mod crypto;
+#[cfg(feature = "rustls-tls")]
+mod ct;
+mod deadline;
+#[cfg(feature = "rustls-tls")]
+mod desec;
 mod directory;
+mod env;
+mod events;
+#[cfg(feature = "rustls-tls")]
+mod hetzner;
+mod hook;
+mod http01;
+mod limiter;
+mod manager;
+mod manual;
+#[cfg(feature = "s3")]
+mod object_storage;
+mod ocsp;
+mod offline;
 mod persist;
+mod poll;
+#[cfg(feature = "dns-check")]
+mod propagation;
+#[cfg(feature = "quic")]
+mod quic;
+mod rate_limit;
+mod resolve;
+#[cfg(feature = "rustls-tls")]
+mod rest;
+mod retry;
+#[cfg(feature = "aws")]
+mod route53;
+mod runtime;
+mod sct;
 mod server;
+mod service;
+#[cfg(feature = "rustls-tls")]
+mod sni;
+mod solver;
+mod standalone;
+mod store;
+mod stream;
+#[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+mod tls;
+#[cfg(feature = "tls-alpn")]
+mod tls_alpn;
+mod tokens;
+#[cfg(unix)]
+mod unix;
+#[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+mod webhook;
 
+pub use accounts::*;
+// the dtos and server traits so acme_core isn't needed as a direct dependency
+pub use acme_core::dto::*;
+pub use acme_core::response::{AcmeResponse, Link, RetryAfter};
+pub use acme_core::server::dynamic::{DynAcmeServer, ErrorWrapper};
+pub use acme_core::server::{AcmeServer, AcmeServerBuilder, AcmeServerExt, ByteStream};
+pub use cancel::*;
+pub use capture::*;
+pub use client::*;
+pub use credentials::*;
+pub use crypto::KeyAlgorithm;
+#[cfg(feature = "rustls-tls")]
+pub use ct::*;
+pub use deadline::*;
+#[cfg(feature = "rustls-tls")]
+pub use desec::*;
 pub use directory::*;
+pub use env::*;
+pub use events::*;
+#[cfg(feature = "rustls-tls")]
+pub use hetzner::*;
+pub use hook::*;
+pub use http01::*;
+pub use limiter::*;
+pub use manager::*;
+pub use manual::*;
+#[cfg(feature = "s3")]
+pub use object_storage::*;
+pub use ocsp::*;
+pub use offline::*;
 pub use persist::*;
+pub use poll::*;
+#[cfg(feature = "dns-check")]
+pub use propagation::*;
+#[cfg(feature = "quic")]
+pub use quic::*;
+pub use rate_limit::*;
+pub use resolve::*;
+pub use retry::*;
+#[cfg(feature = "aws")]
+pub use route53::*;
+pub use runtime::*;
+pub use sct::*;
 pub use server::*;
+pub use service::*;
+#[cfg(feature = "rustls-tls")]
+pub use sni::*;
+pub use solver::*;
+pub use standalone::*;
+pub use store::*;
+pub use stream::*;
+#[cfg(feature = "tls-alpn")]
+pub use tls_alpn::*;
+pub use tokens::*;
+#[cfg(unix)]
+pub use unix::*;
+#[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+pub use webhook::*;
@@ -0,0 +1,113 @@
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::runtime::default_runtime;
+use crate::Runtime;
+
+// token bucket shared between all clones of a directory
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    inner: Arc<Mutex<Bucket>>,
+    runtime: Arc<dyn Runtime>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    tokens: f64,
+    // time it takes to refill a single token
+    refill: Duration,
+    last: Instant,
+}
+
+impl Bucket {
+    fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() / self.refill.as_secs_f64()).min(self.capacity);
+        self.last = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(self.refill.mul_f64(1.0 - self.tokens))
+    }
+}
+
+impl RateLimiter {
+    // allows a burst of capacity requests, refilled evenly over period
+    pub fn new(capacity: u32, period: Duration) -> Self {
+        let capacity = capacity.max(1);
+        let bucket = Bucket {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill: period / capacity,
+            last: Instant::now(),
+        };
+
+        Self {
+            inner: Arc::new(Mutex::new(bucket)),
+            runtime: default_runtime(),
+        }
+    }
+
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.runtime = Arc::new(runtime);
+        self
+    }
+
+    // see https://letsencrypt.org/docs/rate-limits/
+    pub fn le_new_orders() -> Self {
+        Self::new(300, Duration::from_secs(3 * 60 * 60))
+    }
+
+    pub fn le_new_accounts() -> Self {
+        Self::new(10, Duration::from_secs(3 * 60 * 60))
+    }
+
+    pub async fn acquire(&self) {
+        loop {
+            // the lock can't be held across the sleep
+            let res = self.inner.lock().try_acquire(Instant::now());
+            match res {
+                Ok(()) => return,
+                Err(delay) => self.runtime.sleep(delay).await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(10));
+        let mut bucket = limiter.inner.lock();
+        let now = bucket.last;
+
+        assert_eq!(bucket.try_acquire(now), Ok(()));
+        assert_eq!(bucket.try_acquire(now), Ok(()));
+        assert_eq!(bucket.try_acquire(now), Err(Duration::from_secs(5)));
+
+        let now = now + Duration::from_millis(2500);
+        assert_eq!(bucket.try_acquire(now), Err(Duration::from_millis(2500)));
+
+        let now = now + Duration::from_millis(2500);
+        assert_eq!(bucket.try_acquire(now), Ok(()));
+    }
+
+    #[test]
+    fn bucket_does_not_exceed_capacity() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(1));
+        let mut bucket = limiter.inner.lock();
+        let now = bucket.last + Duration::from_secs(60);
+
+        assert_eq!(bucket.try_acquire(now), Ok(()));
+        assert!(bucket.try_acquire(now).is_err());
+    }
+}
@@ -0,0 +1,868 @@
+use acme_core::{ApiRevocationReason, ApiSuggestedWindow, Uri};
+use futures_util::future::AbortHandle;
+use hyper::http::uri::InvalidUri;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::ari;
+use crate::chain::leaf_certificate;
+use crate::crypto::{Crypto, KeyPair, RingCrypto, RingCryptoError, RingKeyPair};
+use crate::runtime::spawn_abortable;
+#[cfg(feature = "rustls-tls")]
+use crate::SniResolver;
+use crate::{
+    Account, BoxError, CancellationToken, CertificateBundle, CertificateStore, DataType, Directory,
+    DirectoryError, KeyAlgorithm, OcspChecker, OcspStatus, Persist, RetryPolicy, SolverChain,
+    StandaloneHttp01, StoreError,
+};
+#[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+use crate::{CertificateEvent, WebhookNotifier};
+
+// used as persist key if the directory url is unknown
+const ACCOUNT_KEY: &str = "account";
+// a third of the lifetime of let's encrypt certificates
+const RENEW_BEFORE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+#[derive(Debug, Error)]
+pub enum ManagerError {
+    #[error(transparent)]
+    Directory(#[from] DirectoryError),
+    #[error("{0}")]
+    Persist(BoxError),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error("Stored certificate for {0} can't be parsed")]
+    InvalidCertificate(String),
+    #[error("No certificate stored for {0}")]
+    MissingCertificate(String),
+    #[error(transparent)]
+    InvalidUri(#[from] InvalidUri),
+    #[error(transparent)]
+    RingCryptoError(#[from] RingCryptoError),
+    #[error(transparent)]
+    Hyper(#[from] hyper::Error),
+    #[error("Dry run against staging failed: {0}")]
+    DryRun(DirectoryError),
+}
+
+impl ManagerError {
+    fn is_transient(&self) -> bool {
+        match self {
+            ManagerError::Directory(err) | ManagerError::DryRun(err) => err.is_transient(),
+            _ => false,
+        }
+    }
+}
+
+// overrides the manager defaults for a single domain
+#[derive(Debug, Clone, Default)]
+pub struct RenewalPolicy {
+    renew_before: Option<Duration>,
+    solvers: Option<SolverChain>,
+    key_algorithm: Option<KeyAlgorithm>,
+    directory: Option<Directory>,
+}
+
+impl RenewalPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn renew_before(mut self, renew_before: Duration) -> Self {
+        self.renew_before = Some(renew_before);
+        self
+    }
+
+    // the challenge type follows from the solvers, e.g. dns-01 for wildcards
+    pub fn solvers(mut self, solvers: SolverChain) -> Self {
+        self.solvers = Some(solvers);
+        self
+    }
+
+    pub fn key_algorithm(mut self, key_algorithm: KeyAlgorithm) -> Self {
+        self.key_algorithm = Some(key_algorithm);
+        self
+    }
+
+    // e.g. an internal step-ca, an account is registered per directory
+    pub fn directory(mut self, directory: Directory) -> Self {
+        self.directory = Some(directory);
+        self
+    }
+}
+
+// issues and renews the certificates of the configured domains, using http-01 unless the policy
+// of a domain says otherwise
+#[derive(Debug)]
+pub struct CertificateManager<P> {
+    directory: Directory,
+    persist: P,
+    store: CertificateStore<P>,
+    mail: String,
+    domains: Vec<String>,
+    policies: HashMap<String, RenewalPolicy>,
+    renew_before: Duration,
+    interval: Duration,
+    tokens: ChallengeTokenStore,
+    standalone: Option<SocketAddr>,
+    ocsp: bool,
+    key_rotation: Option<Duration>,
+    #[cfg(feature = "rustls-tls")]
+    resolver: Option<SniResolver>,
+    cancellation: Option<CancellationToken>,
+    retry: Option<Arc<dyn RetryPolicy>>,
+    dry_run: Option<Directory>,
+    // by persist key of the directory
+    accounts: Mutex<HashMap<String, Account<'static>>>,
+    // picked once per certificate and window, by ari cert id
+    renewal_times: Mutex<HashMap<String, (ApiSuggestedWindow, OffsetDateTime)>>,
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    webhooks: Option<WebhookNotifier>,
+}
+
+impl<P: Persist + Send + Sync> CertificateManager<P> {
+    pub fn new<T: Into<String>>(directory: Directory, persist: P, mail: T) -> Self {
+        Self {
+            directory,
+            store: CertificateStore::new(persist.clone()),
+            persist,
+            mail: mail.into(),
+            domains: Vec::new(),
+            policies: HashMap::new(),
+            renew_before: RENEW_BEFORE,
+            interval: INTERVAL,
+            tokens: ChallengeTokenStore::new(),
+            standalone: None,
+            ocsp: false,
+            key_rotation: None,
+            #[cfg(feature = "rustls-tls")]
+            resolver: None,
+            cancellation: None,
+            retry: None,
+            dry_run: None,
+            accounts: Mutex::new(HashMap::new()),
+            renewal_times: Mutex::new(HashMap::new()),
+            #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+            webhooks: None,
+        }
+    }
+
+    pub fn domain<T: Into<String>>(mut self, domain: T) -> Self {
+        self.domains.push(domain.into());
+        self
+    }
+
+    pub fn domain_with_policy<T: Into<String>>(mut self, domain: T, policy: RenewalPolicy) -> Self {
+        let domain = domain.into();
+        if !self.domains.contains(&domain) {
+            self.domains.push(domain.clone());
+        }
+        self.policies.insert(domain, policy);
+        self
+    }
+
+    pub fn renew_before(mut self, renew_before: Duration) -> Self {
+        self.renew_before = renew_before;
+        self
+    }
+
+    // time between renewal checks in run
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    // serves the tokens on addr while validating instead of relying on an existing server
+    pub fn standalone(mut self, addr: SocketAddr) -> Self {
+        self.standalone = Some(addr);
+        self
+    }
+
+    // reissues certificates the responder reports as revoked, checked on every renewal check
+    pub fn ocsp(mut self) -> Self {
+        self.ocsp = true;
+        self
+    }
+
+    // retries failed issuances within one renewal run, by default they are retried after the interval
+    pub fn retry<R: RetryPolicy + 'static>(mut self, retry: R) -> Self {
+        self.retry = Some(Arc::new(retry));
+        self
+    }
+
+    // issues every certificate against staging first, e.g. a directory built with le_staging,
+    // so misconfigured solvers don't burn the rate limits of the production directory
+    pub fn dry_run(mut self, staging: Directory) -> Self {
+        self.dry_run = Some(staging);
+        self
+    }
+
+    // aborts waiting on the CA on shutdown, presented challenges are still cleaned up
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    // rolls the account keys over once they are older than every, checked before renewing
+    pub fn rotate_account_key(mut self, every: Duration) -> Self {
+        self.key_rotation = Some(every);
+        self
+    }
+
+    // gets every issued certificate, load the stored ones with SniResolver::load on startup
+    #[cfg(feature = "rustls-tls")]
+    pub fn sni_resolver(mut self, resolver: SniResolver) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    // notified about issued, renewed, expiring, revoked and failed certificates
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    pub fn webhooks(mut self, webhooks: WebhookNotifier) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    // has to be served under /.well-known/acme-challenge/{token}
+    pub fn tokens(&self) -> &ChallengeTokenStore {
+        &self.tokens
+    }
+
+    pub async fn certificate(
+        &self,
+        domain: &str,
+    ) -> Result<Option<CertificateBundle>, ManagerError> {
+        Ok(self.store.get(&[domain]).await?)
+    }
+
+    pub fn store(&self) -> &CertificateStore<P> {
+        &self.store
+    }
+
+    // issues missing certificates and renews expiring ones, returns the last error
+    pub async fn renew(&self) -> Result<(), ManagerError> {
+        let mut res = Ok(());
+        let rotation = self
+            .renew_all(|_, renewal| {
+                if let Err(err) = renewal {
+                    res = Err(err);
+                }
+            })
+            .await;
+
+        res.and(rotation)
+    }
+
+    // rotates the account keys, renews every domain and notifies about expiring certificates,
+    // the error of the rotation is returned
+    async fn renew_all<F>(&self, mut report: F) -> Result<(), ManagerError>
+    where
+        F: FnMut(&str, Result<Renewal, ManagerError>),
+    {
+        let rotation = self.rotate_account_keys().await;
+        if let Err(err) = &rotation {
+            warn!(error = %err, "account key rotation failed");
+        }
+
+        for domain in &self.domains {
+            let renewal = self.renew_domain(domain).await;
+            if let Err(err) = &renewal {
+                warn!(%domain, error = %err, "renewal failed");
+            }
+            report(domain, renewal);
+        }
+        #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+        self.notify_expiring().await;
+
+        rotation
+    }
+
+    // failed renewals are retried after the interval, only returns once cancelled
+    pub async fn run(&self) {
+        loop {
+            let _ = self.renew().await;
+            if !self.wait_interval().await {
+                return;
+            }
+        }
+    }
+
+    // false if cancelled
+    async fn wait_interval(&self) -> bool {
+        match &self.cancellation {
+            Some(token) => token.sleep(self.directory.runtime(), self.interval).await,
+            None => {
+                self.directory.runtime().sleep(self.interval).await;
+                true
+            }
+        }
+    }
+
+    async fn renew_domain(&self, domain: &str) -> Result<Renewal, ManagerError> {
+        let renew_before = self
+            .policy(domain)
+            .and_then(|policy| policy.renew_before)
+            .unwrap_or(self.renew_before);
+
+        let existing = self.certificate(domain).await?;
+        let renewal = match &existing {
+            Some(bundle) => {
+                let expiring = needs_renewal(&bundle.chain, renew_before)
+                    .ok_or_else(|| ManagerError::InvalidCertificate(domain.to_owned()))?;
+                match expiring {
+                    true => Renewal::Renewed,
+                    false => match self.early_reissue(domain, &bundle.chain).await {
+                        Some(reason) => Renewal::Reissued(reason),
+                        None => return Ok(Renewal::Skipped),
+                    },
+                }
+            }
+            None => Renewal::Renewed,
+        };
+
+        if let Renewal::Reissued(reason) = renewal {
+            warn!(%domain, ?reason, "reissuing certificate early");
+        }
+        self.reissue(domain, existing.is_some()).await?;
+        Ok(renewal)
+    }
+
+    // errors are only logged, the lifetime still triggers the renewal
+    async fn early_reissue(&self, domain: &str, chain: &[u8]) -> Option<ReissueReason> {
+        if self.ocsp {
+            let ocsp = OcspChecker::from_directory(self.directory(domain));
+            match ocsp.check(chain).await {
+                Ok(OcspStatus::Revoked) => return Some(ReissueReason::Revoked),
+                Ok(_) => {}
+                Err(err) => warn!(%domain, error = %err, "ocsp check failed"),
+            }
+        }
+
+        match self.directory(domain).renewal_info(chain).await {
+            Ok(Some(info)) => {
+                let cert_id = ari::cert_id(chain).unwrap_or_else(|| domain.to_owned());
+                let renew_at = self.renewal_time(&cert_id, info.suggested_window);
+                if OffsetDateTime::now_utc() < renew_at {
+                    return None;
+                }
+                self.renewal_times.lock().remove(&cert_id);
+                Some(ReissueReason::RenewalInfo)
+            }
+            Ok(None) => None,
+            Err(err) => {
+                warn!(%domain, error = %err, "fetching renewal info failed");
+                None
+            }
+        }
+    }
+
+    // the CA may move the window, a new time is only picked if it did
+    fn renewal_time(&self, cert_id: &str, window: ApiSuggestedWindow) -> OffsetDateTime {
+        let mut renewal_times = self.renewal_times.lock();
+        match renewal_times.get(cert_id) {
+            Some((stored, renew_at)) if *stored == window => *renew_at,
+            _ => {
+                let renew_at = ari::renewal_time(&window);
+                renewal_times.insert(cert_id.to_owned(), (window, renew_at));
+                renew_at
+            }
+        }
+    }
+
+    // places a new order regardless of the remaining lifetime, e.g. after the key type changed,
+    // the stored certificate is only replaced once the new one is issued
+    pub async fn force_renew(&self, domain: &str) -> Result<CertificateBundle, ManagerError> {
+        let existing = self.certificate(domain).await?;
+        self.reissue(domain, existing.is_some()).await
+    }
+
+    // for a leaked private key: revokes the stored certificate with keyCompromise first so it
+    // stops being trusted as soon as possible, then issues a replacement with a new key
+    pub async fn revoke_and_reissue(
+        &self,
+        domain: &str,
+    ) -> Result<CertificateBundle, ManagerError> {
+        let existing = self
+            .certificate(domain)
+            .await?
+            .ok_or_else(|| ManagerError::MissingCertificate(domain.to_owned()))?;
+
+        let account = self.account(self.directory(domain)).await?;
+        warn!(%domain, "revoking certificate because of key compromise");
+        account
+            .revoke(&existing.chain, Some(ApiRevocationReason::KeyCompromise))
+            .await?;
+
+        #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+        if let Some(webhooks) = &self.webhooks {
+            let event = CertificateEvent::Revoked {
+                domains: vec![domain.to_owned()],
+            };
+            if let Err(err) = webhooks.notify(&event).await {
+                warn!(%domain, error = %err, "webhook failed");
+            }
+        }
+
+        // every order generates a new key, the compromised one is never reused
+        self.reissue(domain, true).await
+    }
+
+    async fn reissue(
+        &self,
+        domain: &str,
+        renewal: bool,
+    ) -> Result<CertificateBundle, ManagerError> {
+        info!(%domain, "issuing certificate");
+        let res = match self.issue(domain).await {
+            Ok(bundle) => self
+                .store
+                .put(&[domain], bundle.clone())
+                .await
+                .map(|_| bundle)
+                .map_err(ManagerError::from),
+            Err(err) => Err(err),
+        };
+
+        #[cfg(feature = "rustls-tls")]
+        if let (Ok(bundle), Some(resolver)) = (&res, &self.resolver) {
+            resolver.insert(bundle);
+        }
+        #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+        self.notify(domain, renewal, &res).await;
+        #[cfg(not(any(feature = "rustls-tls", feature = "native-tls")))]
+        let _ = renewal;
+
+        res
+    }
+
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    async fn notify(
+        &self,
+        domain: &str,
+        renewal: bool,
+        res: &Result<CertificateBundle, ManagerError>,
+    ) {
+        let webhooks = match &self.webhooks {
+            Some(webhooks) => webhooks,
+            None => return,
+        };
+
+        let domains = vec![domain.to_owned()];
+        let event = match res {
+            Ok(bundle) => {
+                let not_after = not_after(&bundle.chain).unwrap_or_default();
+                match renewal {
+                    true => CertificateEvent::Renewed { domains, not_after },
+                    false => CertificateEvent::Issued { domains, not_after },
+                }
+            }
+            Err(err) => CertificateEvent::Failed {
+                domains,
+                error: err.to_string(),
+            },
+        };
+
+        if let Err(err) = webhooks.notify(&event).await {
+            warn!(%domain, error = %err, "webhook failed");
+        }
+    }
+
+    // runs after the renewals, so only certificates whose renewal failed or was skipped are
+    // still within their renew before window
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    async fn notify_expiring(&self) {
+        let webhooks = match &self.webhooks {
+            Some(webhooks) => webhooks,
+            None => return,
+        };
+
+        let window = self
+            .policies
+            .values()
+            .filter_map(|policy| policy.renew_before)
+            .fold(self.renew_before, Duration::max);
+        let expiring = match self.store.scan_expiring(window).await {
+            Ok(expiring) => expiring,
+            Err(err) => {
+                warn!(error = %err, "scanning for expiring certificates failed");
+                return;
+            }
+        };
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        for certificate in expiring {
+            let renew_before = certificate
+                .domains
+                .iter()
+                .filter_map(|domain| self.policy(domain)?.renew_before)
+                .fold(self.renew_before, Duration::max);
+            if certificate.not_after - now > renew_before.as_secs() as i64 {
+                continue;
+            }
+
+            let event = CertificateEvent::Expiring {
+                domains: certificate.domains,
+                not_after: certificate.not_after,
+            };
+            if let Err(err) = webhooks.notify(&event).await {
+                warn!(error = %err, "webhook failed");
+            }
+        }
+    }
+
+    fn policy(&self, domain: &str) -> Option<&RenewalPolicy> {
+        self.policies.get(domain)
+    }
+
+    fn directory(&self, domain: &str) -> &Directory {
+        self.policy(domain)
+            .and_then(|policy| policy.directory.as_ref())
+            .unwrap_or(&self.directory)
+    }
+
+    async fn issue(&self, domain: &str) -> Result<CertificateBundle, ManagerError> {
+        let mut attempt = 0;
+        loop {
+            let err = match self.issue_once(domain).await {
+                Ok(bundle) => return Ok(bundle),
+                Err(err) => err,
+            };
+
+            let delay = self
+                .retry
+                .as_ref()
+                .and_then(|retry| retry.retry(attempt, err.is_transient(), &err));
+            match delay {
+                Some(delay) => {
+                    warn!(%domain, attempt, ?delay, error = %err, "retrying issuance");
+                    self.directory(domain).runtime().sleep(delay).await;
+                }
+                None => return Err(err),
+            }
+
+            attempt += 1;
+        }
+    }
+
+    async fn issue_once(&self, domain: &str) -> Result<CertificateBundle, ManagerError> {
+        let policy = self.policy(domain);
+        let account = self.account(self.directory(domain)).await?;
+        let solvers = match policy.and_then(|policy| policy.solvers.clone()) {
+            Some(solvers) => solvers,
+            None => SolverChain::new().solver(self.tokens.clone()),
+        };
+        let key_algorithm = policy
+            .and_then(|policy| policy.key_algorithm)
+            .unwrap_or_default();
+
+        let responder = match self.standalone {
+            Some(addr) => Some(StandaloneHttp01::bind(addr, self.tokens.clone())?),
+            None => None,
+        };
+        let res = match self.issue_dry_run(domain, &solvers, key_algorithm).await {
+            Ok(()) => account
+                .issue_with_key(vec![domain.to_owned()], &solvers, key_algorithm)
+                .await
+                .map_err(ManagerError::from),
+            Err(err) => Err(err),
+        };
+        if let Some(responder) = responder {
+            responder.shutdown().await?;
+        }
+
+        res
+    }
+
+    // the staging certificate is thrown away
+    async fn issue_dry_run(
+        &self,
+        domain: &str,
+        solvers: &SolverChain,
+        key_algorithm: KeyAlgorithm,
+    ) -> Result<(), ManagerError> {
+        let staging = match &self.dry_run {
+            Some(staging) if staging.url() != self.directory(domain).url() => staging,
+            _ => return Ok(()),
+        };
+
+        let account = self.account(staging).await?;
+        info!(%domain, "dry run against staging");
+        account
+            .issue_with_key(vec![domain.to_owned()], solvers, key_algorithm)
+            .await
+            .map_err(ManagerError::DryRun)?;
+
+        Ok(())
+    }
+
+    // every directory in use has its own account
+    async fn rotate_account_keys(&self) -> Result<(), ManagerError> {
+        let every = match self.key_rotation {
+            Some(every) => every.as_secs() as i64,
+            None => return Ok(()),
+        };
+
+        let mut directories = vec![&self.directory];
+        for policy in self.policies.values() {
+            if let Some(directory) = &policy.directory {
+                if !directories.iter().any(|d| d.url() == directory.url()) {
+                    directories.push(directory);
+                }
+            }
+        }
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        for directory in directories {
+            let key = directory.url().unwrap_or(ACCOUNT_KEY);
+            let rotated_at = self
+                .get(DataType::KeyRotatedAt, key)
+                .await?
+                .and_then(|rotated_at| String::from_utf8(rotated_at).ok())
+                .and_then(|rotated_at| rotated_at.parse::<i64>().ok());
+
+            match rotated_at {
+                Some(rotated_at) if now - rotated_at < every => continue,
+                Some(_) => self.rotate_account_key_of(directory).await?,
+                // accounts registered before rotation was enabled start counting now
+                None => {
+                    self.put(DataType::KeyRotatedAt, key, now.to_string().into_bytes())
+                        .await?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // the new key is persisted as pending first, so it isn't lost if the CA accepts the rollover
+    // but persisting the result fails
+    async fn rotate_account_key_of(&self, directory: &Directory) -> Result<(), ManagerError> {
+        let key = directory.url().unwrap_or(ACCOUNT_KEY);
+        let mut account = self.account(directory).await?;
+
+        let key_pair = RingCrypto::new().private_key()?;
+        let der = key_pair.as_der().to_vec();
+        self.put(DataType::PendingPrivateKey, key, der.clone())
+            .await?;
+
+        account.change_key_to(key_pair).await?;
+        info!(directory = key, "rotated account key");
+
+        self.put(DataType::PrivateKey, key, der).await?;
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        self.put(DataType::KeyRotatedAt, key, now.to_string().into_bytes())
+            .await?;
+        self.put(DataType::PendingPrivateKey, key, Vec::new())
+            .await?;
+
+        self.accounts.lock().insert(key.to_owned(), account);
+        Ok(())
+    }
+
+    // loads the account of directory from persist or registers a new one
+    async fn account(&self, directory: &Directory) -> Result<Account<'static>, ManagerError> {
+        let key = directory.url().unwrap_or(ACCOUNT_KEY);
+        let cached = self.accounts.lock().get(key).cloned();
+        if let Some(account) = cached {
+            return Ok(account);
+        }
+
+        let kid = self.get(DataType::Kid, key).await?;
+        let private_key = self.get(DataType::PrivateKey, key).await?;
+
+        let mut account = match (kid, private_key) {
+            (Some(kid), Some(private_key)) => {
+                let kid = Uri::try_from(String::from_utf8_lossy(&kid).into_owned())?;
+                let key_pair = RingKeyPair::from_der(private_key)?;
+
+                let mut account = directory.account_from_key_pair(kid.clone(), key_pair);
+                let res = account.update().await.map(|_| ());
+                match res {
+                    Ok(()) => account.into_owned(),
+                    Err(err) => self.recover_rotation(directory, kid, err).await?,
+                }
+            }
+            _ => {
+                let account = directory.new_account(&self.mail).await?;
+                let kid = hyper::Uri::from(account.kid()).to_string();
+
+                self.put(
+                    DataType::PrivateKey,
+                    key,
+                    account.key_pair().as_der().to_vec(),
+                )
+                .await?;
+                self.put(DataType::Kid, key, kid.into_bytes()).await?;
+                account.into_owned()
+            }
+        };
+
+        if let Some(token) = &self.cancellation {
+            account.cancellation(token.clone());
+        }
+        self.accounts.lock().insert(key.to_owned(), account.clone());
+        Ok(account)
+    }
+
+    // a rollover was interrupted after the CA accepted the pending key, err is returned if there
+    // is no pending key or the CA doesn't accept it either
+    async fn recover_rotation(
+        &self,
+        directory: &Directory,
+        kid: Uri,
+        err: DirectoryError,
+    ) -> Result<Account<'static>, ManagerError> {
+        let key = directory.url().unwrap_or(ACCOUNT_KEY);
+        let pending = match self.get(DataType::PendingPrivateKey, key).await? {
+            Some(pending) if !pending.is_empty() => pending,
+            _ => return Err(err.into()),
+        };
+
+        let key_pair = RingKeyPair::from_der(pending.clone())?;
+        let mut account = directory.account_from_key_pair(kid, key_pair);
+        if account.update().await.is_err() {
+            return Err(err.into());
+        }
+
+        warn!(
+            directory = key,
+            "recovered interrupted account key rotation"
+        );
+        self.put(DataType::PrivateKey, key, pending).await?;
+        self.put(DataType::PendingPrivateKey, key, Vec::new())
+            .await?;
+        Ok(account.into_owned())
+    }
+
+    async fn get(&self, data_type: DataType, key: &str) -> Result<Option<Vec<u8>>, ManagerError> {
+        self.persist
+            .get(data_type, key)
+            .await
+            .map_err(|err| ManagerError::Persist(err.into()))
+    }
+
+    async fn put(
+        &self,
+        data_type: DataType,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), ManagerError> {
+        self.persist
+            .put(data_type, key, value)
+            .await
+            .map_err(|err| ManagerError::Persist(err.into()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReissueReason {
+    // reported by the ocsp responder
+    Revoked,
+    // the CA suggests renewing now, e.g. because of a mass revocation
+    RenewalInfo,
+}
+
+#[derive(Debug)]
+pub enum RenewalEvent {
+    Renewed(String),
+    // renewed before the renew before window
+    Reissued(String, ReissueReason),
+    // the certificate is still valid long enough
+    Skipped(String),
+    Failed(String, ManagerError),
+}
+
+enum Renewal {
+    Renewed,
+    Reissued(ReissueReason),
+    Skipped,
+}
+
+#[derive(Debug)]
+pub struct RenewalTask {
+    handle: AbortHandle,
+}
+
+impl RenewalTask {
+    // dropping the handle keeps the task running
+    pub fn abort(&self) {
+        self.handle.abort();
+    }
+}
+
+// runs the renewals of manager on the runtime of its directory like run, an event is sent for
+// every domain and check, the task keeps running if the receiver is dropped
+pub fn spawn_renewal_task<P>(
+    manager: Arc<CertificateManager<P>>,
+) -> (RenewalTask, mpsc::UnboundedReceiver<RenewalEvent>)
+where
+    P: Persist + Send + Sync + 'static,
+{
+    let (events, rx) = mpsc::unbounded_channel();
+
+    let directory = manager.directory.clone();
+    let handle = spawn_abortable(directory.runtime(), async move {
+        loop {
+            let _ = manager
+                .renew_all(|domain, renewal| {
+                    let domain = domain.to_owned();
+                    let event = match renewal {
+                        Ok(Renewal::Renewed) => RenewalEvent::Renewed(domain),
+                        Ok(Renewal::Reissued(reason)) => RenewalEvent::Reissued(domain, reason),
+                        Ok(Renewal::Skipped) => RenewalEvent::Skipped(domain),
+                        Err(err) => RenewalEvent::Failed(domain, err),
+                    };
+                    let _ = events.send(event);
+                })
+                .await;
+            if !manager.wait_interval().await {
+                return;
+            }
+        }
+    });
+
+    (RenewalTask { handle }, rx)
+}
+
+// None if the chain doesn't start with a parsable certificate
+fn not_after(chain: &[u8]) -> Option<i64> {
+    leaf_certificate(chain, |leaf| Some(leaf.validity().not_after.timestamp()))
+}
+
+fn needs_renewal(chain: &[u8], renew_before: Duration) -> Option<bool> {
+    let renew_at = not_after(chain)? - renew_before.as_secs() as i64;
+    Some(OffsetDateTime::now_utc().unix_timestamp() >= renew_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(not_after: OffsetDateTime) -> Vec<u8> {
+        let mut params = rcgen::CertificateParams::new(vec!["example.com".to_string()]);
+        params.not_after = not_after;
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+
+        cert.serialize_pem().unwrap().into_bytes()
+    }
+
+    #[test]
+    fn renewal_window() {
+        let now = OffsetDateTime::now_utc();
+        let expiring = chain(now + time::Duration::days(10));
+        let fresh = chain(now + time::Duration::days(60));
+
+        assert_eq!(needs_renewal(&expiring, RENEW_BEFORE), Some(true));
+        assert_eq!(needs_renewal(&fresh, RENEW_BEFORE), Some(false));
+        assert_eq!(needs_renewal(b"not a certificate", RENEW_BEFORE), None);
+    }
+}
@@ -0,0 +1,109 @@
+use acme_core::ApiChallengeType;
+use async_trait::async_trait;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{BoxError, ChallengeProof, Solver};
+
+// a challenge the operator has to publish, validation starts once it is confirmed
+#[derive(Debug)]
+pub struct ManualChallenge {
+    pub challenge_type: ApiChallengeType,
+    pub proof: ChallengeProof,
+    confirm: oneshot::Sender<()>,
+}
+
+impl ManualChallenge {
+    // dropping the challenge instead aborts the validation
+    pub fn confirm(self) {
+        let _ = self.confirm.send(());
+    }
+}
+
+#[derive(Debug)]
+pub enum ManualStep {
+    Present(ManualChallenge),
+    // the record or file can be removed again
+    Cleanup(ChallengeProof),
+}
+
+// for one-off issuance without api access, the steps are handed to the operator
+#[derive(Debug, Clone)]
+pub struct ManualSolver {
+    challenge_type: ApiChallengeType,
+    steps: mpsc::Sender<ManualStep>,
+}
+
+impl ManualSolver {
+    pub fn new(challenge_type: ApiChallengeType) -> (Self, mpsc::Receiver<ManualStep>) {
+        let (steps, rx) = mpsc::channel(16);
+        let solver = Self {
+            challenge_type,
+            steps,
+        };
+
+        (solver, rx)
+    }
+}
+
+#[async_trait]
+impl Solver for ManualSolver {
+    fn challenge_type(&self) -> ApiChallengeType {
+        self.challenge_type.clone()
+    }
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let (confirm, confirmed) = oneshot::channel();
+        let challenge = ManualChallenge {
+            challenge_type: self.challenge_type.clone(),
+            proof: proof.clone(),
+            confirm,
+        };
+
+        self.steps
+            .send(ManualStep::Present(challenge))
+            .await
+            .map_err(|_| "manual steps are no longer received")?;
+        confirmed
+            .await
+            .map_err(|_| format!("challenge for {} was not confirmed", proof.domain))?;
+
+        Ok(())
+    }
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        // nobody is listening anymore, so there is nothing to clean up
+        let _ = self.steps.send(ManualStep::Cleanup(proof.clone())).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proof() -> ChallengeProof {
+        ChallengeProof {
+            domain: "example.com".into(),
+            token: "token".into(),
+            key_authorization: "token.thumbprint".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn waits_for_confirmation() {
+        let (solver, mut steps) = ManualSolver::new(ApiChallengeType::DNS);
+
+        let operator = tokio::spawn(async move {
+            match steps.recv().await {
+                Some(ManualStep::Present(challenge)) => challenge.confirm(),
+                step => panic!("unexpected step {:?}", step),
+            }
+            // the second challenge is rejected
+            drop(steps.recv().await);
+        });
+
+        assert!(solver.present(&proof()).await.is_ok());
+        assert!(solver.present(&proof()).await.is_err());
+        operator.await.unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+use acme_core::ApiChallengeType;
+use async_trait::async_trait;
+use s3::Bucket;
+
+use crate::{BoxError, ChallengeProof, Solver};
+
+// publishes the key authorization to a s3 compatible bucket, which has to be served
+// by a cdn or static host under /.well-known/acme-challenge/
+#[derive(Debug, Clone)]
+pub struct ObjectStorageSolver {
+    bucket: Bucket,
+    prefix: String,
+}
+
+impl ObjectStorageSolver {
+    pub fn new(bucket: Bucket) -> Self {
+        Self {
+            bucket,
+            prefix: String::new(),
+        }
+    }
+
+    // for hosts serving the challenges from a subdirectory of the bucket
+    pub fn prefix<T: Into<String>>(mut self, prefix: T) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, token: &str) -> String {
+        match self.prefix.is_empty() || self.prefix.ends_with('/') {
+            true => format!("{}{}", self.prefix, token),
+            false => format!("{}/{}", self.prefix, token),
+        }
+    }
+}
+
+fn check_status(action: &str, status: u16) -> Result<(), BoxError> {
+    match status {
+        200..=299 => Ok(()),
+        status => Err(format!("{} failed with status {}", action, status).into()),
+    }
+}
+
+#[async_trait]
+impl Solver for ObjectStorageSolver {
+    fn challenge_type(&self) -> ApiChallengeType {
+        ApiChallengeType::HTTP
+    }
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let (_, status) = self
+            .bucket
+            .put_object_with_content_type(
+                self.key(&proof.token),
+                proof.key_authorization.as_bytes(),
+                "text/plain",
+            )
+            .await?;
+
+        check_status("upload", status)
+    }
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let (_, status) = self.bucket.delete_object(self.key(&proof.token)).await?;
+
+        // deleting a missing object is not an error
+        match status {
+            404 => Ok(()),
+            status => check_status("delete", status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use s3::creds::Credentials;
+    use s3::Region;
+
+    use super::*;
+
+    #[test]
+    fn object_key() -> Result<(), BoxError> {
+        let region = Region::Custom {
+            region: "".to_string(),
+            endpoint: "http://localhost:9000".to_string(),
+        };
+        let credentials = Credentials {
+            access_key: Some("minioadmin".to_string()),
+            secret_key: Some("minioadmin".to_string()),
+            security_token: None,
+            session_token: None,
+        };
+        let solver =
+            ObjectStorageSolver::new(Bucket::new_with_path_style("static", region, credentials)?);
+
+        assert_eq!(solver.key("token"), "token");
+        assert_eq!(solver.clone().prefix("acme").key("token"), "acme/token");
+        assert_eq!(solver.prefix("acme/").key("token"), "acme/token");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,468 @@
+use hyper::client::HttpConnector;
+use hyper::http::header::CONTENT_TYPE;
+use hyper::{body, Body, Client, Request};
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+use x509_parser::parse_x509_certificate;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+use crate::chain::leaf_certificate;
+use crate::runtime::{default_runtime, timeout};
+use crate::{Directory, Resolver, Runtime};
+
+const OCSP_TIMEOUT: Duration = Duration::from_secs(10);
+const OCSP_ACCESS_METHOD: &str = "1.3.6.1.5.5.7.48.1";
+// der of the sha1 algorithm identifier
+const SHA1_ALGORITHM: &[u8] = &[
+    0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00,
+];
+// id-pkix-ocsp-basic
+const BASIC_RESPONSE: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+// signature algorithms a response or a delegated responder may be signed with
+const ECDSA_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const ECDSA_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+const RSA_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+const RSA_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c];
+const RSA_SHA512: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d];
+const EC_PUBLIC_KEY: &str = "1.2.840.10045.2.1";
+const RSA_PUBLIC_KEY: &str = "1.2.840.113549.1.1.1";
+
+#[derive(Debug, Error)]
+pub enum OcspError {
+    #[error(transparent)]
+    Hyper(#[from] hyper::Error),
+    #[error(transparent)]
+    Http(#[from] hyper::http::Error),
+    #[error("Chain needs the leaf followed by its issuer")]
+    InvalidChain,
+    #[error("Certificate has no ocsp responder")]
+    MissingResponder,
+    #[error("Ocsp responder timed out")]
+    Timeout,
+    #[error("Ocsp responder responded with {0}")]
+    Status(u16),
+    #[error("Ocsp response status is {0}")]
+    ResponseStatus(u8),
+    #[error("Malformed ocsp response")]
+    Malformed,
+    #[error("Ocsp response is not signed by the issuer or its responder")]
+    InvalidSignature,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcspStatus {
+    Good,
+    Revoked,
+    Unknown,
+}
+
+// asks the responder of the leaf whether it was revoked, responders are plain http
+#[derive(Debug, Clone)]
+pub struct OcspChecker {
+    client: Client<HttpConnector<Resolver>>,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl Default for OcspChecker {
+    fn default() -> Self {
+        Self {
+            client: Client::builder().build(HttpConnector::new_with_resolver(Resolver::default())),
+            runtime: default_runtime(),
+        }
+    }
+}
+
+impl OcspChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // shares the resolver, local address and runtime of the directory
+    pub fn from_directory(directory: &Directory) -> Self {
+        Self {
+            client: directory.http_client().clone(),
+            runtime: directory.runtime_handle(),
+        }
+    }
+
+    // runs the request timeout, the hyper client still needs tokio
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.runtime = Arc::new(runtime);
+        self
+    }
+
+    pub async fn check(&self, chain: &[u8]) -> Result<OcspStatus, OcspError> {
+        let certs = rustls_pemfile::certs(&mut &*chain).map_err(|_| OcspError::InvalidChain)?;
+        let issuer = certs.get(1).ok_or(OcspError::InvalidChain)?;
+        let (_, issuer) = parse_x509_certificate(issuer).map_err(|_| OcspError::InvalidChain)?;
+
+        let (responder, request, serial) = leaf_certificate(chain, |leaf| {
+            let url = responder(leaf).map(str::to_owned);
+            Some((url, request(leaf, &issuer), leaf.raw_serial().to_vec()))
+        })
+        .ok_or(OcspError::InvalidChain)?;
+        let responder = responder.ok_or(OcspError::MissingResponder)?;
+        let req = Request::post(responder)
+            .header(CONTENT_TYPE, "application/ocsp-request")
+            .body(Body::from(request))?;
+
+        let res = timeout(&*self.runtime, OCSP_TIMEOUT, async {
+            let res = self.client.request(req).await?;
+            let status = res.status();
+            let body = body::to_bytes(res.into_body()).await?;
+            Ok::<_, hyper::Error>((status, body))
+        })
+        .await
+        .map_err(|_| OcspError::Timeout)??;
+
+        match res {
+            (status, body) if status.is_success() => status_of(&body, &serial, &issuer),
+            (status, _) => Err(OcspError::Status(status.as_u16())),
+        }
+    }
+}
+
+fn responder<'a>(leaf: &'a X509Certificate<'_>) -> Option<&'a str> {
+    leaf.extensions()
+        .iter()
+        .find_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::AuthorityInfoAccess(aia) => Some(aia),
+            _ => None,
+        })?
+        .accessdescs
+        .iter()
+        .filter(|desc| desc.access_method.to_id_string() == OCSP_ACCESS_METHOD)
+        .find_map(|desc| match desc.access_location {
+            GeneralName::URI(uri) => Some(uri),
+            _ => None,
+        })
+}
+
+// OCSPRequest with a single sha1 CertID of RFC 6960, without nonce and signature
+fn request(leaf: &X509Certificate<'_>, issuer: &X509Certificate<'_>) -> Vec<u8> {
+    let name_hash = digest(&SHA1_FOR_LEGACY_USE_ONLY, leaf.issuer().as_raw());
+    let key_hash = digest(
+        &SHA1_FOR_LEGACY_USE_ONLY,
+        &issuer.public_key().subject_public_key.data,
+    );
+
+    let mut cert_id = SHA1_ALGORITHM.to_vec();
+    cert_id.extend(tlv(0x04, name_hash.as_ref()));
+    cert_id.extend(tlv(0x04, key_hash.as_ref()));
+    cert_id.extend(tlv(0x02, leaf.raw_serial()));
+
+    let request = tlv(0x30, &tlv(0x30, &cert_id));
+    let request_list = tlv(0x30, &request);
+    let tbs_request = tlv(0x30, &request_list);
+    tlv(0x30, &tbs_request)
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    match len {
+        0..=0x7f => out.push(len as u8),
+        0x80..=0xff => out.extend([0x81, len as u8]),
+        _ => out.extend([0x82, (len >> 8) as u8, len as u8]),
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+// splits the first element off input, returns its tag, content and the remaining input
+fn next(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&len, mut input) = input.split_first()?;
+
+    let len = match len {
+        0..=0x7f => len as usize,
+        0x81..=0x84 => {
+            let (bytes, rest) = input.split_at(usize::from(len & 0x7f).min(input.len()));
+            input = rest;
+            bytes.iter().fold(0, |len, &byte| len << 8 | byte as usize)
+        }
+        _ => return None,
+    };
+    if input.len() < len {
+        return None;
+    }
+
+    let (content, rest) = input.split_at(len);
+    Some((tag, content, rest))
+}
+
+fn expect(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    match next(input)? {
+        (actual, content, rest) if actual == tag => Some((content, rest)),
+        _ => None,
+    }
+}
+
+// walks OCSPResponse down to the SingleResponse of serial
+fn status_of(
+    res: &[u8],
+    serial: &[u8],
+    issuer: &X509Certificate<'_>,
+) -> Result<OcspStatus, OcspError> {
+    let (res, _) = expect(res, 0x30).ok_or(OcspError::Malformed)?;
+    let (status, res) = expect(res, 0x0a).ok_or(OcspError::Malformed)?;
+    match status {
+        [0] => {}
+        [status] => return Err(OcspError::ResponseStatus(*status)),
+        _ => return Err(OcspError::Malformed),
+    }
+
+    let basic = basic_response(res).ok_or(OcspError::Malformed)?;
+    verify(basic, issuer)?;
+    basic_status(basic, serial).ok_or(OcspError::Malformed)
+}
+
+// content of the BasicOCSPResponse
+fn basic_response(res: &[u8]) -> Option<&[u8]> {
+    let (bytes, _) = expect(res, 0xa0)?;
+    let (bytes, _) = expect(bytes, 0x30)?;
+    let (response_type, bytes) = expect(bytes, 0x06)?;
+    if response_type != BASIC_RESPONSE {
+        return None;
+    }
+    let (basic, _) = expect(bytes, 0x04)?;
+    let (basic, _) = expect(basic, 0x30)?;
+    Some(basic)
+}
+
+// the response is signed by the issuer or by a responder the issuer delegated to
+fn verify(basic: &[u8], issuer: &X509Certificate<'_>) -> Result<(), OcspError> {
+    let (_, _, rest) = next(basic).ok_or(OcspError::Malformed)?;
+    let data = &basic[..basic.len() - rest.len()];
+    let (algorithm, rest) = expect(rest, 0x30).ok_or(OcspError::Malformed)?;
+    let (algorithm, _) = expect(algorithm, 0x06).ok_or(OcspError::Malformed)?;
+    // bit string without unused bits
+    let (signature, rest) = match expect(rest, 0x03).ok_or(OcspError::Malformed)? {
+        ([0, signature @ ..], rest) => (signature, rest),
+        _ => return Err(OcspError::Malformed),
+    };
+
+    if signed_by(data, algorithm, signature, issuer.public_key()) {
+        return Ok(());
+    }
+
+    let (certs, _) = expect(rest, 0xa0).ok_or(OcspError::InvalidSignature)?;
+    let (certs, _) = expect(certs, 0x30).ok_or(OcspError::Malformed)?;
+    let (_, responder) = parse_x509_certificate(certs).map_err(|_| OcspError::Malformed)?;
+    let delegated = ocsp_signing(&responder)
+        && signed_by(
+            responder.tbs_certificate.as_ref(),
+            responder.signature_algorithm.algorithm.as_bytes(),
+            &responder.signature_value.data,
+            issuer.public_key(),
+        );
+
+    match delegated && signed_by(data, algorithm, signature, responder.public_key()) {
+        true => Ok(()),
+        false => Err(OcspError::InvalidSignature),
+    }
+}
+
+fn ocsp_signing(cert: &X509Certificate<'_>) -> bool {
+    cert.extensions().iter().any(|extension| {
+        matches!(
+            extension.parsed_extension(),
+            ParsedExtension::ExtendedKeyUsage(usage) if usage.ocsp_signing
+        )
+    })
+}
+
+// the curve of an ec key follows from the length of its uncompressed point
+fn signed_by(
+    message: &[u8],
+    algorithm: &[u8],
+    signature: &[u8],
+    key: &SubjectPublicKeyInfo<'_>,
+) -> bool {
+    let public_key: &[u8] = &key.subject_public_key.data;
+    let key_type = key.algorithm.algorithm.to_id_string();
+    let algorithm: &'static dyn VerificationAlgorithm =
+        match (key_type.as_str(), algorithm, public_key.len()) {
+            (EC_PUBLIC_KEY, ECDSA_SHA256, 65) => &signature::ECDSA_P256_SHA256_ASN1,
+            (EC_PUBLIC_KEY, ECDSA_SHA384, 65) => &signature::ECDSA_P256_SHA384_ASN1,
+            (EC_PUBLIC_KEY, ECDSA_SHA256, 97) => &signature::ECDSA_P384_SHA256_ASN1,
+            (EC_PUBLIC_KEY, ECDSA_SHA384, 97) => &signature::ECDSA_P384_SHA384_ASN1,
+            (RSA_PUBLIC_KEY, RSA_SHA256, _) => &signature::RSA_PKCS1_2048_8192_SHA256,
+            (RSA_PUBLIC_KEY, RSA_SHA384, _) => &signature::RSA_PKCS1_2048_8192_SHA384,
+            (RSA_PUBLIC_KEY, RSA_SHA512, _) => &signature::RSA_PKCS1_2048_8192_SHA512,
+            _ => return false,
+        };
+
+    UnparsedPublicKey::new(algorithm, public_key)
+        .verify(message, signature)
+        .is_ok()
+}
+
+fn basic_status(basic: &[u8], serial: &[u8]) -> Option<OcspStatus> {
+    let (data, _) = expect(basic, 0x30)?;
+    // optional version
+    let data = match expect(data, 0xa0) {
+        Some((_, data)) => data,
+        None => data,
+    };
+    // responder id by name or key hash
+    let (tag, _, data) = next(data)?;
+    if tag != 0xa1 && tag != 0xa2 {
+        return None;
+    }
+    let (_, data) = expect(data, 0x18)?;
+    let (mut responses, _) = expect(data, 0x30)?;
+
+    while !responses.is_empty() {
+        let (single, rest) = expect(responses, 0x30)?;
+        responses = rest;
+
+        let (cert_id, single) = expect(single, 0x30)?;
+        let (_, cert_id) = expect(cert_id, 0x30)?;
+        let (_, cert_id) = expect(cert_id, 0x04)?;
+        let (_, cert_id) = expect(cert_id, 0x04)?;
+        let (response_serial, _) = expect(cert_id, 0x02)?;
+        if response_serial != serial {
+            continue;
+        }
+
+        return match next(single)?.0 {
+            0x80 => Some(OcspStatus::Good),
+            0xa1 => Some(OcspStatus::Revoked),
+            _ => Some(OcspStatus::Unknown),
+        };
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::{Certificate, CertificateParams, ExtendedKeyUsagePurpose};
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    use super::*;
+
+    fn ca() -> Certificate {
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        Certificate::from_params(params).unwrap()
+    }
+
+    // OCSPResponse with one SingleResponse signed by signer, status is the encoded CertStatus
+    fn response(serial: &[u8], status: &[u8], signer: &Certificate, certs: &[u8]) -> Vec<u8> {
+        let mut cert_id = SHA1_ALGORITHM.to_vec();
+        cert_id.extend(tlv(0x04, &[0; 20]));
+        cert_id.extend(tlv(0x04, &[0; 20]));
+        cert_id.extend(tlv(0x02, serial));
+
+        let mut single = tlv(0x30, &cert_id);
+        single.extend_from_slice(status);
+        single.extend(tlv(0x18, b"20261016000000Z"));
+
+        let mut data = tlv(0xa2, &tlv(0x04, &[0; 20]));
+        data.extend(tlv(0x18, b"20261016000000Z"));
+        data.extend(tlv(0x30, &tlv(0x30, &single)));
+        let data = tlv(0x30, &data);
+
+        let key = signer.serialize_private_key_der();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &key).unwrap();
+        let mut signature = vec![0];
+        signature.extend_from_slice(key.sign(&SystemRandom::new(), &data).unwrap().as_ref());
+
+        let mut basic = data;
+        basic.extend(tlv(0x30, &tlv(0x06, ECDSA_SHA256)));
+        basic.extend(tlv(0x03, &signature));
+        if !certs.is_empty() {
+            basic.extend(tlv(0xa0, &tlv(0x30, certs)));
+        }
+
+        let mut bytes = tlv(0x06, BASIC_RESPONSE);
+        bytes.extend(tlv(0x04, &tlv(0x30, &basic)));
+
+        let mut res = tlv(0x0a, &[0]);
+        res.extend(tlv(0xa0, &tlv(0x30, &bytes)));
+        tlv(0x30, &res)
+    }
+
+    #[test]
+    fn parses_status() {
+        let ca = ca();
+        let der = ca.serialize_der().unwrap();
+        let (_, issuer) = parse_x509_certificate(&der).unwrap();
+
+        let good = response(&[0x01, 0x02], &[0x80, 0x00], &ca, &[]);
+        let revoked = tlv(0xa1, &tlv(0x18, b"20261015000000Z"));
+        let revoked = response(&[0x01, 0x02], &revoked, &ca, &[]);
+
+        assert_eq!(
+            status_of(&good, &[0x01, 0x02], &issuer).unwrap(),
+            OcspStatus::Good
+        );
+        assert_eq!(
+            status_of(&revoked, &[0x01, 0x02], &issuer).unwrap(),
+            OcspStatus::Revoked
+        );
+        assert!(matches!(
+            status_of(&good, &[0x03], &issuer),
+            Err(OcspError::Malformed)
+        ));
+        // unauthorized
+        assert!(matches!(
+            status_of(&tlv(0x30, &tlv(0x0a, &[6])), &[0x01], &issuer),
+            Err(OcspError::ResponseStatus(6))
+        ));
+    }
+
+    #[test]
+    fn rejects_forged_response() {
+        let der = ca().serialize_der().unwrap();
+        let (_, issuer) = parse_x509_certificate(&der).unwrap();
+
+        let forged = response(&[0x01], &[0x80, 0x00], &ca(), &[]);
+        assert!(matches!(
+            status_of(&forged, &[0x01], &issuer),
+            Err(OcspError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn accepts_delegated_responder() {
+        let ca = ca();
+        let der = ca.serialize_der().unwrap();
+        let (_, issuer) = parse_x509_certificate(&der).unwrap();
+
+        let mut params = CertificateParams::new(vec![]);
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::OcspSigning];
+        let responder = Certificate::from_params(params).unwrap();
+        let responder_der = responder.serialize_der_with_signer(&ca).unwrap();
+        let delegated = response(&[0x01], &[0x80, 0x00], &responder, &responder_der);
+        assert_eq!(
+            status_of(&delegated, &[0x01], &issuer).unwrap(),
+            OcspStatus::Good
+        );
+
+        // the issuer has to delegate ocsp signing
+        let other = Certificate::from_params(CertificateParams::new(vec![])).unwrap();
+        let other_der = other.serialize_der_with_signer(&ca).unwrap();
+        let undelegated = response(&[0x01], &[0x80, 0x00], &other, &other_der);
+        assert!(matches!(
+            status_of(&undelegated, &[0x01], &issuer),
+            Err(OcspError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn long_length() {
+        let content = vec![0; 300];
+        let encoded = tlv(0x04, &content);
+        assert_eq!(&encoded[..4], &[0x04, 0x82, 0x01, 0x2c]);
+        assert_eq!(next(&encoded), Some((0x04, &content[..], &[][..])));
+    }
+}
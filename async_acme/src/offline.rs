@@ -0,0 +1,274 @@
+use acme_core::{
+    ApiAuthorization, ApiChallengeResponse, ApiIdentifier, ApiIdentifierType, ApiNewOrder,
+    ApiOrder, ApiOrderFinalization, Kid, Nonce, PostAsGet, RequestImpl, SignError, Signer, Uri,
+};
+use serde::Serialize;
+
+use crate::{Directory, DirectoryError, JwsProtected};
+
+// a request signed outside of this process, e.g. by an hsm or on an air gapped machine.
+// the protected header contains a nonce which the CA expires, so submit soon after preparing
+pub struct UnsignedRequest<T> {
+    url: Uri,
+    protected: JwsProtected<Kid, Nonce>,
+    payload: T,
+    signing_input: String,
+}
+
+impl<T: Serialize + Send + Sync> UnsignedRequest<T> {
+    fn new(
+        url: Uri,
+        protected: JwsProtected<Kid, Nonce>,
+        payload: T,
+    ) -> Result<Self, DirectoryError> {
+        let request: RequestImpl<Kid, Nonce, _, _, _> = RequestImpl::new(
+            protected.clone(),
+            &payload,
+            ExternalSignature(String::new()),
+        );
+        let (header, body) = request.signing_input()?;
+
+        Ok(UnsignedRequest {
+            url,
+            protected,
+            payload,
+            signing_input: format!("{}.{}", header, body),
+        })
+    }
+
+    pub fn url(&self) -> &Uri {
+        &self.url
+    }
+
+    // the exact bytes to sign, base64url protected header and payload joined by a dot
+    pub fn signing_input(&self) -> Vec<u8> {
+        self.signing_input.as_bytes().to_vec()
+    }
+
+    // for ES384 the signature is r and s concatenated, not DER
+    pub fn into_signed(self, signature: &[u8]) -> OfflineRequest<T> {
+        let signature = base64::encode_config(signature, base64::URL_SAFE_NO_PAD);
+        RequestImpl::new(self.protected, self.payload, ExternalSignature(signature))
+    }
+}
+
+// the signature created by the external signer, serializing the request doesn't sign again
+#[derive(Debug, Clone)]
+pub struct ExternalSignature(String);
+
+impl Signer for ExternalSignature {
+    fn sign(&self, _: String, _: String) -> Result<String, SignError> {
+        Ok(self.0.clone())
+    }
+}
+
+pub type OfflineRequest<T> =
+    RequestImpl<Kid, Nonce, JwsProtected<Kid, Nonce>, T, ExternalSignature>;
+
+// an existing account whose key is not available to this process,
+// every request is prepared, signed externally and submitted
+#[derive(Debug, Clone)]
+pub struct OfflineAccount<'a> {
+    directory: &'a Directory,
+    kid: Uri,
+    alg: &'static str,
+}
+
+impl Directory {
+    pub fn offline_account(&self, kid: Uri) -> OfflineAccount<'_> {
+        OfflineAccount {
+            directory: self,
+            kid,
+            alg: "ES384",
+        }
+    }
+}
+
+impl<'a> OfflineAccount<'a> {
+    // the jws algorithm of the external key, ES384 by default
+    pub fn algorithm(mut self, alg: &'static str) -> Self {
+        self.alg = alg;
+        self
+    }
+
+    pub fn kid(&self) -> &Uri {
+        &self.kid
+    }
+
+    async fn prepare<T: Serialize + Send + Sync>(
+        &self,
+        url: Uri,
+        payload: T,
+    ) -> Result<UnsignedRequest<T>, DirectoryError> {
+        let protected = self
+            .directory
+            .protect_with(&url, self.alg, Kid::from(&self.kid))
+            .await?;
+
+        UnsignedRequest::new(url, protected, payload)
+    }
+
+    pub async fn prepare_new_order<I, T>(
+        &self,
+        domains: I,
+    ) -> Result<UnsignedRequest<ApiNewOrder>, DirectoryError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let identifiers = domains
+            .into_iter()
+            .map(|domain| ApiIdentifier {
+                type_field: ApiIdentifierType::DNS,
+                value: domain.into(),
+            })
+            .collect();
+        let new_order = ApiNewOrder {
+            identifiers,
+            not_after: None,
+            not_before: None,
+        };
+        let url = self.directory.server.directory().new_order.clone();
+        self.prepare(url, new_order).await
+    }
+
+    // returns the order and its location
+    pub async fn submit_new_order(
+        &self,
+        signed: OfflineRequest<ApiNewOrder>,
+    ) -> Result<(ApiOrder, Uri), DirectoryError> {
+        let server = &self.directory.server;
+        Ok(self
+            .directory
+            .limit_request(server.new_order(signed))
+            .await?
+            .body)
+    }
+
+    // post-as-get for orders, authorizations and certificates
+    pub async fn prepare_get(
+        &self,
+        url: Uri,
+    ) -> Result<UnsignedRequest<PostAsGet>, DirectoryError> {
+        self.prepare(url, PostAsGet).await
+    }
+
+    pub async fn submit_get_order(
+        &self,
+        signed: OfflineRequest<PostAsGet>,
+        url: &Uri,
+    ) -> Result<ApiOrder, DirectoryError> {
+        let server = &self.directory.server;
+        Ok(self
+            .directory
+            .limit_request(server.get_order(url, signed))
+            .await?
+            .body)
+    }
+
+    pub async fn submit_get_authorization(
+        &self,
+        signed: OfflineRequest<PostAsGet>,
+        url: &Uri,
+    ) -> Result<ApiAuthorization, DirectoryError> {
+        let server = &self.directory.server;
+        Ok(self
+            .directory
+            .limit_request(server.get_authorization(url, signed))
+            .await?
+            .body)
+    }
+
+    // the pem chain
+    pub async fn submit_download(
+        &self,
+        signed: OfflineRequest<PostAsGet>,
+        url: &Uri,
+    ) -> Result<Vec<u8>, DirectoryError> {
+        let server = &self.directory.server;
+        Ok(self
+            .directory
+            .limit_request(server.download_certificate(url, signed))
+            .await?
+            .body)
+    }
+
+    // url is the url of the challenge, the payload is an empty object
+    pub async fn prepare_validate(
+        &self,
+        url: Uri,
+    ) -> Result<UnsignedRequest<ApiChallengeResponse>, DirectoryError> {
+        self.prepare(url, ApiChallengeResponse {}).await
+    }
+
+    pub async fn submit_validate(
+        &self,
+        signed: OfflineRequest<ApiChallengeResponse>,
+        url: &Uri,
+    ) -> Result<(), DirectoryError> {
+        let server = &self.directory.server;
+        self.directory
+            .limit_request(server.validate_challenge(url, signed))
+            .await?;
+        Ok(())
+    }
+
+    // csr is DER encoded, the url is the finalize url of the order
+    pub async fn prepare_finalize(
+        &self,
+        url: Uri,
+        csr: &[u8],
+    ) -> Result<UnsignedRequest<ApiOrderFinalization>, DirectoryError> {
+        let csr = base64::encode_config(csr, base64::URL_SAFE_NO_PAD);
+        let finalization = ApiOrderFinalization { csr };
+        self.prepare(url, finalization).await
+    }
+
+    pub async fn submit_finalize(
+        &self,
+        signed: OfflineRequest<ApiOrderFinalization>,
+        url: &Uri,
+    ) -> Result<ApiOrder, DirectoryError> {
+        let server = &self.directory.server;
+        Ok(self
+            .directory
+            .limit_request(server.finalize(url, signed))
+            .await?
+            .body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn signing_input() {
+        let url = Uri::try_from("https://ca/order/1").unwrap();
+        let kid = Kid::from(&Uri::try_from("https://ca/acct/1").unwrap());
+        let protected = JwsProtected::new("ES384", kid, Nonce("nonce".to_owned()), url.clone());
+
+        let request = UnsignedRequest::new(url.clone(), protected.clone(), PostAsGet).unwrap();
+        let input = String::from_utf8(request.signing_input()).unwrap();
+        let (header, payload) = input.split_once('.').unwrap();
+        assert_eq!(payload, "");
+
+        let header = base64::decode_config(header, base64::URL_SAFE_NO_PAD).unwrap();
+        let header: serde_json::Value = serde_json::from_slice(&header).unwrap();
+        assert_eq!(header["kid"], "https://ca/acct/1");
+        assert_eq!(header["nonce"], "nonce");
+
+        let signed = serde_json::to_value(request.into_signed(&[0xfb, 0xff])).unwrap();
+        assert_eq!(signed["payload"], "");
+        assert_eq!(signed["signature"], "-_8");
+        assert_eq!(format!("{}.", signed["protected"].as_str().unwrap()), input);
+
+        let finalization = ApiOrderFinalization {
+            csr: "csr".to_owned(),
+        };
+        let request = UnsignedRequest::new(url, protected, finalization).unwrap();
+        let input = String::from_utf8(request.signing_input()).unwrap();
+        assert!(input.ends_with(".eyJjc3IiOiJjc3IifQ"));
+    }
+}
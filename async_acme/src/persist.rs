@@ -10,6 +10,17 @@ use std::sync::Arc;
 #[derive(Hash, PartialEq, Eq, Copy, Clone, Debug)]
 pub enum DataType {
     PrivateKey,
+    Kid,
+    // keyed by domain or the sorted and comma separated domains of the certificate
+    Certificate,
+    CertificateKey,
+    CertificateHistory,
+    // keys of all stored certificates
+    CertificateIndex,
+    // account key written before a rollover, empty once the rollover is done
+    PendingPrivateKey,
+    // unix timestamp of the last account key rollover
+    KeyRotatedAt,
 }
 
 #[async_trait]
@@ -38,12 +49,26 @@ impl MemoryPersist {
 #[derive(Debug, Hash, Eq, PartialEq)]
 enum DataHolder<'a> {
     PrivateKey(Cow<'a, str>),
+    Kid(Cow<'a, str>),
+    Certificate(Cow<'a, str>),
+    CertificateKey(Cow<'a, str>),
+    CertificateHistory(Cow<'a, str>),
+    CertificateIndex(Cow<'a, str>),
+    PendingPrivateKey(Cow<'a, str>),
+    KeyRotatedAt(Cow<'a, str>),
 }
 
 impl<'a> DataHolder<'a> {
     fn convert<T: Into<Cow<'a, str>>>(data_type: DataType, key: T) -> DataHolder<'a> {
         match data_type {
             DataType::PrivateKey => DataHolder::PrivateKey(key.into()),
+            DataType::Kid => DataHolder::Kid(key.into()),
+            DataType::Certificate => DataHolder::Certificate(key.into()),
+            DataType::CertificateKey => DataHolder::CertificateKey(key.into()),
+            DataType::CertificateHistory => DataHolder::CertificateHistory(key.into()),
+            DataType::CertificateIndex => DataHolder::CertificateIndex(key.into()),
+            DataType::PendingPrivateKey => DataHolder::PendingPrivateKey(key.into()),
+            DataType::KeyRotatedAt => DataHolder::KeyRotatedAt(key.into()),
         }
     }
 }
@@ -0,0 +1,134 @@
+use acme_core::RetryAfter;
+use std::fmt::Debug;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+use crate::retry::jitter;
+
+// delays between refetching orders and authorizations, see DirectoryBuilder::poll_strategy
+pub trait PollStrategy: Debug + Send + Sync {
+    // attempt starts at 0 after the first fetch, retry_after is the header of the last response
+    fn delay(&self, attempt: u32, retry_after: Option<&RetryAfter>) -> Duration;
+}
+
+#[derive(Debug, Clone)]
+pub struct FixedPoll {
+    interval: Duration,
+}
+
+impl FixedPoll {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+}
+
+impl PollStrategy for FixedPoll {
+    fn delay(&self, _: u32, _: Option<&RetryAfter>) -> Duration {
+        self.interval
+    }
+}
+
+// doubles the delay after every attempt
+#[derive(Debug, Clone)]
+pub struct ExponentialPoll {
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl ExponentialPoll {
+    pub fn new(base_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            base_delay,
+            max_delay,
+        }
+    }
+}
+
+impl PollStrategy for ExponentialPoll {
+    fn delay(&self, attempt: u32, _: Option<&RetryAfter>) -> Duration {
+        2u32.checked_pow(attempt)
+            .and_then(|factor| self.base_delay.checked_mul(factor))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+// follows Retry-After if the CA sends it, the inner strategy is used otherwise.
+// the default is RetryAfterPoll over FixedPoll with 2 seconds
+#[derive(Debug, Clone)]
+pub struct RetryAfterPoll<P> {
+    inner: P,
+    max_delay: Duration,
+}
+
+impl<P: PollStrategy> RetryAfterPoll<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            max_delay: Duration::from_secs(60),
+        }
+    }
+
+    // caps the delay asked for by the CA
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+}
+
+impl<P: PollStrategy> PollStrategy for RetryAfterPoll<P> {
+    fn delay(&self, attempt: u32, retry_after: Option<&RetryAfter>) -> Duration {
+        match retry_after {
+            Some(retry_after) => retry_after
+                .delay(OffsetDateTime::now_utc())
+                .min(self.max_delay),
+            None => self.inner.delay(attempt, retry_after),
+        }
+    }
+}
+
+// randomizes the delay of the inner strategy between half and the full delay so a fleet of
+// clients doesn't poll in lockstep
+#[derive(Debug, Clone)]
+pub struct JitteredPoll<P> {
+    inner: P,
+}
+
+impl<P: PollStrategy> JitteredPoll<P> {
+    pub fn new(inner: P) -> Self {
+        Self { inner }
+    }
+}
+
+impl<P: PollStrategy> PollStrategy for JitteredPoll<P> {
+    fn delay(&self, attempt: u32, retry_after: Option<&RetryAfter>) -> Duration {
+        jitter(self.inner.delay(attempt, retry_after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strategies() {
+        let exponential = ExponentialPoll::new(Duration::from_secs(1), Duration::from_secs(3));
+        assert_eq!(exponential.delay(0, None), Duration::from_secs(1));
+        assert_eq!(exponential.delay(1, None), Duration::from_secs(2));
+        assert_eq!(exponential.delay(2, None), Duration::from_secs(3));
+
+        let retry_after = RetryAfterPoll::new(FixedPoll::new(Duration::from_secs(2)))
+            .max_delay(Duration::from_secs(10));
+        assert_eq!(retry_after.delay(0, None), Duration::from_secs(2));
+        let header = RetryAfter::Delay(Duration::from_secs(5));
+        assert_eq!(retry_after.delay(0, Some(&header)), Duration::from_secs(5));
+        let header = RetryAfter::Delay(Duration::from_secs(300));
+        assert_eq!(retry_after.delay(0, Some(&header)), Duration::from_secs(10));
+
+        let jittered = JitteredPoll::new(FixedPoll::new(Duration::from_secs(2)));
+        for _ in 0..100 {
+            let delay = jittered.delay(0, None);
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use acme_core::ApiChallengeType;
+use async_trait::async_trait;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use trust_dns_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use trust_dns_resolver::TokioAsyncResolver;
+
+use crate::runtime::default_runtime;
+use crate::{BoxError, ChallengeProof, Runtime, Solver};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Error)]
+pub enum PropagationError {
+    #[error(transparent)]
+    Resolve(#[from] ResolveError),
+    #[error("{0} is not visible on {1}")]
+    Timeout(String, String),
+}
+
+// waits until a txt record is visible on public resolvers and the authoritative servers,
+// validating earlier burns the challenge with an incorrectResponse
+#[derive(Debug, Clone)]
+pub struct PropagationChecker {
+    resolvers: Vec<(String, ResolverConfig)>,
+    authoritative: bool,
+    interval: Duration,
+    timeout: Duration,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl Default for PropagationChecker {
+    fn default() -> Self {
+        Self {
+            resolvers: vec![
+                ("google".into(), ResolverConfig::google()),
+                ("cloudflare".into(), ResolverConfig::cloudflare()),
+                ("quad9".into(), ResolverConfig::quad9()),
+            ],
+            authoritative: true,
+            interval: CHECK_INTERVAL,
+            timeout: CHECK_TIMEOUT,
+            runtime: default_runtime(),
+        }
+    }
+}
+
+impl PropagationChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // replaces the default public resolvers
+    pub fn resolvers(mut self, resolvers: Vec<(String, ResolverConfig)>) -> Self {
+        self.resolvers = resolvers;
+        self
+    }
+
+    pub fn authoritative(mut self, authoritative: bool) -> Self {
+        self.authoritative = authoritative;
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // sleeps between checks, the resolvers still need tokio
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.runtime = Arc::new(runtime);
+        self
+    }
+
+    // checks propagation after presenting dns-01 challenges
+    pub fn wrap<S: Solver>(self, solver: S) -> Propagated<S> {
+        Propagated {
+            solver,
+            checker: self,
+        }
+    }
+
+    pub async fn wait(&self, name: &str, value: &str) -> Result<(), PropagationError> {
+        let mut resolvers = Vec::with_capacity(self.resolvers.len() + 1);
+        for (label, config) in &self.resolvers {
+            resolvers.push((label.clone(), resolver(config.clone())?));
+        }
+        if self.authoritative {
+            resolvers.extend(authoritative(name).await?);
+        }
+
+        let start = Instant::now();
+        loop {
+            let mut pending = Vec::new();
+            for (label, resolver) in &resolvers {
+                if !visible(resolver, name, value).await {
+                    pending.push(label.as_str());
+                }
+            }
+
+            if pending.is_empty() {
+                return Ok(());
+            }
+            if start.elapsed() >= self.timeout {
+                return Err(PropagationError::Timeout(
+                    name.to_owned(),
+                    pending.join(", "),
+                ));
+            }
+            self.runtime.sleep(self.interval).await;
+        }
+    }
+}
+
+// answers must not be cached between checks
+fn resolver(config: ResolverConfig) -> Result<TokioAsyncResolver, ResolveError> {
+    let mut opts = ResolverOpts::default();
+    opts.cache_size = 0;
+
+    TokioAsyncResolver::tokio(config, opts)
+}
+
+// resolvers querying the nameservers of the zone containing name
+async fn authoritative(name: &str) -> Result<Vec<(String, TokioAsyncResolver)>, ResolveError> {
+    let system = resolver(ResolverConfig::default())?;
+
+    let mut candidate = name;
+    let nameservers = loop {
+        match system.ns_lookup(candidate).await {
+            Ok(nameservers) => break nameservers,
+            Err(err) => match (err.kind(), candidate.split_once('.')) {
+                (ResolveErrorKind::NoRecordsFound { .. }, Some((_, parent))) => candidate = parent,
+                _ => return Err(err),
+            },
+        }
+    };
+
+    let mut resolvers = Vec::new();
+    for nameserver in nameservers.iter() {
+        let nameserver = nameserver.to_utf8();
+        let ips: Vec<_> = system
+            .lookup_ip(nameserver.as_str())
+            .await?
+            .iter()
+            .collect();
+        let group = NameServerConfigGroup::from_ips_clear(&ips, 53, true);
+        let config = ResolverConfig::from_parts(None, vec![], group);
+
+        resolvers.push((nameserver, resolver(config)?));
+    }
+
+    Ok(resolvers)
+}
+
+// lookup errors like nxdomain just mean the record is not there yet
+async fn visible(resolver: &TokioAsyncResolver, name: &str, value: &str) -> bool {
+    match resolver.txt_lookup(name).await {
+        Ok(lookup) => lookup.iter().any(|txt| {
+            let data: Vec<u8> = txt.txt_data().concat();
+            data == value.as_bytes()
+        }),
+        Err(_) => false,
+    }
+}
+
+// presents the challenge and waits for it to propagate before validation starts
+pub struct Propagated<S> {
+    solver: S,
+    checker: PropagationChecker,
+}
+
+impl<S: Debug> Debug for Propagated<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Propagated")
+            .field("solver", &self.solver)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<S: Solver> Solver for Propagated<S> {
+    fn challenge_type(&self) -> ApiChallengeType {
+        self.solver.challenge_type()
+    }
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        self.solver.present(proof).await?;
+        if let ApiChallengeType::DNS = self.solver.challenge_type() {
+            self.checker
+                .wait(&proof.dns_name(), &proof.dns_value())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        self.solver.cleanup(proof).await
+    }
+}
@@ -0,0 +1,62 @@
+use rustls::version::TLS13;
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::{CertificateBundle, SniResolver};
+
+#[derive(Debug, Error)]
+pub enum QuicError {
+    #[error(transparent)]
+    Rustls(#[from] rustls::Error),
+    #[error("Certificate bundle can't be parsed")]
+    InvalidCertificate,
+}
+
+// quic requires tls 1.3, certificates are resolved on every handshake so certificates inserted
+// into resolver by the manager are picked up without rebuilding the endpoint
+pub fn quic_server_config(
+    resolver: &SniResolver,
+    alpn_protocols: Vec<Vec<u8>>,
+) -> Result<quinn::ServerConfig, QuicError> {
+    let mut config = rustls::ServerConfig::builder()
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(&[&TLS13])?
+        .with_no_client_auth()
+        .with_cert_resolver(Arc::new(resolver.clone()));
+    config.alpn_protocols = alpn_protocols;
+
+    Ok(quinn::ServerConfig::with_crypto(Arc::new(config)))
+}
+
+// for a single certificate, use quic_server_config with the resolver of the manager to follow
+// renewals
+pub fn quic_server_config_from_bundle(
+    bundle: &CertificateBundle,
+    alpn_protocols: Vec<Vec<u8>>,
+) -> Result<quinn::ServerConfig, QuicError> {
+    let resolver = SniResolver::new();
+    if !resolver.insert(bundle) {
+        return Err(QuicError::InvalidCertificate);
+    }
+
+    quic_server_config(&resolver, alpn_protocols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_bundle() {
+        let bundle = CertificateBundle {
+            chain: b"not a certificate".to_vec(),
+            private_key: Vec::new(),
+        };
+
+        assert!(matches!(
+            quic_server_config_from_bundle(&bundle, vec![b"h3".to_vec()]),
+            Err(QuicError::InvalidCertificate)
+        ));
+    }
+}
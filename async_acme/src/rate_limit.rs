@@ -0,0 +1,104 @@
+use acme_core::RetryAfter;
+use time::format_description;
+use time::PrimitiveDateTime;
+
+// newer boulder versions append the end of the window to the detail
+const RETRY_AFTER_DATE: &str = "[year]-[month]-[day] [hour]:[minute]:[second]";
+
+// see https://letsencrypt.org/docs/rate-limits/
+#[derive(Clone, Debug, PartialEq)]
+pub enum RateLimit {
+    CertificatesPerDomain,
+    DuplicateCertificate,
+    FailedValidation,
+    NewOrders,
+    NewRegistrations,
+    PendingAuthorizations,
+    Other(String),
+}
+
+impl RateLimit {
+    // boulder only explains the limit in the human readable detail
+    pub fn from_detail(detail: &str) -> Self {
+        let lower = detail.to_ascii_lowercase();
+        let contains = |needles: &[&str]| needles.iter().all(|needle| lower.contains(needle));
+
+        if contains(&["too many certificates", "exact set"]) {
+            RateLimit::DuplicateCertificate
+        } else if contains(&["too many certificates"]) {
+            RateLimit::CertificatesPerDomain
+        } else if contains(&["too many failed authorizations"]) {
+            RateLimit::FailedValidation
+        } else if contains(&["too many new orders"]) {
+            RateLimit::NewOrders
+        } else if contains(&["too many registrations"]) {
+            RateLimit::NewRegistrations
+        } else if contains(&["too many currently pending authorizations"]) {
+            RateLimit::PendingAuthorizations
+        } else {
+            RateLimit::Other(detail.to_string())
+        }
+    }
+}
+
+// used when the Retry-After header is missing, ascii lowercasing keeps the byte offsets of
+// lower valid for detail
+pub(crate) fn retry_after_from_detail(detail: &str) -> Option<RetryAfter> {
+    let lower = detail.to_ascii_lowercase();
+    let start = lower.find("retry after ")? + "retry after ".len();
+    let date = detail[start..].split(" UTC").next()?;
+
+    let format = format_description::parse(RETRY_AFTER_DATE).ok()?;
+    let date = PrimitiveDateTime::parse(date.trim(), &format).ok()?;
+
+    Some(RetryAfter::Date(date.assume_utc()))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn rate_limit_from_detail() {
+        assert_eq!(
+            RateLimit::from_detail("Error creating new order :: too many certificates already issued for exact set of domains: example.com: see https://letsencrypt.org/docs/rate-limits/"),
+            RateLimit::DuplicateCertificate
+        );
+        assert_eq!(
+            RateLimit::from_detail("Error creating new order :: too many certificates already issued for: example.com: see https://letsencrypt.org/docs/rate-limits/"),
+            RateLimit::CertificatesPerDomain
+        );
+        assert_eq!(
+            RateLimit::from_detail(
+                "Error creating new account :: too many registrations for this IP"
+            ),
+            RateLimit::NewRegistrations
+        );
+        assert_eq!(
+            RateLimit::from_detail("slow down"),
+            RateLimit::Other("slow down".to_string())
+        );
+    }
+
+    #[test]
+    fn retry_after_in_detail() {
+        let detail = "too many certificates (5) already issued for this exact set of domains in the last 168 hours: example.com, retry after 2022-05-13 12:30:00 UTC: see https://letsencrypt.org/docs/rate-limits/";
+        assert_eq!(
+            retry_after_from_detail(detail),
+            Some(RetryAfter::Date(datetime!(2022-05-13 12:30:00 UTC)))
+        );
+        assert_eq!(retry_after_from_detail("too many new orders"), None);
+    }
+
+    #[test]
+    fn retry_after_in_non_ascii_detail() {
+        // İ grows from two to three bytes when lowercased with unicode rules
+        let detail = "too many certificates already issued for: İİİİ.example.com, Retry After 2022-05-13 12:30:00 UTC";
+        assert_eq!(
+            retry_after_from_detail(detail),
+            Some(RetryAfter::Date(datetime!(2022-05-13 12:30:00 UTC)))
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use hyper::client::connect::dns::{GaiResolver, Name};
+use hyper::service::Service;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::vec;
+
+pub type ResolveFuture = Pin<Box<dyn Future<Output = Result<Vec<SocketAddr>, io::Error>> + Send>>;
+
+// the port of the returned addresses is replaced by the connector
+pub trait Resolve: Send + Sync + 'static {
+    fn resolve(&self, name: Name) -> ResolveFuture;
+}
+
+impl Resolve for GaiResolver {
+    fn resolve(&self, name: Name) -> ResolveFuture {
+        let mut resolver = self.clone();
+        Box::pin(async move { Ok(resolver.call(name).await?.collect()) })
+    }
+}
+
+// resolves hosts from a fixed map, useful for hermetic tests
+#[derive(Debug, Clone, Default)]
+pub struct StaticResolver {
+    hosts: HashMap<String, Vec<SocketAddr>>,
+}
+
+impl StaticResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn host<T: Into<String>>(mut self, host: T, addr: SocketAddr) -> Self {
+        self.hosts.entry(host.into()).or_default().push(addr);
+        self
+    }
+}
+
+impl Resolve for StaticResolver {
+    fn resolve(&self, name: Name) -> ResolveFuture {
+        let res = match self.hosts.get(name.as_str()) {
+            Some(addrs) => Ok(addrs.clone()),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no address for {}", name.as_str()),
+            )),
+        };
+        Box::pin(async move { res })
+    }
+}
+
+#[derive(Clone)]
+pub struct Resolver(Arc<dyn Resolve>);
+
+impl Resolver {
+    pub fn new<R: Resolve>(resolver: R) -> Self {
+        Self(Arc::new(resolver))
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new(GaiResolver::new())
+    }
+}
+
+impl Debug for Resolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Resolver").finish()
+    }
+}
+
+impl Service<Name> for Resolver {
+    type Response = vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let addrs = self.0.resolve(name);
+        Box::pin(async move { Ok(interleave(addrs.await?).into_iter()) })
+    }
+}
+
+// RFC 8305 prefers IPv6 and alternates address families, the connector
+// races the first address family against the other one
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+    let mut v6 = v6.into_iter();
+    let mut v4 = v4.into_iter();
+
+    let mut addrs = Vec::with_capacity(v6.len() + v4.len());
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => return addrs,
+            (v6, v4) => addrs.extend(v6.into_iter().chain(v4)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn interleave_prefers_ipv6() {
+        let v4 = |last| SocketAddr::from(([192, 0, 2, last], 0));
+        let v6 = |last| SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, last], 0));
+
+        assert_eq!(
+            interleave(vec![v4(1), v4(2), v4(3), v6(1)]),
+            vec![v6(1), v4(1), v4(2), v4(3)]
+        );
+        assert_eq!(
+            interleave(vec![v6(1), v4(1), v6(2), v4(2)]),
+            vec![v6(1), v4(1), v6(2), v4(2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn static_resolver() -> Result<(), Box<dyn std::error::Error>> {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut resolver = Resolver::new(StaticResolver::new().host("acme.test", addr));
+
+        let addrs = resolver.call(Name::from_str("acme.test")?).await?;
+        assert_eq!(addrs.collect::<Vec<_>>(), vec![addr]);
+
+        assert!(resolver.call(Name::from_str("other.test")?).await.is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use hyper::client::HttpConnector;
+use hyper::http::header::{HeaderName, CONTENT_TYPE};
+use hyper::http::{HeaderValue, Method};
+use hyper::{body, Body, Client, Request};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::runtime::{default_runtime, timeout};
+use crate::{BoxError, Runtime};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// json client for the apis of the dns providers
+#[derive(Clone)]
+pub(crate) struct RestClient {
+    client: Client<HttpsConnector<HttpConnector>>,
+    base_url: String,
+    auth: (HeaderName, HeaderValue),
+    runtime: Arc<dyn Runtime>,
+}
+
+impl RestClient {
+    pub(crate) fn new(base_url: &str, header: &'static str, value: &str) -> Result<Self, BoxError> {
+        let connector = HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_only()
+            .enable_http1()
+            .build();
+        let mut value = HeaderValue::from_str(value)?;
+        value.set_sensitive(true);
+
+        Ok(Self {
+            client: Client::builder().build(connector),
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            auth: (HeaderName::from_static(header), value),
+            runtime: default_runtime(),
+        })
+    }
+
+    pub(crate) fn runtime<R: Runtime>(&mut self, runtime: R) {
+        self.runtime = Arc::new(runtime);
+    }
+
+    pub(crate) async fn send<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<Vec<u8>, BoxError> {
+        let mut req = Request::builder()
+            .method(method)
+            .uri(format!("{}{}", self.base_url, path))
+            .header(&self.auth.0, &self.auth.1);
+        let body = match body {
+            Some(body) => {
+                req = req.header(CONTENT_TYPE, "application/json");
+                Body::from(serde_json::to_vec(body)?)
+            }
+            None => Body::empty(),
+        };
+
+        let res = timeout(&*self.runtime, REQUEST_TIMEOUT, async {
+            let res = self.client.request(req.body(body)?).await?;
+            let status = res.status();
+            let body = body::to_bytes(res.into_body()).await?;
+            Ok::<_, BoxError>((status, body))
+        })
+        .await??;
+
+        match res {
+            (status, body) if status.is_success() => Ok(body.to_vec()),
+            (status, body) => Err(format!("{}: {}", status, String::from_utf8_lossy(&body)).into()),
+        }
+    }
+
+    pub(crate) async fn get<R: DeserializeOwned>(&self, path: &str) -> Result<R, BoxError> {
+        let body = self.send::<()>(Method::GET, path, None).await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
@@ -0,0 +1,157 @@
+use ring::rand::{generate, SystemRandom};
+use std::error::Error;
+use std::fmt::Debug;
+use std::time::Duration;
+
+// decides if and when a failed request or issuance is tried again
+pub trait RetryPolicy: Debug + Send + Sync {
+    // attempt starts at 0 for the first try, transient is set for connection errors, 5xx responses
+    // and serverInternal problems. None means give up
+    fn retry(
+        &self,
+        attempt: u32,
+        transient: bool,
+        error: &(dyn Error + 'static),
+    ) -> Option<Duration>;
+}
+
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+        }
+    }
+}
+
+impl ExponentialBackoff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    // includes the first attempt
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    // attempt starts at 0 for the first try, None means give up
+    pub(crate) fn next_delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt.saturating_add(1) >= self.max_attempts {
+            return None;
+        }
+
+        let delay = 2u32
+            .checked_pow(attempt)
+            .and_then(|factor| self.base_delay.checked_mul(factor))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        match self.jitter {
+            true => Some(jitter(delay)),
+            false => Some(delay),
+        }
+    }
+}
+
+// keeps at least half of the delay so retries don't collapse to zero
+pub(crate) fn jitter(delay: Duration) -> Duration {
+    let random = match generate::<[u8; 4]>(&SystemRandom::new()) {
+        Ok(random) => u32::from_be_bytes(random.expose()),
+        Err(_) => return delay,
+    };
+    let half = delay / 2;
+
+    half + half.mul_f64(random as f64 / u32::MAX as f64)
+}
+
+// only retries transient errors
+impl RetryPolicy for ExponentialBackoff {
+    fn retry(&self, attempt: u32, transient: bool, _: &(dyn Error + 'static)) -> Option<Duration> {
+        match transient {
+            true => self.next_delay(attempt),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_until_max() {
+        let backoff = ExponentialBackoff::new()
+            .max_attempts(6)
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5))
+            .jitter(false);
+
+        assert_eq!(backoff.next_delay(0), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.next_delay(1), Some(Duration::from_secs(2)));
+        assert_eq!(backoff.next_delay(2), Some(Duration::from_secs(4)));
+        assert_eq!(backoff.next_delay(3), Some(Duration::from_secs(5)));
+        assert_eq!(backoff.next_delay(4), Some(Duration::from_secs(5)));
+        assert_eq!(backoff.next_delay(5), None);
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let backoff = ExponentialBackoff::new().base_delay(Duration::from_secs(2));
+
+        for _ in 0..100 {
+            let delay = backoff.next_delay(0).unwrap();
+            assert!(delay >= Duration::from_secs(1));
+            assert!(delay <= Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    fn policy_skips_permanent_errors() {
+        let backoff = ExponentialBackoff::new().jitter(false);
+        let err = std::io::Error::new(std::io::ErrorKind::Other, "err");
+
+        assert_eq!(
+            backoff.retry(0, true, &err),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(backoff.retry(0, false, &err), None);
+    }
+
+    #[test]
+    fn disabled_never_retries() {
+        assert_eq!(ExponentialBackoff::disabled().next_delay(0), None);
+    }
+}
@@ -0,0 +1,200 @@
+use acme_core::ApiChallengeType;
+use async_trait::async_trait;
+use aws_sdk_route53::model::{
+    Change, ChangeAction, ChangeBatch, ChangeStatus, ResourceRecord, ResourceRecordSet, RrType,
+};
+use aws_sdk_route53::Client;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::runtime::default_runtime;
+use crate::{BoxError, ChallengeProof, Runtime, Solver};
+
+const TTL: i64 = 60;
+// changes usually take about a minute to reach all route53 nameservers
+const CHANGE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const CHANGE_POLL_ATTEMPTS: usize = 60;
+
+// dns-01 solver for zones hosted on route53, the hosted zone is discovered from the domain
+pub struct Route53Solver {
+    client: Client,
+    // a wildcard and its base domain share one record set
+    records: Mutex<HashMap<String, (String, Vec<String>)>>,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl Route53Solver {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            records: Mutex::new(HashMap::new()),
+            runtime: default_runtime(),
+        }
+    }
+
+    // sleeps while waiting for changes to reach INSYNC
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.runtime = Arc::new(runtime);
+        self
+    }
+
+    // uses the default credential chain, like instance profiles on ec2 or irsa on eks
+    pub async fn from_env() -> Self {
+        let config = aws_config::load_from_env().await;
+        Self::new(Client::new(&config))
+    }
+
+    // walks up the labels until a public hosted zone matches
+    async fn hosted_zone(&self, domain: &str) -> Result<String, BoxError> {
+        let mut candidate = domain.trim_end_matches('.');
+        loop {
+            let name = format!("{}.", candidate);
+            let res = self
+                .client
+                .list_hosted_zones_by_name()
+                .dns_name(&name)
+                .max_items(1)
+                .send()
+                .await?;
+
+            let zone = res.hosted_zones().unwrap_or_default().iter().find(|zone| {
+                let private = zone
+                    .config()
+                    .map(|config| config.private_zone())
+                    .unwrap_or(false);
+                zone.name() == Some(name.as_str()) && !private
+            });
+            if let Some(id) = zone.and_then(|zone| zone.id()) {
+                return Ok(id.to_owned());
+            }
+
+            candidate = match candidate.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => parent,
+                _ => return Err(format!("no hosted zone found for {}", domain).into()),
+            };
+        }
+    }
+
+    async fn change(
+        &self,
+        zone: &str,
+        action: ChangeAction,
+        name: &str,
+        values: &[String],
+    ) -> Result<(), BoxError> {
+        let records = values
+            .iter()
+            .map(|value| {
+                ResourceRecord::builder()
+                    .value(format!("\"{}\"", value))
+                    .build()
+            })
+            .collect();
+        let record_set = ResourceRecordSet::builder()
+            .name(name)
+            .r#type(RrType::Txt)
+            .ttl(TTL)
+            .set_resource_records(Some(records))
+            .build();
+        let batch = ChangeBatch::builder()
+            .changes(
+                Change::builder()
+                    .action(action)
+                    .resource_record_set(record_set)
+                    .build(),
+            )
+            .build();
+
+        let res = self
+            .client
+            .change_resource_record_sets()
+            .hosted_zone_id(zone)
+            .change_batch(batch)
+            .send()
+            .await?;
+
+        match res.change_info().and_then(|info| info.id()) {
+            Some(id) => self.wait_for_change(id).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn wait_for_change(&self, id: &str) -> Result<(), BoxError> {
+        for _ in 0..CHANGE_POLL_ATTEMPTS {
+            let res = self.client.get_change().id(id).send().await?;
+            let status = res.change_info().and_then(|info| info.status());
+            if status == Some(&ChangeStatus::Insync) {
+                return Ok(());
+            }
+            self.runtime.sleep(CHANGE_POLL_INTERVAL).await;
+        }
+
+        Err(format!("change {} did not reach INSYNC", id).into())
+    }
+}
+
+impl Debug for Route53Solver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Route53Solver").finish()
+    }
+}
+
+#[async_trait]
+impl Solver for Route53Solver {
+    fn challenge_type(&self) -> ApiChallengeType {
+        ApiChallengeType::DNS
+    }
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let name = format!("{}.", proof.dns_name());
+        let zone = self.hosted_zone(&name).await?;
+
+        let values = {
+            let mut records = self.records.lock();
+            let (_, values) = records
+                .entry(name.clone())
+                .or_insert_with(|| (zone.clone(), Vec::new()));
+            values.push(proof.dns_value());
+            values.clone()
+        };
+
+        self.change(&zone, ChangeAction::Upsert, &name, &values)
+            .await
+    }
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let name = format!("{}.", proof.dns_name());
+        let value = proof.dns_value();
+
+        let (zone, values, remaining) = {
+            let mut records = self.records.lock();
+            let (zone, values) = match records.get_mut(&name) {
+                Some(record) => record,
+                // present failed before anything was created
+                None => return Ok(()),
+            };
+            let current = values.clone();
+            values.retain(|v| v != &value);
+            let record = (zone.clone(), current, values.clone());
+            if values.is_empty() {
+                records.remove(&name);
+            }
+            record
+        };
+
+        // deleting a record set requires its current values
+        match remaining.is_empty() {
+            true => {
+                self.change(&zone, ChangeAction::Delete, &name, &values)
+                    .await
+            }
+            false => {
+                self.change(&zone, ChangeAction::Upsert, &name, &remaining)
+                    .await
+            }
+        }
+    }
+}
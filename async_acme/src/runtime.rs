@@ -0,0 +1,142 @@
+use futures_util::future::{abortable, AbortHandle};
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use thiserror::Error;
+
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+// the timer and spawn surface used outside of the hyper client
+pub trait Runtime: Debug + Send + Sync + 'static {
+    fn sleep(&self, duration: Duration) -> BoxFuture<()>;
+    fn spawn(&self, future: BoxFuture<()>);
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+impl Runtime for TokioRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    fn spawn(&self, future: BoxFuture<()>) {
+        tokio::spawn(future);
+    }
+}
+
+#[cfg(feature = "async-std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncStdRuntime;
+
+#[cfg(feature = "async-std")]
+impl Runtime for AsyncStdRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+
+    fn spawn(&self, future: BoxFuture<()>) {
+        async_std::task::spawn(future);
+    }
+}
+
+#[cfg(feature = "smol")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmolRuntime;
+
+#[cfg(feature = "smol")]
+impl Runtime for SmolRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+        Box::pin(async move {
+            smol::Timer::after(duration).await;
+        })
+    }
+
+    fn spawn(&self, future: BoxFuture<()>) {
+        smol::spawn(future).detach();
+    }
+}
+
+pub(crate) fn default_runtime() -> Arc<dyn Runtime> {
+    Arc::new(TokioRuntime)
+}
+
+// Runtime::spawn has no join handle, background tasks are stopped through the abort handle
+pub(crate) fn spawn_abortable<F>(runtime: &dyn Runtime, future: F) -> AbortHandle
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let (future, handle) = abortable(future);
+    runtime.spawn(Box::pin(async move {
+        let _ = future.await;
+    }));
+    handle
+}
+
+#[derive(Debug, Error, Clone, Copy, PartialEq)]
+#[error("deadline has elapsed")]
+pub struct Elapsed;
+
+pub(crate) fn timeout<F: Future>(
+    runtime: &dyn Runtime,
+    duration: Duration,
+    future: F,
+) -> Timeout<F> {
+    Timeout {
+        future: Box::pin(future),
+        sleep: runtime.sleep(duration),
+    }
+}
+
+pub(crate) struct Timeout<F> {
+    future: Pin<Box<F>>,
+    sleep: BoxFuture<()>,
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        match self.sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Elapsed)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn timeout_elapses() {
+        let runtime = TokioRuntime;
+
+        let res = timeout(&runtime, Duration::from_secs(1), async { 1 }).await;
+        assert_eq!(res, Ok(1));
+
+        let sleep = runtime.sleep(Duration::from_secs(1));
+        let res = timeout(&runtime, Duration::from_millis(1), sleep).await;
+        assert_eq!(res, Err(Elapsed));
+    }
+
+    #[tokio::test]
+    async fn abort_spawned() {
+        let (tx, rx) = futures_util::channel::oneshot::channel::<()>();
+        let handle = spawn_abortable(&TokioRuntime, async move {
+            let _tx = tx;
+            std::future::pending::<()>().await;
+        });
+
+        handle.abort();
+        // the sender is dropped with the aborted task
+        assert!(rx.await.is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use ring::digest::{digest, SHA256};
+use thiserror::Error;
+use x509_parser::extensions::ParsedExtension;
+
+use crate::chain::leaf_certificate;
+use crate::CertificateBundle;
+
+#[derive(Debug, Error)]
+pub enum SctError {
+    #[error("Leaf certificate can't be parsed")]
+    InvalidCertificate,
+    #[error("Found {found} scts from trusted logs but {required} are required")]
+    NotEnoughScts { required: usize, found: usize },
+}
+
+// signed certificate timestamp embedded into the leaf by the CA
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sct {
+    // sha256 of the public key of the log
+    pub log_id: [u8; 32],
+    // milliseconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl CertificateBundle {
+    pub fn scts(&self) -> Result<Vec<Sct>, SctError> {
+        leaf_certificate(&self.chain, |leaf| {
+            let scts = leaf
+                .extensions()
+                .iter()
+                .filter_map(|extension| match extension.parsed_extension() {
+                    ParsedExtension::SCT(scts) => Some(scts),
+                    _ => None,
+                })
+                .flatten()
+                .map(|sct| Sct {
+                    log_id: *sct.id.key_id,
+                    timestamp: sct.timestamp,
+                })
+                .collect();
+
+            Some(scts)
+        })
+        .ok_or(SctError::InvalidCertificate)
+    }
+}
+
+// checks that enough scts claiming to be from trusted logs are embedded before a certificate
+// gets deployed. the signatures are not verified, so this catches misissued or incomplete
+// certificates of an honest CA but gives no guarantee of ct policy compliance
+#[derive(Debug, Clone)]
+pub struct SctPolicy {
+    trusted_logs: Vec<[u8; 32]>,
+    required: usize,
+}
+
+impl Default for SctPolicy {
+    fn default() -> Self {
+        Self {
+            trusted_logs: Vec::new(),
+            // what chrome requires for short lived certificates
+            required: 2,
+        }
+    }
+}
+
+impl SctPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // der encoded subject public key info as published in the log lists
+    pub fn trusted_log<T: AsRef<[u8]>>(mut self, public_key: T) -> Self {
+        let mut log_id = [0; 32];
+        log_id.copy_from_slice(digest(&SHA256, public_key.as_ref()).as_ref());
+        self.trusted_logs.push(log_id);
+        self
+    }
+
+    pub fn required(mut self, required: usize) -> Self {
+        self.required = required;
+        self
+    }
+
+    // only compares the log ids, the sct signatures are not verified
+    pub fn check_log_ids(&self, bundle: &CertificateBundle) -> Result<Vec<Sct>, SctError> {
+        let scts: Vec<Sct> = bundle
+            .scts()?
+            .into_iter()
+            .filter(|sct| self.trusted_logs.contains(&sct.log_id))
+            .collect();
+
+        match scts.len() >= self.required {
+            true => Ok(scts),
+            false => Err(SctError::NotEnoughScts {
+                required: self.required,
+                found: scts.len(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::{CertificateParams, CustomExtension};
+
+    use super::*;
+
+    const SCT_OID: &[u64] = &[1, 3, 6, 1, 4, 1, 11129, 2, 4, 2];
+
+    // tls encoded sct list wrapped into a der octet string
+    fn sct_extension(log_id: [u8; 32]) -> Vec<u8> {
+        let mut sct = vec![0];
+        sct.extend_from_slice(&log_id);
+        sct.extend_from_slice(&1_600_000_000_000u64.to_be_bytes());
+        // no extensions, sha256 with ecdsa and a dummy signature
+        sct.extend_from_slice(&[0, 0, 4, 3, 0, 2, 1, 2]);
+
+        let mut list = (sct.len() as u16 + 2).to_be_bytes().to_vec();
+        list.extend_from_slice(&(sct.len() as u16).to_be_bytes());
+        list.extend_from_slice(&sct);
+
+        let mut octet_string = vec![4, list.len() as u8];
+        octet_string.extend_from_slice(&list);
+        octet_string
+    }
+
+    fn bundle(extension: Vec<u8>) -> CertificateBundle {
+        let mut params = CertificateParams::new(vec!["example.com".to_string()]);
+        params.custom_extensions = vec![CustomExtension::from_oid_content(SCT_OID, extension)];
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+
+        CertificateBundle {
+            chain: cert.serialize_pem().unwrap().into_bytes(),
+            private_key: cert.serialize_private_key_der(),
+        }
+    }
+
+    #[test]
+    fn checks_log_ids() {
+        let log_key = b"log public key";
+        let mut log_id = [0; 32];
+        log_id.copy_from_slice(digest(&SHA256, log_key).as_ref());
+
+        let bundle = bundle(sct_extension(log_id));
+        assert_eq!(
+            bundle.scts().unwrap(),
+            vec![Sct {
+                log_id,
+                timestamp: 1_600_000_000_000,
+            }]
+        );
+
+        let policy = SctPolicy::new().required(1);
+        assert!(policy.check_log_ids(&bundle).is_err());
+        assert_eq!(
+            policy
+                .trusted_log(log_key)
+                .check_log_ids(&bundle)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -1,31 +1,77 @@
 use acme_core::{
-    AcmeServer, AcmeServerBuilder, ApiAccount, ApiAuthorization, ApiChallenge, ApiDirectory,
-    ApiError, ApiKeyChange, ApiNewOrder, ApiOrder, ApiOrderFinalization, SignedRequest, Uri,
+    AcmeResponse, AcmeServer, AcmeServerBuilder, ApiAccount, ApiAuthorization, ApiChallenge,
+    ApiChallengeResponse, ApiDirectory, ApiError, ApiErrorType, ApiKeyChange, ApiNewOrder,
+    ApiOrder, ApiOrderFinalization, ApiRenewalInfo, ApiRevocation, ByteStream, HttpClient, Jwk,
+    Kid, NoExternalAccountBinding, NoNonce, PostAsGet, Request as JwsRequest, RetryAfter, Uri,
 };
 use async_trait::async_trait;
 use hyper::body::Bytes;
-use hyper::client::connect::Connect as HyperConnect;
-use hyper::http::header::{HeaderName, CONTENT_TYPE};
+use hyper::client::connect::{Connect as HyperConnect, Connection};
+use hyper::http::header::{HeaderName, CONTENT_TYPE, RETRY_AFTER};
 use hyper::http::uri::InvalidUri;
-use hyper::http::HeaderValue;
-use hyper::{body, HeaderMap, Response};
-use hyper::{Body, Client, Request};
+use hyper::http::{HeaderValue, Method, StatusCode};
+use hyper::{Body, Request};
+use hyper::{HeaderMap, Response};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::convert::TryInto;
 use std::fmt::Debug;
 use std::str;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tower::{Layer, Service};
+use tracing::{debug, field, instrument, warn, Span};
+
+use crate::client::Timeouts;
+use crate::rate_limit::retry_after_from_detail;
+use crate::runtime::{default_runtime, timeout};
+use crate::service::{box_layer, BoxLayer};
+use crate::{
+    BoxError, CaptureSink, CapturedExchange, CertificateStream, ConnectTimeout, Elapsed,
+    ExponentialBackoff, HttpService, HyperClient, RateLimit, RetryPolicy, Runtime,
+};
 
 const REPLAY_NONCE_HEADER: &str = "replay-nonce";
 const LOCATION_HEADER: &str = "location";
 
-pub trait Connect: HyperConnect + Clone + Debug + Send + Sync + 'static {}
-impl<C: HyperConnect + Clone + Debug + Send + Sync + 'static> Connect for C {}
+pub(crate) const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// connection attempt delay recommended by RFC 8305
+pub(crate) const HAPPY_EYEBALLS_TIMEOUT: Duration = Duration::from_millis(250);
+// certificate chains are the largest responses and only a few KB
+const MAX_RESPONSE_SIZE: usize = 4 * 1024 * 1024;
+const DEADLINE: Duration = Duration::from_secs(120);
+
+pub trait Connect: HyperConnect + Clone + Debug + Send + Sync + 'static {
+    type Timeout: HyperConnect + Clone + Send + Sync + 'static;
+
+    // wraps the connector so connecting fails after the timeout
+    fn connect_timeout(self, timeout: Duration, runtime: Arc<dyn Runtime>) -> Self::Timeout;
+}
+
+impl<C, T> Connect for C
+where
+    C: Service<hyper::Uri, Response = T> + Clone + Debug + Send + Sync + 'static,
+    C::Error: Into<BoxError>,
+    C::Future: Unpin + Send + 'static,
+    T: AsyncRead + AsyncWrite + Connection + Unpin + Send + 'static,
+{
+    type Timeout = ConnectTimeout<C>;
+
+    fn connect_timeout(self, timeout: Duration, runtime: Arc<dyn Runtime>) -> Self::Timeout {
+        ConnectTimeout::new(self, timeout, runtime)
+    }
+}
 
+#[derive(Clone)]
 enum Endpoint {
     LetsEncryptStaging,
     LetsEncrypt,
+    Buypass,
+    BuypassTest,
+    // the ecc directory as all certificate keys are ecdsa
+    SslCom,
     Url(Cow<'static, str>),
 }
 
@@ -45,16 +91,26 @@ impl Endpoint {
             Endpoint::LetsEncryptStaging => {
                 "https://acme-staging-v02.api.letsencrypt.org/directory"
             }
+            Endpoint::Buypass => "https://api.buypass.com/acme/directory",
+            Endpoint::BuypassTest => "https://api.test4.buypass.no/acme/directory",
+            Endpoint::SslCom => "https://acme.ssl.com/sslcom-dv-ecc",
             Endpoint::Url(endpoint) => endpoint.as_ref(),
         }
     }
+
+    // step-ca serves a directory per acme provisioner
+    fn step_ca(host: &str, provisioner: &str) -> Self {
+        Endpoint::Url(format!("https://{}/acme/{}/directory", host, provisioner).into())
+    }
 }
 
-// todo: retain this error somehow for dyn AcmeServer
+// also used by HttpAcmeServer, errors of other clients end up in Service
 #[derive(Debug, Error)]
 pub enum HyperAcmeServerError {
     #[error("No connector configured")]
     NoConnector,
+    #[error("No http client configured")]
+    NoClient,
     #[error("API returned nonce {0:?}")]
     Nonce(Option<HeaderValue>),
     #[error(transparent)]
@@ -63,69 +119,339 @@ pub enum HyperAcmeServerError {
     Http(#[from] hyper::http::Error),
     #[error(transparent)]
     Json(#[from] serde_json::Error),
-    #[error("API returned error {0:?}")]
-    ApiError(ApiError),
+    #[error("API returned error {0}")]
+    ApiError(#[source] ApiError),
+    #[error("API returned status {0}")]
+    Status(StatusCode),
+    #[error("Rate limited by {limit:?}, retry after {retry_after:?}")]
+    RateLimited {
+        retry_after: Option<RetryAfter>,
+        limit: RateLimit,
+    },
     #[error("Invalid header {0} is {1:?}")]
     InvalidHeader(&'static str, Option<HeaderValue>),
     #[error(transparent)]
     InvalidUri(#[from] InvalidUri),
+    #[error("Request timed out")]
+    Timeout(#[from] Elapsed),
+    #[error("{0}")]
+    Service(BoxError),
+    #[error("Response body exceeds {0} bytes")]
+    BodyTooLarge(usize),
+    #[error("Invalid unix url {0}")]
+    InvalidUnixUrl(String),
+    #[error("Certificate chain contains an incomplete pem block")]
+    InvalidCertificateChain,
+    #[error("Server violates RFC 8555: {0}")]
+    Strict(String),
+    // added once the retries of a request are exhausted
+    #[error("Request to {url} failed after {attempts} attempts: {source}")]
+    Request {
+        url: Uri,
+        attempts: u32,
+        #[source]
+        source: Box<HyperAcmeServerError>,
+    },
 }
 
-pub struct HyperAcmeServerBuilder<C> {
-    connector: Option<C>,
+impl HyperAcmeServerError {
+    // layers and clients can return arbitrary errors, keep the known ones matchable
+    pub(crate) fn from_service(err: BoxError) -> Self {
+        let err = match err.downcast::<HyperAcmeServerError>() {
+            Ok(err) => return *err,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<hyper::Error>() {
+            Ok(err) => return HyperAcmeServerError::Hyper(*err),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<tokio::time::error::Elapsed>() {
+            Ok(_) => return HyperAcmeServerError::Timeout(Elapsed),
+            Err(err) => err,
+        };
+        match err.downcast::<Elapsed>() {
+            Ok(err) => HyperAcmeServerError::Timeout(*err),
+            Err(err) => HyperAcmeServerError::Service(err),
+        }
+    }
+
+    // the error without the url and attempts of the request
+    pub fn without_context(&self) -> &HyperAcmeServerError {
+        match self {
+            HyperAcmeServerError::Request { source, .. } => source.without_context(),
+            err => err,
+        }
+    }
+
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            HyperAcmeServerError::Request { source, .. } => source.class(),
+            HyperAcmeServerError::Hyper(err)
+                if err.is_connect() || err.is_closed() || err.is_incomplete_message() =>
+            {
+                ErrorClass::Transient
+            }
+            HyperAcmeServerError::Status(StatusCode::TOO_MANY_REQUESTS) => ErrorClass::RateLimited,
+            HyperAcmeServerError::Status(status) if status.is_server_error() => {
+                ErrorClass::Transient
+            }
+            HyperAcmeServerError::Timeout(_) => ErrorClass::Transient,
+            HyperAcmeServerError::RateLimited { .. } => ErrorClass::RateLimited,
+            HyperAcmeServerError::ApiError(err) => ErrorClass::from(&err.type_val),
+            _ => ErrorClass::Permanent,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.class().is_retryable()
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        self.class() == ErrorClass::RateLimited
+    }
+
+    pub(crate) fn is_transient(&self) -> bool {
+        self.class() == ErrorClass::Transient
+    }
+
+    // the connection failed before the request was written
+    pub(crate) fn is_unsent(&self) -> bool {
+        match self.without_context() {
+            HyperAcmeServerError::Hyper(err) => err.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+// coarse classification of failures so retry loops don't have to match nested errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    // network failures, timeouts and server errors of the CA, retrying soon should work
+    Transient,
+    // retrying works once the limit resets, which can take hours
+    RateLimited,
+    // a challenge or authorization failed, retrying only helps if the solver is fixed
+    Validation,
+    Permanent,
+}
+
+impl ErrorClass {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ErrorClass::Transient | ErrorClass::RateLimited)
+    }
+}
+
+impl From<&ApiErrorType> for ErrorClass {
+    fn from(type_val: &ApiErrorType) -> Self {
+        match type_val {
+            ApiErrorType::ServerInternal => ErrorClass::Transient,
+            ApiErrorType::RateLimited => ErrorClass::RateLimited,
+            ApiErrorType::CAA
+            | ApiErrorType::Connection
+            | ApiErrorType::DNS
+            | ApiErrorType::IncorrectResponse
+            | ApiErrorType::TLS
+            | ApiErrorType::Unauthorized => ErrorClass::Validation,
+            _ => ErrorClass::Permanent,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HttpAcmeServerBuilder<H> {
+    client: Option<H>,
     endpoint: Endpoint,
+    retry: Arc<dyn RetryPolicy>,
+    deadline: Duration,
+    runtime: Arc<dyn Runtime>,
+    capture: Option<Arc<dyn CaptureSink>>,
+    strict: bool,
 }
 
-impl<C> Default for HyperAcmeServerBuilder<C> {
+impl<H> Default for HttpAcmeServerBuilder<H> {
     fn default() -> Self {
         Self {
-            connector: None,
+            client: None,
             endpoint: Endpoint::LetsEncrypt,
+            retry: Arc::new(ExponentialBackoff::default()),
+            deadline: DEADLINE,
+            runtime: default_runtime(),
+            capture: None,
+            strict: false,
         }
     }
 }
 
+impl<H> HttpAcmeServerBuilder<H> {
+    pub fn client(&mut self, client: H) -> &mut Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn le_staging(&mut self) -> &mut Self {
+        self.endpoint = Endpoint::LetsEncryptStaging;
+        self
+    }
+
+    pub fn buypass(&mut self) -> &mut Self {
+        self.endpoint = Endpoint::Buypass;
+        self
+    }
+
+    pub fn buypass_test(&mut self) -> &mut Self {
+        self.endpoint = Endpoint::BuypassTest;
+        self
+    }
+
+    pub fn ssl_com(&mut self) -> &mut Self {
+        self.endpoint = Endpoint::SslCom;
+        self
+    }
+
+    // host can include a port, e.g. ca.internal:9000
+    pub fn step_ca(&mut self, host: &str, provisioner: &str) -> &mut Self {
+        self.endpoint = Endpoint::step_ca(host, provisioner);
+        self
+    }
+
+    pub fn url<T: Into<Cow<'static, str>>>(&mut self, url: T) -> &mut Self {
+        self.endpoint = Endpoint::from(url);
+        self
+    }
+
+    // signed requests are only resent if the connection failed before sending them,
+    // other failures are retried by Directory with a fresh nonce
+    pub fn retry<R: RetryPolicy + 'static>(&mut self, retry: R) -> &mut Self {
+        self.retry = Arc::new(retry);
+        self
+    }
+
+    // whole request including retries
+    pub fn deadline(&mut self, deadline: Duration) -> &mut Self {
+        self.deadline = deadline;
+        self
+    }
+
+    // used for retry delays and the deadline
+    pub fn runtime<R: Runtime>(&mut self, runtime: R) -> &mut Self {
+        self.runtime = Arc::new(runtime);
+        self
+    }
+
+    // records every signed request and its response, e.g. to debug malformed errors
+    pub fn capture<S: CaptureSink + 'static>(&mut self, capture: S) -> &mut Self {
+        self.capture = Some(Arc::new(capture));
+        self
+    }
+
+    // fails responses which violate RFC 8555, e.g. a missing Replay-Nonce or a wrong
+    // content type, to catch broken private CAs before issuance
+    pub fn strict(&mut self) -> &mut Self {
+        self.strict = true;
+        self
+    }
+}
+
 #[async_trait]
-impl<C: Connect> AcmeServerBuilder for HyperAcmeServerBuilder<C> {
-    type Server = HyperAcmeServer<C>;
+impl<H: HttpClient + Clone> AcmeServerBuilder for HttpAcmeServerBuilder<H> {
+    type Server = HttpAcmeServer<H>;
 
     async fn build(&mut self) -> Result<Self::Server, <Self::Server as AcmeServer>::Error> {
-        let replay_nonce_header = HeaderName::from_static(REPLAY_NONCE_HEADER);
-        let location_header = HeaderName::from_static(LOCATION_HEADER);
-
-        let connector = self
-            .connector
-            .take()
-            .ok_or(HyperAcmeServerError::NoConnector)?;
-        let client = Client::builder().build(connector);
+        let client = self.client.take().ok_or(HyperAcmeServerError::NoClient)?;
 
-        let req = Request::get(self.endpoint.to_str()).body(Body::empty())?;
-        let mut res = client.request(req).await?;
         // todo: add error handling
-        // todo: does no length check if in the future we allow custom acme endpoints we should keep this in mind
-        let body = body::to_bytes(res.body_mut()).await?;
-
-        let directory = serde_json::from_slice(body.as_ref())?;
+        let req = Request::get(self.endpoint.to_str()).body(Vec::new())?;
+        let res = client
+            .request(req)
+            .await
+            .map_err(|err| HyperAcmeServerError::from_service(err.into()))?;
+        let directory = serde_json::from_slice(res.body())?;
 
-        let acme_server = HyperAcmeServer {
-            replay_nonce_header,
-            location_header,
+        Ok(HttpAcmeServer {
+            replay_nonce_header: HeaderName::from_static(REPLAY_NONCE_HEADER),
+            location_header: HeaderName::from_static(LOCATION_HEADER),
             client,
             directory,
-        };
+            retry: self.retry.clone(),
+            deadline: self.deadline,
+            runtime: self.runtime.clone(),
+            capture: self.capture.clone(),
+            strict: self.strict,
+        })
+    }
 
-        Ok(acme_server)
+    fn directory_url(&self) -> Option<&str> {
+        Some(self.endpoint.to_str())
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct HyperAcmeServer<C> {
+pub struct HttpAcmeServer<H> {
     replay_nonce_header: HeaderName,
     location_header: HeaderName,
-    client: Client<C, Body>,
+    client: H,
     directory: ApiDirectory,
+    retry: Arc<dyn RetryPolicy>,
+    deadline: Duration,
+    runtime: Arc<dyn Runtime>,
+    capture: Option<Arc<dyn CaptureSink>>,
+    strict: bool,
+}
+
+#[derive(Clone)]
+pub struct HyperAcmeServerBuilder<C> {
+    connector: Option<C>,
+    inner: HttpAcmeServerBuilder<HyperClient<C>>,
+    timeouts: Timeouts,
+    connect_timeout: Duration,
+    max_response_size: usize,
+    layers: Vec<BoxLayer>,
+}
+
+impl<C> Default for HyperAcmeServerBuilder<C> {
+    fn default() -> Self {
+        Self {
+            connector: None,
+            inner: HttpAcmeServerBuilder::default(),
+            timeouts: Timeouts::default(),
+            connect_timeout: CONNECT_TIMEOUT,
+            max_response_size: MAX_RESPONSE_SIZE,
+            layers: Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl<C: Connect> AcmeServerBuilder for HyperAcmeServerBuilder<C> {
+    type Server = HyperAcmeServer<C>;
+
+    async fn build(&mut self) -> Result<Self::Server, <Self::Server as AcmeServer>::Error> {
+        let connector = self
+            .connector
+            .take()
+            .ok_or(HyperAcmeServerError::NoConnector)?;
+        let runtime = self.inner.runtime.clone();
+        let client = HyperClient::new(
+            connector.connect_timeout(self.connect_timeout, runtime.clone()),
+            &self.layers,
+            self.timeouts,
+            self.max_response_size,
+            runtime,
+        );
+
+        let server = self.inner.client(client).build().await?;
+        Ok(HyperAcmeServer(server))
+    }
+
+    fn directory_url(&self) -> Option<&str> {
+        self.inner.directory_url()
+    }
 }
 
+// HttpAcmeServer using hyper as http client
+#[derive(Debug, Clone)]
+pub struct HyperAcmeServer<C>(HttpAcmeServer<HyperClient<C>>);
+
 impl<C> HyperAcmeServerBuilder<C> {
     pub fn connector(&mut self, connector: C) -> &mut Self {
         self.connector = Some(connector);
@@ -133,29 +459,319 @@ impl<C> HyperAcmeServerBuilder<C> {
     }
 
     pub fn le_staging(&mut self) -> &mut Self {
-        self.endpoint = Endpoint::LetsEncryptStaging;
+        self.inner.le_staging();
+        self
+    }
+
+    pub fn buypass(&mut self) -> &mut Self {
+        self.inner.buypass();
+        self
+    }
+
+    pub fn buypass_test(&mut self) -> &mut Self {
+        self.inner.buypass_test();
+        self
+    }
+
+    pub fn ssl_com(&mut self) -> &mut Self {
+        self.inner.ssl_com();
+        self
+    }
+
+    pub fn step_ca(&mut self, host: &str, provisioner: &str) -> &mut Self {
+        self.inner.step_ca(host, provisioner);
         self
     }
 
     pub fn url<T: Into<Cow<'static, str>>>(&mut self, url: T) -> &mut Self {
-        self.endpoint = Endpoint::from(url);
+        self.inner.url(url);
+        self
+    }
+
+    pub fn retry<R: RetryPolicy + 'static>(&mut self, retry: R) -> &mut Self {
+        self.inner.retry(retry);
+        self
+    }
+
+    // applies to custom connectors as well, 10 seconds by default
+    pub fn connect_timeout(&mut self, connect_timeout: Duration) -> &mut Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn response_timeout(&mut self, response_timeout: Duration) -> &mut Self {
+        self.timeouts.response = response_timeout;
+        self
+    }
+
+    pub fn read_timeout(&mut self, read_timeout: Duration) -> &mut Self {
+        self.timeouts.read = read_timeout;
+        self
+    }
+
+    pub fn deadline(&mut self, deadline: Duration) -> &mut Self {
+        self.inner.deadline(deadline);
+        self
+    }
+
+    // used for all timeouts and retry delays, hyper itself still needs tokio
+    pub fn runtime<R: Runtime>(&mut self, runtime: R) -> &mut Self {
+        self.inner.runtime(runtime);
+        self
+    }
+
+    pub fn max_response_size(&mut self, max_response_size: usize) -> &mut Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
+    pub fn layer<L>(&mut self, layer: L) -> &mut Self
+    where
+        L: Layer<HttpService> + Send + Sync + 'static,
+        L::Service: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+        <L::Service as Service<Request<Body>>>::Error: Into<BoxError>,
+        <L::Service as Service<Request<Body>>>::Future: Send + 'static,
+    {
+        self.layers.push(box_layer(layer));
+        self
+    }
+
+    // signatures and keys are redacted, see CapturedExchange
+    pub fn capture<S: CaptureSink + 'static>(&mut self, capture: S) -> &mut Self {
+        self.inner.capture(capture);
+        self
+    }
+
+    pub fn strict(&mut self) -> &mut Self {
+        self.inner.strict();
         self
     }
 }
 
 static APPLICATION_JOSE_JSON: HeaderValue = HeaderValue::from_static("application/jose+json");
+const APPLICATION_JSON: &str = "application/json";
+const APPLICATION_PEM_CHAIN: &str = "application/pem-certificate-chain";
+
+// every response carries a nonce, RFC 8555 section 6.5
+fn check_strict<B>(
+    res: &Response<B>,
+    replay_nonce_header: &HeaderName,
+    content_type: Option<&'static str>,
+) -> Result<(), HyperAcmeServerError> {
+    if !res.headers().contains_key(replay_nonce_header) {
+        return Err(HyperAcmeServerError::Strict(format!(
+            "response is missing the {} header",
+            REPLAY_NONCE_HEADER
+        )));
+    }
+
+    let expected = match content_type {
+        Some(expected) => expected,
+        None => return Ok(()),
+    };
+    // parameters like charset are allowed
+    let actual = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|actual| actual.to_str().ok())
+        .and_then(|actual| actual.split(';').next())
+        .map(str::trim);
+    match actual {
+        Some(actual) if actual.eq_ignore_ascii_case(expected) => Ok(()),
+        actual => Err(HyperAcmeServerError::Strict(format!(
+            "expected content type {} but got {:?}",
+            expected, actual
+        ))),
+    }
+}
+
+// collects the response body of a request, error responses are always buffered so
+// handle_if_error can parse the problem document
+#[async_trait]
+trait Collect<H>: Send + Sync {
+    type Body: Send;
+
+    // Err is the buffered error response
+    async fn collect(
+        &self,
+        client: &H,
+        req: Request<Vec<u8>>,
+    ) -> Result<Result<Response<Self::Body>, Response<Vec<u8>>>, HyperAcmeServerError>;
+
+    // None if the body is not available for capture
+    fn captured(body: &Self::Body) -> Option<&[u8]>;
+}
+
+struct Buffered;
+
+#[async_trait]
+impl<H: HttpClient> Collect<H> for Buffered {
+    type Body = Vec<u8>;
+
+    async fn collect(
+        &self,
+        client: &H,
+        req: Request<Vec<u8>>,
+    ) -> Result<Result<Response<Vec<u8>>, Response<Vec<u8>>>, HyperAcmeServerError> {
+        let res = client
+            .request(req)
+            .await
+            .map_err(|err| HyperAcmeServerError::from_service(err.into()))?;
+
+        match res.status().is_success() {
+            true => Ok(Ok(res)),
+            false => Ok(Err(res)),
+        }
+    }
 
-impl<C: Connect> HyperAcmeServer<C> {
-    fn handle_if_error(
+    fn captured(body: &Vec<u8>) -> Option<&[u8]> {
+        Some(body)
+    }
+}
+
+// only the body of successful responses is streamed
+struct Streamed;
+
+#[async_trait]
+impl<C: Connect> Collect<HyperClient<C>> for Streamed {
+    type Body = CertificateStream;
+
+    async fn collect(
         &self,
-        res: &Response<Body>,
-        body: &Bytes,
-    ) -> Result<(), HyperAcmeServerError> {
+        client: &HyperClient<C>,
+        req: Request<Vec<u8>>,
+    ) -> Result<Result<Response<CertificateStream>, Response<Vec<u8>>>, HyperAcmeServerError> {
+        let (parts, body) = client.send(req).await?.into_parts();
+        if parts.status.is_success() {
+            let body = client.certificate_stream(body);
+            return Ok(Ok(Response::from_parts(parts, body)));
+        }
+
+        let body = client.read(body).await?;
+        Ok(Err(Response::from_parts(parts, body)))
+    }
+
+    fn captured(_: &CertificateStream) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl<H: HttpClient> HttpAcmeServer<H> {
+    fn handle_if_error(&self, res: &Response<Vec<u8>>) -> Result<(), HyperAcmeServerError> {
         if res.status().is_success() {
             return Ok(());
         }
-        let error: ApiError = serde_json::from_slice(body.as_ref())?;
-        Err(HyperAcmeServerError::ApiError(error))
+        // proxies in front of the CA don't answer with problem documents
+        let error: ApiError = match serde_json::from_slice(res.body()) {
+            Ok(error) => error,
+            Err(_) => return Err(HyperAcmeServerError::Status(res.status())),
+        };
+
+        if error.type_val != ApiErrorType::RateLimited {
+            return Err(HyperAcmeServerError::ApiError(error));
+        }
+
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|retry_after| retry_after.to_str().ok())
+            .and_then(RetryAfter::parse)
+            .or_else(|| retry_after_from_detail(&error.detail));
+
+        Err(HyperAcmeServerError::RateLimited {
+            retry_after,
+            limit: RateLimit::from_detail(&error.detail),
+        })
+    }
+
+    async fn send<F, C>(
+        &self,
+        uri: &Uri,
+        request: F,
+        collect: &C,
+    ) -> Result<Response<C::Body>, HyperAcmeServerError>
+    where
+        F: Fn() -> Result<Request<Vec<u8>>, hyper::http::Error> + Send,
+        C: Collect<H>,
+    {
+        let mut attempts = 0;
+        let send = self.send_with_retry(request, collect, &mut attempts);
+        let res = match timeout(&*self.runtime, self.deadline, send).await {
+            Ok(res) => res,
+            Err(err) => Err(err.into()),
+        };
+
+        res.map_err(|err| HyperAcmeServerError::Request {
+            url: uri.clone(),
+            attempts,
+            source: Box::new(err),
+        })
+    }
+
+    async fn send_with_retry<F, C>(
+        &self,
+        request: F,
+        collect: &C,
+        attempts: &mut u32,
+    ) -> Result<Response<C::Body>, HyperAcmeServerError>
+    where
+        F: Fn() -> Result<Request<Vec<u8>>, hyper::http::Error> + Send,
+        C: Collect<H>,
+    {
+        let mut attempt = 0;
+        loop {
+            *attempts = attempt + 1;
+            let req = request()?;
+            // the nonce of a signed request is consumed once it reached the CA,
+            // those are re-signed by Directory instead, see DirectoryBuilder::retry
+            let signed = req.method() == Method::POST;
+            let err = match self.send_once(req, collect).await {
+                Ok(res) => return Ok(res),
+                Err(err) => err,
+            };
+
+            let transient = match signed {
+                true => err.is_unsent(),
+                false => err.is_transient(),
+            };
+            let delay = self.retry.retry(attempt, transient, &err);
+            match delay {
+                Some(delay) => {
+                    warn!(attempt, ?delay, error = %err, "retrying transient failure");
+                    self.runtime.sleep(delay).await
+                }
+                None => return Err(err),
+            }
+
+            attempt += 1;
+        }
+    }
+
+    // the body is skipped as it contains signed payloads
+    #[instrument(skip_all, fields(method = %req.method(), url = %req.uri(), status = field::Empty))]
+    async fn send_once<C: Collect<H>>(
+        &self,
+        req: Request<Vec<u8>>,
+        collect: &C,
+    ) -> Result<Response<C::Body>, HyperAcmeServerError> {
+        let res = collect.collect(&self.client, req).await?;
+        let (status, headers) = match &res {
+            Ok(res) => (res.status(), res.headers()),
+            Err(res) => (res.status(), res.headers()),
+        };
+        Span::current().record("status", &status.as_u16());
+        debug!(
+            nonce = headers.contains_key(&self.replay_nonce_header),
+            "received response"
+        );
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(res) => {
+                self.handle_if_error(&res)?;
+                Err(HyperAcmeServerError::Status(res.status()))
+            }
+        }
     }
 
     fn extract_location(
@@ -189,47 +805,84 @@ impl<C: Connect> HyperAcmeServer<C> {
         &self,
         body: T,
         uri: &Uri,
-    ) -> Result<(R, Option<Uri>), HyperAcmeServerError>
+    ) -> Result<AcmeResponse<R>, HyperAcmeServerError>
     where
         R: for<'a> Deserialize<'a>,
     {
-        let (res, location) = self.post(body, uri).await?;
-        let res = serde_json::from_slice(res.as_ref())?;
-        Ok((res, location))
+        let res = self.post(body, uri, Some(APPLICATION_JSON)).await?;
+        let body = serde_json::from_slice(res.body.as_ref())?;
+        Ok(res.map(|_| body))
     }
 
+    // content_type is only checked in strict mode
     async fn post<T: Serialize>(
         &self,
         body: T,
         uri: &Uri,
-    ) -> Result<(Bytes, Option<Uri>), HyperAcmeServerError> {
+        content_type: Option<&'static str>,
+    ) -> Result<AcmeResponse<Bytes>, HyperAcmeServerError> {
+        let res = self.post_with(body, uri, content_type, &Buffered).await?;
+        Ok(res.map(Bytes::from))
+    }
+
+    // like post but collect decides how the body of a successful response is read
+    async fn post_with<T: Serialize, C: Collect<H>>(
+        &self,
+        body: T,
+        uri: &Uri,
+        content_type: Option<&'static str>,
+        collect: &C,
+    ) -> Result<AcmeResponse<C::Body>, HyperAcmeServerError> {
         let body = serde_json::to_vec(&body)?;
 
-        let mut req = Request::post(uri).body(Body::from(body))?;
-        req.headers_mut()
-            .append(CONTENT_TYPE, APPLICATION_JOSE_JSON.clone());
+        let res = self
+            .send(
+                uri,
+                || {
+                    Request::post(uri)
+                        .header(CONTENT_TYPE, APPLICATION_JOSE_JSON.clone())
+                        .body(body.clone())
+                },
+                collect,
+            )
+            .await;
 
-        let mut res = self.client.request(req).await?;
-        // todo: also no length check here
-        let body = body::to_bytes(res.body_mut()).await?;
-        self.handle_if_error(&res, &body)?;
+        if let Some(capture) = &self.capture {
+            let exchange = CapturedExchange::new(uri.to_string(), &body);
+            let exchange = match &res {
+                Ok(res) => exchange.response(res.status().as_u16(), C::captured(res.body())),
+                Err(err) => exchange.error(err),
+            };
+            capture.capture(exchange);
+        }
+        let res = res?;
+        if self.strict {
+            check_strict(&res, &self.replay_nonce_header, content_type)?;
+        }
 
-        let location = self.extract_location(res.headers_mut())?;
+        let (mut parts, body) = res.into_parts();
+        let location = self.extract_location(&mut parts.headers)?;
+        let mut res = AcmeResponse::from_headers(body, &parts.headers);
+        res.location = location;
 
-        Ok((body, location))
+        Ok(res)
     }
 }
 
 #[async_trait]
-impl<C: Connect> AcmeServer for HyperAcmeServer<C> {
+impl<H: HttpClient + Clone> AcmeServer for HttpAcmeServer<H> {
     type Error = HyperAcmeServerError;
-    type Builder = HyperAcmeServerBuilder<C>;
+    type Builder = HttpAcmeServerBuilder<H>;
 
+    #[instrument(skip_all)]
     async fn new_nonce(&self) -> Result<String, Self::Error> {
-        let req = Request::head(&self.directory.new_nonce).body(Body::empty())?;
-        let mut res = self.client.request(req).await?;
-        let body = body::to_bytes(res.body_mut()).await?;
-        self.handle_if_error(&res, &body)?;
+        let mut res = self
+            .send(
+                &self.directory.new_nonce,
+                || Request::head(&self.directory.new_nonce).body(Vec::new()),
+                &Buffered,
+            )
+            .await?;
 
         let nonce = res
             .headers_mut()
@@ -237,7 +890,10 @@ impl<C: Connect> AcmeServer for HyperAcmeServer<C> {
             .ok_or(HyperAcmeServerError::Nonce(None))?;
 
         match nonce.to_str() {
-            Ok(nonce) => Ok(nonce.to_owned()),
+            Ok(nonce) => {
+                debug!("fetched new nonce");
+                Ok(nonce.to_owned())
+            }
             Err(_) => Err(HyperAcmeServerError::Nonce(Some(nonce))),
         }
     }
@@ -246,111 +902,251 @@ impl<C: Connect> AcmeServer for HyperAcmeServer<C> {
         &self.directory
     }
 
-    async fn new_account(
+    #[instrument(skip_all)]
+    async fn new_account<E: JwsRequest<Jwk, Kid, NoNonce>>(
         &self,
-        req: SignedRequest<ApiAccount<()>>,
-    ) -> Result<(ApiAccount<()>, Uri), Self::Error> {
-        let (account, kid) = self
+        req: impl JwsRequest<ApiAccount<E>, Jwk>,
+    ) -> Result<AcmeResponse<(ApiAccount, Uri)>, Self::Error> {
+        let res = self
             .post_and_deserialize(req, &self.directory.new_account)
             .await?;
 
-        let kid = match kid {
-            Some(kid) => kid,
+        let kid = match &res.location {
+            Some(kid) => kid.clone(),
             None => return Err(HyperAcmeServerError::InvalidHeader(LOCATION_HEADER, None)),
         };
 
-        Ok((account, kid))
+        Ok(res.map(|account| (account, kid)))
     }
 
+    #[instrument(skip_all)]
     async fn get_account(
         &self,
         uri: &Uri,
-        req: SignedRequest<()>,
-    ) -> Result<ApiAccount<()>, Self::Error> {
-        let (account, _) = self.post_and_deserialize(req, uri).await?;
-        Ok(account)
+        req: impl JwsRequest<PostAsGet>,
+    ) -> Result<AcmeResponse<ApiAccount>, Self::Error> {
+        self.post_and_deserialize(req, uri).await
     }
 
+    #[instrument(skip_all)]
     async fn update_account(
         &self,
         uri: &Uri,
-        req: SignedRequest<ApiAccount<()>>,
-    ) -> Result<ApiAccount<()>, Self::Error> {
-        let (account, _) = self.post_and_deserialize(req, uri).await?;
-        Ok(account)
+        req: impl JwsRequest<ApiAccount<NoExternalAccountBinding>>,
+    ) -> Result<AcmeResponse<ApiAccount>, Self::Error> {
+        self.post_and_deserialize(req, uri).await
     }
 
-    async fn change_key<K: Send>(
+    #[instrument(skip_all)]
+    async fn change_key<R: JwsRequest<ApiKeyChange<Jwk>, Jwk, NoNonce>>(
         &self,
-        req: SignedRequest<SignedRequest<ApiKeyChange<K>>>,
-    ) -> Result<(), Self::Error> {
-        let ((), _) = self
-            .post_and_deserialize(req, &self.directory.key_change)
+        req: impl JwsRequest<R>,
+    ) -> Result<AcmeResponse<()>, Self::Error> {
+        // the CA answers with the account object which isn't needed
+        let res = self
+            .post(req, &self.directory.key_change, Some(APPLICATION_JSON))
             .await?;
-
-        Ok(())
+        Ok(res.map(|_| ()))
     }
 
+    #[instrument(skip_all)]
     async fn new_order(
         &self,
-        req: SignedRequest<ApiNewOrder>,
-    ) -> Result<(ApiOrder<()>, Uri), Self::Error> {
-        let (order, location) = self
+        req: impl JwsRequest<ApiNewOrder>,
+    ) -> Result<AcmeResponse<(ApiOrder, Uri)>, Self::Error> {
+        let res = self
             .post_and_deserialize(req, &self.directory.new_order)
             .await?;
 
-        let location = match location {
-            Some(location) => location,
+        let location = match &res.location {
+            Some(location) => location.clone(),
             None => return Err(HyperAcmeServerError::InvalidHeader(LOCATION_HEADER, None)),
         };
 
-        Ok((order, location))
+        Ok(res.map(|order| (order, location)))
     }
 
+    #[instrument(skip_all)]
     async fn get_order(
         &self,
         uri: &Uri,
-        req: SignedRequest<()>,
-    ) -> Result<ApiOrder<()>, Self::Error> {
-        let (order, _) = self.post_and_deserialize(req, uri).await?;
-        Ok(order)
+        req: impl JwsRequest<PostAsGet>,
+    ) -> Result<AcmeResponse<ApiOrder>, Self::Error> {
+        self.post_and_deserialize(req, uri).await
     }
 
     // todo: use retry Retry-After header
+    #[instrument(skip_all)]
     async fn get_authorization(
         &self,
         uri: &Uri,
-        req: SignedRequest<()>,
-    ) -> Result<ApiAuthorization, Self::Error> {
-        let (authorization, _) = self.post_and_deserialize(req, uri).await?;
-        Ok(authorization)
+        req: impl JwsRequest<PostAsGet>,
+    ) -> Result<AcmeResponse<ApiAuthorization>, Self::Error> {
+        self.post_and_deserialize(req, uri).await
     }
 
+    #[instrument(skip_all)]
     async fn validate_challenge(
         &self,
         uri: &Uri,
-        req: SignedRequest<()>,
-    ) -> Result<ApiChallenge, Self::Error> {
-        let (challenge, _) = self.post_and_deserialize(req, uri).await?;
-        Ok(challenge)
+        req: impl JwsRequest<ApiChallengeResponse>,
+    ) -> Result<AcmeResponse<ApiChallenge>, Self::Error> {
+        self.post_and_deserialize(req, uri).await
     }
 
+    #[instrument(skip_all)]
     async fn finalize(
         &self,
         uri: &Uri,
-        req: SignedRequest<ApiOrderFinalization>,
-    ) -> Result<ApiOrder<()>, Self::Error> {
-        let (order, _) = self.post_and_deserialize(req, uri).await?;
-        Ok(order)
+        req: impl JwsRequest<ApiOrderFinalization>,
+    ) -> Result<AcmeResponse<ApiOrder>, Self::Error> {
+        self.post_and_deserialize(req, uri).await
     }
 
+    #[instrument(skip_all)]
     async fn download_certificate(
         &self,
         uri: &Uri,
-        req: SignedRequest<()>,
-    ) -> Result<Vec<u8>, Self::Error> {
-        let (res, _) = self.post(req, uri).await?;
-        Ok(res.to_vec())
+        req: impl JwsRequest<PostAsGet>,
+    ) -> Result<AcmeResponse<Vec<u8>>, Self::Error> {
+        let res = self.post(req, uri, Some(APPLICATION_PEM_CHAIN)).await?;
+        Ok(res.map(|body| body.to_vec()))
+    }
+
+    // the CA answers with an empty body
+    #[instrument(skip_all)]
+    async fn revoke_certificate(
+        &self,
+        req: impl JwsRequest<ApiRevocation>,
+    ) -> Result<AcmeResponse<()>, Self::Error> {
+        let res = self.post(req, &self.directory.revoke_cert, None).await?;
+        Ok(res.map(|_| ()))
+    }
+
+    #[instrument(skip_all)]
+    async fn renewal_info(&self, uri: &Uri) -> Result<AcmeResponse<ApiRenewalInfo>, Self::Error> {
+        let res = self
+            .send(uri, || Request::get(uri).body(Vec::new()), &Buffered)
+            .await?;
+        let body = serde_json::from_slice(res.body())?;
+
+        Ok(AcmeResponse::from_headers(body, res.headers()))
+    }
+}
+
+#[async_trait]
+impl<C: Connect> AcmeServer for HyperAcmeServer<C> {
+    type Error = HyperAcmeServerError;
+    type Builder = HyperAcmeServerBuilder<C>;
+
+    async fn new_nonce(&self) -> Result<String, Self::Error> {
+        self.0.new_nonce().await
+    }
+
+    fn directory(&self) -> &ApiDirectory {
+        self.0.directory()
+    }
+
+    async fn new_account<E: JwsRequest<Jwk, Kid, NoNonce>>(
+        &self,
+        req: impl JwsRequest<ApiAccount<E>, Jwk>,
+    ) -> Result<AcmeResponse<(ApiAccount, Uri)>, Self::Error> {
+        self.0.new_account(req).await
+    }
+
+    async fn get_account(
+        &self,
+        uri: &Uri,
+        req: impl JwsRequest<PostAsGet>,
+    ) -> Result<AcmeResponse<ApiAccount>, Self::Error> {
+        self.0.get_account(uri, req).await
+    }
+
+    async fn update_account(
+        &self,
+        uri: &Uri,
+        req: impl JwsRequest<ApiAccount<NoExternalAccountBinding>>,
+    ) -> Result<AcmeResponse<ApiAccount>, Self::Error> {
+        self.0.update_account(uri, req).await
+    }
+
+    async fn change_key<R: JwsRequest<ApiKeyChange<Jwk>, Jwk, NoNonce>>(
+        &self,
+        req: impl JwsRequest<R>,
+    ) -> Result<AcmeResponse<()>, Self::Error> {
+        self.0.change_key(req).await
+    }
+
+    async fn new_order(
+        &self,
+        req: impl JwsRequest<ApiNewOrder>,
+    ) -> Result<AcmeResponse<(ApiOrder, Uri)>, Self::Error> {
+        self.0.new_order(req).await
+    }
+
+    async fn get_order(
+        &self,
+        uri: &Uri,
+        req: impl JwsRequest<PostAsGet>,
+    ) -> Result<AcmeResponse<ApiOrder>, Self::Error> {
+        self.0.get_order(uri, req).await
+    }
+
+    async fn get_authorization(
+        &self,
+        uri: &Uri,
+        req: impl JwsRequest<PostAsGet>,
+    ) -> Result<AcmeResponse<ApiAuthorization>, Self::Error> {
+        self.0.get_authorization(uri, req).await
+    }
+
+    async fn validate_challenge(
+        &self,
+        uri: &Uri,
+        req: impl JwsRequest<ApiChallengeResponse>,
+    ) -> Result<AcmeResponse<ApiChallenge>, Self::Error> {
+        self.0.validate_challenge(uri, req).await
+    }
+
+    async fn finalize(
+        &self,
+        uri: &Uri,
+        req: impl JwsRequest<ApiOrderFinalization>,
+    ) -> Result<AcmeResponse<ApiOrder>, Self::Error> {
+        self.0.finalize(uri, req).await
+    }
+
+    async fn download_certificate(
+        &self,
+        uri: &Uri,
+        req: impl JwsRequest<PostAsGet>,
+    ) -> Result<AcmeResponse<Vec<u8>>, Self::Error> {
+        self.0.download_certificate(uri, req).await
+    }
+
+    // yields the chain block by block, the body is not captured
+    #[instrument(skip_all)]
+    async fn download_certificate_stream(
+        &self,
+        uri: &Uri,
+        req: impl JwsRequest<PostAsGet>,
+    ) -> Result<AcmeResponse<ByteStream<Self::Error>>, Self::Error> {
+        let res = self
+            .0
+            .post_with(req, uri, Some(APPLICATION_PEM_CHAIN), &Streamed)
+            .await?;
+        Ok(res.map(CertificateStream::into_byte_stream))
+    }
+
+    async fn revoke_certificate(
+        &self,
+        req: impl JwsRequest<ApiRevocation>,
+    ) -> Result<AcmeResponse<()>, Self::Error> {
+        self.0.revoke_certificate(req).await
+    }
+
+    async fn renewal_info(&self, uri: &Uri) -> Result<AcmeResponse<ApiRenewalInfo>, Self::Error> {
+        self.0.renewal_info(uri).await
     }
 }
 
@@ -380,7 +1176,7 @@ mod tests {
             .await?;
 
         // check if directory getter works as expected
-        assert_eq!(&server.directory, server.directory());
+        assert_eq!(&server.0.directory, server.directory());
 
         // test if we get a nonce and if two nonces are different
         let nonce_one = server.new_nonce().await?;
@@ -396,8 +1192,9 @@ mod tests {
             new_authz,
             revoke_cert,
             key_change,
+            renewal_info,
             meta,
-        } = server.directory;
+        } = server.0.directory;
 
         // test if directory returns correct url
         assert_eq!(new_nonce, Uri::try_from(stepca.endpoint("/new-nonce"))?);
@@ -406,11 +1203,79 @@ mod tests {
         assert_eq!(new_authz, None);
         assert_eq!(revoke_cert, Uri::try_from(stepca.endpoint("/revoke-cert"))?);
         assert_eq!(key_change, Uri::try_from(stepca.endpoint("/key-change"))?);
+        assert_eq!(renewal_info, None);
         assert_eq!(meta, None);
 
         Ok(())
     }
 
+    #[test]
+    fn transient_errors() {
+        assert!(HyperAcmeServerError::Status(StatusCode::BAD_GATEWAY).is_transient());
+        assert!(!HyperAcmeServerError::Status(StatusCode::BAD_REQUEST).is_transient());
+
+        let error = |type_val| {
+            HyperAcmeServerError::ApiError(ApiError {
+                type_val,
+                detail: String::new(),
+                subproblems: Vec::new(),
+            })
+        };
+        assert!(error(ApiErrorType::ServerInternal).is_transient());
+        assert!(!error(ApiErrorType::BadCSR).is_transient());
+
+        assert!(HyperAcmeServerError::Status(StatusCode::TOO_MANY_REQUESTS).is_rate_limited());
+        assert!(error(ApiErrorType::RateLimited).is_retryable());
+        assert_eq!(
+            error(ApiErrorType::IncorrectResponse).class(),
+            ErrorClass::Validation
+        );
+        assert_eq!(error(ApiErrorType::BadCSR).class(), ErrorClass::Permanent);
+
+        let err = HyperAcmeServerError::Request {
+            url: Uri::try_from("https://ca/new-order").unwrap(),
+            attempts: 3,
+            source: Box::new(HyperAcmeServerError::Status(StatusCode::BAD_GATEWAY)),
+        };
+        assert!(err.is_transient());
+        // the CA received the request, a signed request has to be signed again
+        assert!(!err.is_unsent());
+        assert!(matches!(
+            err.without_context(),
+            HyperAcmeServerError::Status(StatusCode::BAD_GATEWAY)
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Request to https://ca/new-order failed after 3 attempts: API returned status 502 Bad Gateway"
+        );
+    }
+
+    #[test]
+    fn strict_responses() {
+        let nonce = HeaderName::from_static(REPLAY_NONCE_HEADER);
+        let response = |content_type: &str| {
+            Response::builder()
+                .header(REPLAY_NONCE_HEADER, "nonce")
+                .header(CONTENT_TYPE, content_type)
+                .body(Vec::new())
+                .unwrap()
+        };
+
+        let res = response("application/json; charset=utf-8");
+        assert!(check_strict(&res, &nonce, Some(APPLICATION_JSON)).is_ok());
+        assert!(check_strict(&res, &nonce, Some(APPLICATION_PEM_CHAIN)).is_err());
+        assert!(check_strict(&response("text/plain"), &nonce, None).is_ok());
+
+        let res = Response::builder()
+            .header(CONTENT_TYPE, APPLICATION_JSON)
+            .body(Vec::new())
+            .unwrap();
+        assert!(matches!(
+            check_strict(&res, &nonce, Some(APPLICATION_JSON)),
+            Err(HyperAcmeServerError::Strict(_))
+        ));
+    }
+
     #[test]
     fn endpoint_should_return_correct_url() {
         assert_eq!(
@@ -423,6 +1288,12 @@ mod tests {
         );
 
         let endpoint = Endpoint::from("https://test.com");
-        assert_eq!("https://test.com", endpoint.to_str())
+        assert_eq!("https://test.com", endpoint.to_str());
+
+        let endpoint = Endpoint::step_ca("ca.internal:9000", "acme");
+        assert_eq!(
+            "https://ca.internal:9000/acme/acme/directory",
+            endpoint.to_str()
+        );
     }
 }
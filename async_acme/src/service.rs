@@ -0,0 +1,44 @@
+use hyper::{Body, Request, Response};
+use parking_lot::Mutex;
+use std::error::Error;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+use tower::util::BoxCloneService;
+use tower::{Layer, Service, ServiceExt};
+
+pub type BoxError = Box<dyn Error + Send + Sync>;
+pub type HttpService = BoxCloneService<Request<Body>, Response<Body>, BoxError>;
+
+// shared so builders holding layers stay cloneable
+pub(crate) type BoxLayer = Arc<dyn Fn(HttpService) -> HttpService + Send + Sync>;
+
+pub(crate) fn box_layer<L>(layer: L) -> BoxLayer
+where
+    L: Layer<HttpService> + Send + Sync + 'static,
+    L::Service: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    <L::Service as Service<Request<Body>>>::Error: Into<BoxError>,
+    <L::Service as Service<Request<Body>>>::Future: Send + 'static,
+{
+    Arc::new(move |service| BoxCloneService::new(layer.layer(service).map_err(Into::into)))
+}
+
+// BoxCloneService is not Sync, so we only hold the lock to clone it
+#[derive(Clone)]
+pub(crate) struct SharedService(Arc<Mutex<HttpService>>);
+
+impl SharedService {
+    pub(crate) fn new(service: HttpService) -> Self {
+        Self(Arc::new(Mutex::new(service)))
+    }
+
+    pub(crate) async fn call(&self, req: Request<Body>) -> Result<Response<Body>, BoxError> {
+        let service = self.0.lock().clone();
+        service.oneshot(req).await
+    }
+}
+
+impl Debug for SharedService {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedService").finish()
+    }
+}
@@ -0,0 +1,162 @@
+use parking_lot::RwLock;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+
+use crate::chain::leaf_certificate;
+use crate::{CertificateBundle, CertificateStore, Persist, StoreError};
+#[cfg(feature = "tls-alpn")]
+use crate::{TlsAlpnAcceptor, TlsAlpnChallenges};
+
+// picks the certificate by sni, names are taken from the subject alternative names so a
+// *.example.com certificate answers for www.example.com but not for example.com
+#[derive(Clone, Default)]
+pub struct SniResolver(Arc<RwLock<HashMap<String, Arc<CertifiedKey>>>>);
+
+impl SniResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // replaces the certificates of the names in bundle, returns false if it can't be parsed
+    pub fn insert(&self, bundle: &CertificateBundle) -> bool {
+        let (names, key) = match (names(&bundle.chain), bundle.certified_key()) {
+            (Some(names), Some(key)) => (names, Arc::new(key)),
+            _ => return false,
+        };
+
+        let mut certs = self.0.write();
+        for name in names {
+            certs.insert(name, key.clone());
+        }
+        true
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.0.write().remove(&name.to_ascii_lowercase());
+    }
+
+    // loads the stored certificate of every domain, missing ones are skipped
+    pub async fn load<P, T>(
+        &self,
+        store: &CertificateStore<P>,
+        domains: &[T],
+    ) -> Result<(), StoreError>
+    where
+        P: Persist + Send + Sync,
+        T: AsRef<str>,
+    {
+        for domain in domains {
+            let domain = domain.as_ref();
+            if let Some(bundle) = store.get(&[domain]).await? {
+                if !self.insert(&bundle) {
+                    return Err(StoreError::InvalidCertificate(domain.to_owned()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn server_config(&self) -> ServerConfig {
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(self.clone()))
+    }
+
+    // also answers the tls-alpn-01 validations of challenges on the same listener
+    #[cfg(feature = "tls-alpn")]
+    pub fn acceptor(&self, challenges: TlsAlpnChallenges) -> TlsAlpnAcceptor {
+        TlsAlpnAcceptor::new(self.server_config(), challenges)
+    }
+
+    fn get(&self, name: &str) -> Option<Arc<CertifiedKey>> {
+        let name = name.to_ascii_lowercase();
+        let certs = self.0.read();
+        if let Some(key) = certs.get(&name) {
+            return Some(key.clone());
+        }
+
+        // a wildcard only covers a single label
+        let (_, parent) = name.split_once('.')?;
+        certs.get(&format!("*.{}", parent)).cloned()
+    }
+}
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        client_hello.server_name().and_then(|name| self.get(name))
+    }
+}
+
+impl Debug for SniResolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SniResolver")
+            .field("names", &self.0.read().keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn names(chain: &[u8]) -> Option<Vec<String>> {
+    leaf_certificate(chain, |leaf| {
+        let names = leaf
+            .extensions()
+            .iter()
+            .filter_map(|extension| match extension.parsed_extension() {
+                ParsedExtension::SubjectAlternativeName(san) => Some(&san.general_names),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(name) => Some(name.to_ascii_lowercase()),
+                _ => None,
+            })
+            .collect();
+
+        Some(names)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(names: &[&str]) -> CertificateBundle {
+        let names = names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        let cert = rcgen::generate_simple_self_signed(names).unwrap();
+
+        CertificateBundle {
+            chain: cert.serialize_pem().unwrap().into_bytes(),
+            private_key: cert.serialize_private_key_der(),
+        }
+    }
+
+    #[test]
+    fn wildcard_matching() {
+        let resolver = SniResolver::new();
+        assert!(resolver.insert(&bundle(&["example.com"])));
+        assert!(resolver.insert(&bundle(&["*.example.com", "api.example.org"])));
+
+        let apex = resolver.get("Example.com").unwrap();
+        let www = resolver.get("www.example.com").unwrap();
+        assert!(!Arc::ptr_eq(&apex, &www));
+        assert!(Arc::ptr_eq(&www, &resolver.get("api.example.org").unwrap()));
+        assert!(resolver.get("a.b.example.com").is_none());
+
+        resolver.remove("*.example.com");
+        assert!(resolver.get("www.example.com").is_none());
+
+        assert!(!resolver.insert(&CertificateBundle {
+            chain: b"not a certificate".to_vec(),
+            private_key: Vec::new(),
+        }));
+    }
+}
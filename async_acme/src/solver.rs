@@ -0,0 +1,245 @@
+use acme_core::ApiChallengeType;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use ring::digest::{digest, SHA256};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
+
+use crate::{BoxError, ChallengeTokenStore};
+
+const CHALLENGE_DIR: &str = ".well-known/acme-challenge";
+
+// everything needed to publish a challenge
+#[derive(Debug, Clone)]
+pub struct ChallengeProof {
+    pub domain: String,
+    pub token: String,
+    pub key_authorization: String,
+}
+
+impl ChallengeProof {
+    // the wildcard label is not part of the record name
+    pub fn dns_name(&self) -> String {
+        let domain = self.domain.strip_prefix("*.").unwrap_or(&self.domain);
+        format!("_acme-challenge.{}", domain)
+    }
+
+    pub fn dns_value(&self) -> String {
+        let digest = digest(&SHA256, self.key_authorization.as_bytes());
+        base64::encode_config(digest, base64::URL_SAFE_NO_PAD)
+    }
+}
+
+// publishes challenges so the CA can validate them, cleanup runs on success and failure
+#[async_trait]
+pub trait Solver: Debug + Send + Sync {
+    fn challenge_type(&self) -> ApiChallengeType;
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), BoxError>;
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), BoxError>;
+}
+
+#[async_trait]
+impl Solver for ChallengeTokenStore {
+    fn challenge_type(&self) -> ApiChallengeType {
+        ApiChallengeType::HTTP
+    }
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        self.insert(proof.token.clone(), proof.key_authorization.clone());
+        Ok(())
+    }
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        self.remove(&proof.token);
+        Ok(())
+    }
+}
+
+// solvers tried in order, the first one the CA offers a challenge for is used and the next one
+// takes over if presenting or validating fails
+#[derive(Debug, Clone, Default)]
+pub struct SolverChain {
+    solvers: Vec<Arc<dyn Solver>>,
+    domains: HashMap<String, Vec<Arc<dyn Solver>>>,
+}
+
+impl SolverChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // used for domains without their own solvers
+    pub fn solver<S: Solver + 'static>(mut self, solver: S) -> Self {
+        self.solvers.push(Arc::new(solver));
+        self
+    }
+
+    // wildcards have to be configured as *.example.com
+    pub fn domain_solver<T: Into<String>, S: Solver + 'static>(
+        mut self,
+        domain: T,
+        solver: S,
+    ) -> Self {
+        self.domains
+            .entry(domain.into())
+            .or_default()
+            .push(Arc::new(solver));
+        self
+    }
+
+    pub(crate) fn solvers(&self, domain: &str) -> &[Arc<dyn Solver>] {
+        self.domains.get(domain).unwrap_or(&self.solvers)
+    }
+}
+
+// txt values presented per record name, a wildcard and its base domain share one record set
+#[derive(Debug, Default)]
+pub(crate) struct TxtRecords(Mutex<HashMap<String, Vec<String>>>);
+
+impl TxtRecords {
+    // returns all values of the record set
+    pub(crate) fn add(&self, name: &str, value: String) -> Vec<String> {
+        let mut records = self.0.lock();
+        let values = records.entry(name.to_owned()).or_default();
+        values.push(value);
+        values.clone()
+    }
+
+    // returns the remaining values, the record set can be deleted if empty
+    pub(crate) fn remove(&self, name: &str, value: &str) -> Vec<String> {
+        let mut records = self.0.lock();
+        let values = match records.get_mut(name) {
+            Some(values) => values,
+            None => return Vec::new(),
+        };
+        values.retain(|v| v != value);
+        let values = values.clone();
+        if values.is_empty() {
+            records.remove(name);
+        }
+        values
+    }
+}
+
+// writes the key authorization below the document root of an existing webserver
+#[derive(Debug, Clone)]
+pub struct WebrootSolver {
+    root: PathBuf,
+}
+
+impl WebrootSolver {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_owned(),
+        }
+    }
+
+    fn path(&self, token: &str) -> Result<PathBuf, io::Error> {
+        // tokens are base64url, anything else could escape the webroot
+        let valid = !token.is_empty()
+            && token
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        match valid {
+            true => Ok(self.root.join(CHALLENGE_DIR).join(token)),
+            false => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid token {}", token),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl Solver for WebrootSolver {
+    fn challenge_type(&self) -> ApiChallengeType {
+        ApiChallengeType::HTTP
+    }
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        let path = self.path(&proof.token)?;
+        fs::create_dir_all(self.root.join(CHALLENGE_DIR)).await?;
+        fs::write(path, &proof.key_authorization).await?;
+
+        Ok(())
+    }
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), BoxError> {
+        match fs::remove_file(self.path(&proof.token)?).await {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proof(domain: &str, token: &str) -> ChallengeProof {
+        ChallengeProof {
+            domain: domain.into(),
+            token: token.into(),
+            key_authorization: format!("{}.thumbprint", token),
+        }
+    }
+
+    #[test]
+    fn dns_record() {
+        let proof = proof("*.example.com", "token");
+        assert_eq!(proof.dns_name(), "_acme-challenge.example.com");
+        // sha256 of token.thumbprint
+        assert_eq!(proof.dns_value().len(), 43);
+    }
+
+    #[test]
+    fn chain_per_domain() {
+        let chain = SolverChain::new()
+            .solver(ChallengeTokenStore::new())
+            .domain_solver("*.example.com", WebrootSolver::new("/var/www"))
+            .domain_solver("*.example.com", ChallengeTokenStore::new());
+
+        assert_eq!(chain.solvers("example.com").len(), 1);
+        assert_eq!(chain.solvers("*.example.com").len(), 2);
+    }
+
+    #[test]
+    fn txt_records() {
+        let records = TxtRecords::default();
+        assert_eq!(records.add("name", "a".into()), vec!["a"]);
+        assert_eq!(records.add("name", "b".into()), vec!["a", "b"]);
+        assert_eq!(records.remove("name", "a"), vec!["b"]);
+        assert!(records.remove("name", "b").is_empty());
+        assert!(records.remove("name", "b").is_empty());
+    }
+
+    #[tokio::test]
+    async fn webroot() -> Result<(), BoxError> {
+        let root = std::env::temp_dir().join(format!("async_acme_webroot_{}", std::process::id()));
+        let solver = WebrootSolver::new(&root);
+        let proof = proof("example.com", "token");
+
+        solver.present(&proof).await?;
+        let path = root.join(".well-known/acme-challenge/token");
+        assert_eq!(fs::read_to_string(&path).await?, "token.thumbprint");
+
+        solver.cleanup(&proof).await?;
+        assert!(!path.exists());
+        // cleanup after a failed present must not fail
+        solver.cleanup(&proof).await?;
+
+        assert!(solver
+            .present(&self::proof("example.com", "../token"))
+            .await
+            .is_err());
+        fs::remove_dir_all(root).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use hyper::service::make_service_fn;
+use hyper::Server;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::{ChallengeTokenStore, Http01Service};
+
+// like certbot --standalone, answers http-01 validations until it is shut down or dropped
+#[derive(Debug)]
+pub struct StandaloneHttp01 {
+    local_addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<Result<(), hyper::Error>>,
+}
+
+impl StandaloneHttp01 {
+    // has to be called from within a tokio runtime
+    pub fn bind(addr: SocketAddr, tokens: ChallengeTokenStore) -> Result<Self, hyper::Error> {
+        let make_service = make_service_fn(move |_| {
+            let service = Http01Service::new(tokens.clone());
+            async move { Ok::<_, Infallible>(service) }
+        });
+        let server = Server::try_bind(&addr)?.serve(make_service);
+        let local_addr = server.local_addr();
+
+        let (shutdown, rx) = oneshot::channel();
+        let handle = tokio::spawn(server.with_graceful_shutdown(async {
+            let _ = rx.await;
+        }));
+
+        Ok(Self {
+            local_addr,
+            shutdown: Some(shutdown),
+            handle,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    // waits for open connections to finish
+    pub async fn shutdown(mut self) -> Result<(), hyper::Error> {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+
+        match (&mut self.handle).await {
+            Ok(res) => res,
+            // the server task only fails to join if the runtime shuts down
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl Drop for StandaloneHttp01 {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{body, Client};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_until_shutdown() -> Result<(), Box<dyn std::error::Error>> {
+        let tokens = ChallengeTokenStore::new();
+        tokens.insert("token", "token.thumbprint");
+
+        let responder = StandaloneHttp01::bind(([127, 0, 0, 1], 0).into(), tokens)?;
+        let uri = format!(
+            "http://{}/.well-known/acme-challenge/token",
+            responder.local_addr()
+        );
+
+        let res = Client::new().get(uri.parse()?).await?;
+        assert_eq!(body::to_bytes(res.into_body()).await?, "token.thumbprint");
+
+        let addr = responder.local_addr();
+        responder.shutdown().await?;
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+
+        Ok(())
+    }
+}
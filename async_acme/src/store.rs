@@ -0,0 +1,375 @@
+#[cfg(feature = "rustls-tls")]
+use rustls::sign::{any_supported_type, CertifiedKey};
+#[cfg(feature = "rustls-tls")]
+use rustls::PrivateKey;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+
+use crate::chain::leaf_certificate;
+use crate::{BoxError, CertificateBundle, DataType, Persist};
+
+const INDEX_KEY: &str = "index";
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("{0}")]
+    Persist(BoxError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("Stored certificate for {0} can't be parsed")]
+    InvalidCertificate(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Issuance {
+    // unix timestamps
+    pub issued_at: i64,
+    pub not_after: i64,
+    pub serial: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpiringCertificate {
+    pub domains: Vec<String>,
+    // unix timestamp
+    pub not_after: i64,
+}
+
+// latest certificate and issuance history per domain or set of domains
+#[derive(Debug, Clone)]
+pub struct CertificateStore<P> {
+    persist: P,
+    // serializes read modify write of the index and the histories within this process
+    index: Arc<Mutex<()>>,
+}
+
+impl<P: Persist + Send + Sync> CertificateStore<P> {
+    pub fn new(persist: P) -> Self {
+        Self {
+            persist,
+            index: Default::default(),
+        }
+    }
+
+    pub async fn get<T: AsRef<str>>(
+        &self,
+        domains: &[T],
+    ) -> Result<Option<CertificateBundle>, StoreError> {
+        let key = key(domains);
+        let chain = self.get_data(DataType::Certificate, &key).await?;
+        let private_key = self.get_data(DataType::CertificateKey, &key).await?;
+
+        match (chain, private_key) {
+            (Some(chain), Some(private_key)) => Ok(Some(CertificateBundle { chain, private_key })),
+            _ => Ok(None),
+        }
+    }
+
+    pub async fn put<T: AsRef<str>>(
+        &self,
+        domains: &[T],
+        bundle: CertificateBundle,
+    ) -> Result<(), StoreError> {
+        let key = key(domains);
+        let issuance =
+            issuance(&bundle.chain).ok_or_else(|| StoreError::InvalidCertificate(key.clone()))?;
+
+        // held until the index is written, concurrent puts would drop issuances otherwise
+        let _lock = self.index.lock().await;
+        let mut history = self.history(domains).await?;
+        history.push(issuance);
+
+        // the key is written first, a chain without key is treated as missing
+        self.put_data(DataType::CertificateKey, &key, bundle.private_key)
+            .await?;
+        self.put_data(DataType::Certificate, &key, bundle.chain)
+            .await?;
+        self.put_data(
+            DataType::CertificateHistory,
+            &key,
+            serde_json::to_vec(&history)?,
+        )
+        .await?;
+
+        let mut index = self.keys().await?;
+        if !index.contains(&key) {
+            index.push(key);
+            self.put_data(
+                DataType::CertificateIndex,
+                INDEX_KEY,
+                serde_json::to_vec(&index)?,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    // certificates expiring within window, the ones expiring first come first
+    pub async fn scan_expiring(
+        &self,
+        window: Duration,
+    ) -> Result<Vec<ExpiringCertificate>, StoreError> {
+        let deadline = OffsetDateTime::now_utc().unix_timestamp() + window.as_secs() as i64;
+
+        let mut expiring = Vec::new();
+        for key in self.keys().await? {
+            let chain = match self.get_data(DataType::Certificate, &key).await? {
+                Some(chain) => chain,
+                None => continue,
+            };
+            let not_after = issuance(&chain)
+                .ok_or_else(|| StoreError::InvalidCertificate(key.clone()))?
+                .not_after;
+
+            if not_after <= deadline {
+                expiring.push(ExpiringCertificate {
+                    domains: key.split(',').map(ToOwned::to_owned).collect(),
+                    not_after,
+                });
+            }
+        }
+
+        expiring.sort_by_key(|certificate| certificate.not_after);
+        Ok(expiring)
+    }
+
+    async fn keys(&self) -> Result<Vec<String>, StoreError> {
+        match self.get_data(DataType::CertificateIndex, INDEX_KEY).await? {
+            Some(index) => Ok(serde_json::from_slice(&index)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // oldest issuance first
+    pub async fn history<T: AsRef<str>>(&self, domains: &[T]) -> Result<Vec<Issuance>, StoreError> {
+        self.history_of(&key(domains)).await
+    }
+
+    // issuances of every SAN set containing domain, ordered per SAN set
+    pub async fn domain_history(&self, domain: &str) -> Result<Vec<Issuance>, StoreError> {
+        let domain = key(&[domain]);
+        let mut history = Vec::new();
+        for key in self.keys().await? {
+            if key.split(',').any(|name| name == domain) {
+                history.extend(self.history_of(&key).await?);
+            }
+        }
+
+        Ok(history)
+    }
+
+    async fn history_of(&self, key: &str) -> Result<Vec<Issuance>, StoreError> {
+        match self.get_data(DataType::CertificateHistory, key).await? {
+            Some(history) => Ok(serde_json::from_slice(&history)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // ready to be returned from a rustls ResolvesServerCert
+    #[cfg(feature = "rustls-tls")]
+    pub async fn certified_key<T: AsRef<str>>(
+        &self,
+        domains: &[T],
+    ) -> Result<Option<CertifiedKey>, StoreError> {
+        match self.get(domains).await? {
+            Some(bundle) => bundle
+                .certified_key()
+                .map(Some)
+                .ok_or_else(|| StoreError::InvalidCertificate(key(domains))),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_data(
+        &self,
+        data_type: DataType,
+        key: &str,
+    ) -> Result<Option<Vec<u8>>, StoreError> {
+        self.persist
+            .get(data_type, key)
+            .await
+            .map_err(|err| StoreError::Persist(err.into()))
+    }
+
+    async fn put_data(
+        &self,
+        data_type: DataType,
+        key: &str,
+        value: Vec<u8>,
+    ) -> Result<(), StoreError> {
+        self.persist
+            .put(data_type, key, value)
+            .await
+            .map_err(|err| StoreError::Persist(err.into()))
+    }
+}
+
+// the order of the domains doesn't matter, a single domain is its own key
+fn key<T: AsRef<str>>(domains: &[T]) -> String {
+    let mut domains: Vec<String> = domains
+        .iter()
+        .map(|domain| domain.as_ref().to_ascii_lowercase())
+        .collect();
+    domains.sort();
+    domains.dedup();
+
+    domains.join(",")
+}
+
+impl CertificateBundle {
+    // None if the chain or the key can't be parsed
+    #[cfg(feature = "rustls-tls")]
+    pub fn certified_key(&self) -> Option<CertifiedKey> {
+        let chain = rustls_pemfile::certs(&mut &*self.chain).ok()?;
+        let key = any_supported_type(&PrivateKey(self.private_key.clone())).ok()?;
+        let chain = chain.into_iter().map(rustls::Certificate).collect();
+
+        Some(CertifiedKey::new(chain, key))
+    }
+}
+
+// None if the chain doesn't start with a parsable certificate
+fn issuance(chain: &[u8]) -> Option<Issuance> {
+    leaf_certificate(chain, |leaf| {
+        Some(Issuance {
+            issued_at: OffsetDateTime::now_utc().unix_timestamp(),
+            not_after: leaf.validity().not_after.timestamp(),
+            serial: leaf.raw_serial_as_string(),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryPersist;
+
+    fn bundle() -> CertificateBundle {
+        bundle_expiring(OffsetDateTime::now_utc() + time::Duration::days(90))
+    }
+
+    fn bundle_expiring(not_after: OffsetDateTime) -> CertificateBundle {
+        let mut params = rcgen::CertificateParams::new(vec!["example.com".to_string()]);
+        params.not_after = not_after;
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+
+        CertificateBundle {
+            chain: cert.serialize_pem().unwrap().into_bytes(),
+            private_key: cert.serialize_private_key_der(),
+        }
+    }
+
+    #[tokio::test]
+    async fn stores_latest_and_history() -> Result<(), StoreError> {
+        let store = CertificateStore::new(MemoryPersist::new());
+        assert!(store.get(&["example.com"]).await?.is_none());
+
+        store
+            .put(&["www.example.com", "example.com"], bundle())
+            .await?;
+        let latest = bundle();
+        store
+            .put(&["example.com", "WWW.example.com"], latest.clone())
+            .await?;
+
+        let stored = store
+            .get(&["example.com", "www.example.com"])
+            .await?
+            .unwrap();
+        assert_eq!(stored.chain, latest.chain);
+        assert_eq!(
+            store
+                .history(&["example.com", "www.example.com"])
+                .await?
+                .len(),
+            2
+        );
+        #[cfg(feature = "rustls-tls")]
+        assert!(store
+            .certified_key(&["example.com", "www.example.com"])
+            .await?
+            .is_some());
+
+        assert!(store
+            .put(
+                &["example.com"],
+                CertificateBundle {
+                    chain: b"not a certificate".to_vec(),
+                    private_key: Vec::new(),
+                }
+            )
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn domain_history_spans_san_sets() -> Result<(), StoreError> {
+        let store = CertificateStore::new(MemoryPersist::new());
+        store.put(&["example.com"], bundle()).await?;
+        store
+            .put(&["example.com", "www.example.com"], bundle())
+            .await?;
+        store.put(&["example.org"], bundle()).await?;
+
+        assert_eq!(store.domain_history("Example.com").await?.len(), 2);
+        assert_eq!(store.domain_history("www.example.com").await?.len(), 1);
+        assert!(store.domain_history("example").await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrent_puts_keep_history() -> Result<(), StoreError> {
+        let store = CertificateStore::new(MemoryPersist::new());
+        let (first, second) = tokio::join!(
+            store.put(&["example.com"], bundle()),
+            store.put(&["example.com"], bundle())
+        );
+        first?;
+        second?;
+
+        assert_eq!(store.history(&["example.com"]).await?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scans_expiring() -> Result<(), StoreError> {
+        let store = CertificateStore::new(MemoryPersist::new());
+        let now = OffsetDateTime::now_utc();
+
+        store.put(&["fresh.com"], bundle()).await?;
+        store
+            .put(
+                &["b.com", "a.com"],
+                bundle_expiring(now + time::Duration::days(5)),
+            )
+            .await?;
+        store
+            .put(
+                &["soon.com"],
+                bundle_expiring(now + time::Duration::days(2)),
+            )
+            .await?;
+
+        let expiring = store
+            .scan_expiring(Duration::from_secs(7 * 24 * 60 * 60))
+            .await?;
+        let domains: Vec<_> = expiring.into_iter().map(|c| c.domains).collect();
+        assert_eq!(
+            domains,
+            vec![
+                vec!["soon.com".to_string()],
+                vec!["a.com".into(), "b.com".into()]
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use futures_util::stream;
+use hyper::body::{BytesMut, HttpBody};
+use hyper::Body;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::runtime::timeout;
+use crate::{ByteStream, Elapsed, HyperAcmeServerError, Runtime};
+
+const PEM_END: &[u8] = b"-----END ";
+const PEM_DASHES: &[u8] = b"-----";
+
+// yields the pem blocks of a certificate chain while the body is still being received
+#[derive(Debug)]
+pub struct CertificateStream {
+    body: Body,
+    buf: BytesMut,
+    read: usize,
+    limit: usize,
+    read_timeout: Duration,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl CertificateStream {
+    pub(crate) fn new(
+        body: Body,
+        limit: usize,
+        read_timeout: Duration,
+        runtime: Arc<dyn Runtime>,
+    ) -> Self {
+        Self {
+            body,
+            buf: BytesMut::new(),
+            read: 0,
+            limit,
+            read_timeout,
+            runtime,
+        }
+    }
+
+    // every block ends with a single newline, returns None once the chain is complete
+    pub async fn next_pem(&mut self) -> Option<Result<Vec<u8>, HyperAcmeServerError>> {
+        loop {
+            if let Some(pem) = split_pem(&mut self.buf) {
+                return Some(Ok(pem));
+            }
+
+            let chunk = match timeout(&*self.runtime, self.read_timeout, self.body.data()).await {
+                Ok(Some(Ok(chunk))) => chunk,
+                Ok(Some(Err(err))) => return Some(Err(err.into())),
+                Ok(None) => break,
+                Err(_) => return Some(Err(Elapsed.into())),
+            };
+
+            self.read += chunk.len();
+            if self.read > self.limit {
+                return Some(Err(HyperAcmeServerError::BodyTooLarge(self.limit)));
+            }
+            self.buf.extend_from_slice(&chunk);
+        }
+
+        // anything left over is a truncated or malformed block
+        match trim(&self.buf).is_empty() {
+            true => None,
+            false => {
+                self.buf.clear();
+                Some(Err(HyperAcmeServerError::InvalidCertificateChain))
+            }
+        }
+    }
+
+    pub async fn collect(mut self) -> Result<Vec<u8>, HyperAcmeServerError> {
+        let mut chain = Vec::new();
+        while let Some(pem) = self.next_pem().await {
+            chain.extend(pem?);
+        }
+
+        Ok(chain)
+    }
+
+    // one pem block per item
+    pub(crate) fn into_byte_stream(self) -> ByteStream<HyperAcmeServerError> {
+        let stream = stream::unfold(self, |mut stream| async move {
+            let pem = stream.next_pem().await?;
+            Some((pem, stream))
+        });
+        Box::pin(stream)
+    }
+}
+
+fn split_pem(buf: &mut BytesMut) -> Option<Vec<u8>> {
+    let end = find(&buf[..], PEM_END)? + PEM_END.len();
+    let end = end + find(&buf[end..], PEM_DASHES)? + PEM_DASHES.len();
+
+    let block = buf.split_to(end);
+    let mut pem = trim(&block).to_vec();
+    pem.push(b'\n');
+
+    Some(pem)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn trim(buf: &[u8]) -> &[u8] {
+    let start = match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+        Some(start) => start,
+        None => return &[],
+    };
+    let end = buf
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .unwrap_or(start);
+
+    &buf[start..=end]
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::body::Bytes;
+
+    use super::*;
+    use crate::TokioRuntime;
+
+    const CHAIN: &str = "-----BEGIN CERTIFICATE-----\r\nAAAA\r\n-----END CERTIFICATE-----\r\n\
+        \r\n-----BEGIN CERTIFICATE-----\r\nBBBB\r\n-----END CERTIFICATE-----\r\n";
+
+    fn stream(chunk_size: usize, limit: usize) -> CertificateStream {
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            for chunk in CHAIN.as_bytes().chunks(chunk_size) {
+                if sender
+                    .send_data(Bytes::copy_from_slice(chunk))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+
+        CertificateStream::new(body, limit, Duration::from_secs(5), Arc::new(TokioRuntime))
+    }
+
+    #[tokio::test]
+    async fn yields_pem_blocks() {
+        let mut stream = stream(7, 1024);
+
+        let first = stream.next_pem().await.unwrap().unwrap();
+        assert_eq!(
+            first,
+            b"-----BEGIN CERTIFICATE-----\r\nAAAA\r\n-----END CERTIFICATE-----\n"
+        );
+        let second = stream.next_pem().await.unwrap().unwrap();
+        assert!(second.starts_with(b"-----BEGIN CERTIFICATE-----\r\nBBBB"));
+        assert!(stream.next_pem().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn enforces_limit() {
+        let res = stream(7, 64).collect().await;
+        assert!(matches!(res, Err(HyperAcmeServerError::BodyTooLarge(64))));
+    }
+
+    #[tokio::test]
+    async fn rejects_truncated_chain() {
+        let body = Body::from("-----BEGIN CERTIFICATE-----\nAAAA\n");
+        let mut stream =
+            CertificateStream::new(body, 1024, Duration::from_secs(5), Arc::new(TokioRuntime));
+        assert!(matches!(
+            stream.next_pem().await,
+            Some(Err(HyperAcmeServerError::InvalidCertificateChain))
+        ));
+    }
+}
@@ -0,0 +1,201 @@
+#[cfg(feature = "rustls-tls")]
+use rustls::RootCertStore;
+
+// the only protocols the hyper client speaks
+#[cfg(feature = "rustls-tls")]
+const ALPN_HTTP1: &[u8] = b"http/1.1";
+#[cfg(feature = "rustls-tls")]
+const ALPN_H2: &[u8] = b"h2";
+
+#[derive(Clone, Default)]
+pub(crate) struct TlsConfig {
+    // der encoded, added on top of the platform or webpki roots
+    roots: Vec<Vec<u8>>,
+    #[cfg(feature = "rustls-tls")]
+    pub(crate) http2: bool,
+    #[cfg(feature = "rustls-tls")]
+    alpn_protocols: Vec<Vec<u8>>,
+    #[cfg(feature = "rustls-tls")]
+    key_log: bool,
+    #[cfg(feature = "danger-accept-invalid-certs")]
+    accept_invalid_certs: bool,
+}
+
+impl TlsConfig {
+    // accepts pem or der, returns false if the certificates can't be parsed
+    pub(crate) fn add_root_certificate(&mut self, cert: &[u8]) -> bool {
+        let certs = match rustls_pemfile::certs(&mut &*cert) {
+            Ok(certs) if !certs.is_empty() => certs,
+            _ => vec![cert.to_vec()],
+        };
+
+        if !parsable(&certs) {
+            return false;
+        }
+
+        self.roots.extend(certs);
+        true
+    }
+
+    // hyper-rustls panics if the client config already has protocols, so they are only
+    // recorded here and enabled on the connector builder. returns false for anything but
+    // http/1.1 and h2
+    #[cfg(feature = "rustls-tls")]
+    pub(crate) fn alpn_protocols(&mut self, alpn_protocols: Vec<Vec<u8>>) -> bool {
+        let supported = |protocol: &Vec<u8>| protocol == ALPN_HTTP1 || protocol == ALPN_H2;
+        if !alpn_protocols.iter().all(supported) {
+            return false;
+        }
+
+        self.alpn_protocols = alpn_protocols;
+        true
+    }
+
+    // http1 is offered unless the protocols were restricted to h2
+    #[cfg(feature = "rustls-tls")]
+    pub(crate) fn alpn_http1(&self) -> bool {
+        self.alpn_protocols.is_empty() || self.alpn_protocols.iter().any(|p| p == ALPN_HTTP1)
+    }
+
+    #[cfg(feature = "rustls-tls")]
+    pub(crate) fn alpn_h2(&self) -> bool {
+        self.http2 || self.alpn_protocols.iter().any(|p| p == ALPN_H2)
+    }
+
+    #[cfg(feature = "rustls-tls")]
+    pub(crate) fn key_log(&mut self) {
+        self.key_log = true;
+    }
+
+    #[cfg(feature = "danger-accept-invalid-certs")]
+    pub(crate) fn danger_accept_invalid_certs(&mut self) {
+        self.accept_invalid_certs = true;
+    }
+
+    #[cfg(feature = "rustls-tls")]
+    pub(crate) fn client_config(&self) -> rustls::ClientConfig {
+        let mut roots = RootCertStore::empty();
+        roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|anchor| {
+            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject,
+                anchor.spki,
+                anchor.name_constraints,
+            )
+        }));
+        roots.add_parsable_certificates(&self.roots);
+
+        let mut config = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        if self.key_log {
+            // does nothing if SSLKEYLOGFILE is not set
+            config.key_log = std::sync::Arc::new(rustls::KeyLogFile::new());
+        }
+
+        #[cfg(feature = "danger-accept-invalid-certs")]
+        if self.accept_invalid_certs {
+            config
+                .dangerous()
+                .set_certificate_verifier(std::sync::Arc::new(danger::NoCertificateVerification));
+        }
+
+        config
+    }
+
+    // alpn is not supported, native-tls needs its alpn feature for this
+    #[cfg(feature = "native-tls")]
+    pub(crate) fn native_connector(&self) -> Result<native_tls::TlsConnector, native_tls::Error> {
+        let mut builder = native_tls::TlsConnector::builder();
+        for root in &self.roots {
+            builder.add_root_certificate(native_tls::Certificate::from_der(root)?);
+        }
+
+        #[cfg(feature = "danger-accept-invalid-certs")]
+        builder.danger_accept_invalid_certs(self.accept_invalid_certs);
+
+        builder.build()
+    }
+}
+
+#[cfg(feature = "rustls-tls")]
+fn parsable(certs: &[Vec<u8>]) -> bool {
+    let (_, ignored) = RootCertStore::empty().add_parsable_certificates(certs);
+    ignored == 0
+}
+
+#[cfg(not(feature = "rustls-tls"))]
+fn parsable(certs: &[Vec<u8>]) -> bool {
+    certs
+        .iter()
+        .all(|cert| native_tls::Certificate::from_der(cert).is_ok())
+}
+
+#[cfg(all(feature = "rustls-tls", feature = "danger-accept-invalid-certs"))]
+mod danger {
+    use rustls::client::{ServerCertVerified, ServerCertVerifier};
+    use rustls::{Certificate, Error, ServerName};
+    use std::time::SystemTime;
+
+    // only meant for pebble or other throwaway test CAs
+    pub(super) struct NoCertificateVerification;
+
+    impl ServerCertVerifier for NoCertificateVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &Certificate,
+            _intermediates: &[Certificate],
+            _server_name: &ServerName,
+            _scts: &mut dyn Iterator<Item = &[u8]>,
+            _ocsp_response: &[u8],
+            _now: SystemTime,
+        ) -> Result<ServerCertVerified, Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn certificate() -> Vec<u8> {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        cert.serialize_der().unwrap()
+    }
+
+    #[test]
+    fn add_der_and_pem_root_certificate() {
+        let der = certificate();
+        let mut pem = "-----BEGIN CERTIFICATE-----\n".to_string();
+        pem.push_str(&base64::encode(&der));
+        pem.push_str("\n-----END CERTIFICATE-----\n");
+
+        let mut tls = TlsConfig::default();
+        assert!(tls.add_root_certificate(&der));
+        assert!(tls.add_root_certificate(pem.as_bytes()));
+        assert_eq!(tls.roots, vec![der.clone(), der]);
+    }
+
+    #[cfg(feature = "rustls-tls")]
+    #[test]
+    fn alpn_protocols() {
+        let mut tls = TlsConfig::default();
+        assert!(tls.alpn_http1() && !tls.alpn_h2());
+
+        assert!(tls.alpn_protocols(vec![b"h2".to_vec()]));
+        assert!(!tls.alpn_http1() && tls.alpn_h2());
+        // hyper-rustls sets the protocols itself
+        assert!(tls.client_config().alpn_protocols.is_empty());
+
+        assert!(!tls.alpn_protocols(vec![b"acme-tls/1".to_vec()]));
+        assert!(!tls.alpn_http1() && tls.alpn_h2());
+    }
+
+    #[test]
+    fn reject_invalid_root_certificate() {
+        let mut tls = TlsConfig::default();
+        assert!(!tls.add_root_certificate(b"not a certificate"));
+        assert!(tls.roots.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use parking_lot::Mutex;
+use rcgen::{CertificateParams, CustomExtension, RcgenError};
+use ring::digest::{digest, SHA256};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::{any_supported_type, CertifiedKey, SignError};
+use rustls::{PrivateKey, ServerConfig};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::io;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+pub const ACME_TLS_ALPN: &[u8] = b"acme-tls/1";
+
+#[derive(Debug, Error)]
+pub enum TlsAlpnError {
+    #[error(transparent)]
+    Rcgen(#[from] RcgenError),
+    #[error(transparent)]
+    Sign(#[from] SignError),
+}
+
+// challenge certificates of the pending tls-alpn-01 validations by domain
+#[derive(Clone, Default)]
+pub struct TlsAlpnChallenges(Arc<Mutex<HashMap<String, Arc<CertifiedKey>>>>);
+
+impl TlsAlpnChallenges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // builds the self signed certificate with the acmeIdentifier extension of RFC 8737
+    pub fn insert<T: Into<String>>(
+        &self,
+        domain: T,
+        key_authorization: &str,
+    ) -> Result<(), TlsAlpnError> {
+        let domain = domain.into();
+        let digest = digest(&SHA256, key_authorization.as_bytes());
+
+        let mut params = CertificateParams::new(vec![domain.clone()]);
+        params.custom_extensions = vec![CustomExtension::new_acme_identifier(digest.as_ref())];
+        let cert = rcgen::Certificate::from_params(params)?;
+
+        let key = any_supported_type(&PrivateKey(cert.serialize_private_key_der()))?;
+        let cert = rustls::Certificate(cert.serialize_der()?);
+
+        self.0
+            .lock()
+            .insert(domain, Arc::new(CertifiedKey::new(vec![cert], key)));
+        Ok(())
+    }
+
+    pub fn remove(&self, domain: &str) {
+        self.0.lock().remove(domain);
+    }
+
+    fn get(&self, domain: &str) -> Option<Arc<CertifiedKey>> {
+        self.0.lock().get(domain).cloned()
+    }
+}
+
+impl Debug for TlsAlpnChallenges {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TlsAlpnChallenges").finish()
+    }
+}
+
+// answers acme-tls/1 handshakes with the challenge certificate, everything else goes to inner
+struct AlpnResolver {
+    challenges: TlsAlpnChallenges,
+    inner: Arc<dyn ResolvesServerCert>,
+}
+
+impl ResolvesServerCert for AlpnResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let validation = client_hello
+            .alpn()
+            .into_iter()
+            .flatten()
+            .any(|protocol| protocol == ACME_TLS_ALPN);
+
+        match validation {
+            true => client_hello
+                .server_name()
+                .and_then(|domain| self.challenges.get(domain)),
+            false => self.inner.resolve(client_hello),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TlsAlpnAcceptor {
+    acceptor: TlsAcceptor,
+}
+
+impl TlsAlpnAcceptor {
+    pub fn new(mut config: ServerConfig, challenges: TlsAlpnChallenges) -> Self {
+        config.cert_resolver = Arc::new(AlpnResolver {
+            challenges,
+            inner: config.cert_resolver.clone(),
+        });
+        config.alpn_protocols.push(ACME_TLS_ALPN.to_vec());
+
+        Self {
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+        }
+    }
+
+    // returns None for validation connections, the CA only needs the handshake
+    pub async fn accept<IO>(&self, stream: IO) -> io::Result<Option<TlsStream<IO>>>
+    where
+        IO: AsyncRead + AsyncWrite + Unpin,
+    {
+        let stream = self.acceptor.accept(stream).await?;
+        let validation = stream.get_ref().1.alpn_protocol() == Some(ACME_TLS_ALPN);
+
+        match validation {
+            true => Ok(None),
+            false => Ok(Some(stream)),
+        }
+    }
+}
+
+impl Debug for TlsAlpnAcceptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsAlpnAcceptor").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use x509_parser::parse_x509_certificate;
+
+    use super::*;
+
+    const ACME_IDENTIFIER_OID: &str = "1.3.6.1.5.5.7.1.31";
+
+    #[test]
+    fn challenge_certificate() {
+        let challenges = TlsAlpnChallenges::new();
+        challenges
+            .insert("example.com", "token.thumbprint")
+            .unwrap();
+
+        let certified = challenges.get("example.com").unwrap();
+        let (_, cert) = parse_x509_certificate(&certified.cert[0].0).unwrap();
+        let extension = cert
+            .extensions()
+            .iter()
+            .find(|extension| extension.oid.to_id_string() == ACME_IDENTIFIER_OID)
+            .unwrap();
+        assert!(extension.critical);
+        // der octet string header followed by the sha256 digest
+        assert_eq!(
+            &extension.value[2..],
+            digest(&SHA256, b"token.thumbprint").as_ref()
+        );
+
+        challenges.remove("example.com");
+        assert!(challenges.get("example.com").is_none());
+    }
+}
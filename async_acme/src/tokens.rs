@@ -0,0 +1,68 @@
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub(crate) const CHALLENGE_PATH: &str = "/.well-known/acme-challenge/";
+
+// key authorizations of the pending http-01 challenges by token, clones share the tokens so a
+// clone can be moved into the handlers of any web framework
+#[derive(Debug, Clone, Default)]
+pub struct ChallengeTokenStore(Arc<RwLock<HashMap<String, String>>>);
+
+impl ChallengeTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, token: &str) -> Option<String> {
+        self.0.read().get(token).cloned()
+    }
+
+    // takes the full request path, None for paths outside of /.well-known/acme-challenge/
+    pub fn lookup(&self, path: &str) -> Option<String> {
+        path.strip_prefix(CHALLENGE_PATH)
+            .and_then(|token| self.get(token))
+    }
+
+    pub fn insert<T: Into<String>, K: Into<String>>(&self, token: T, key_authorization: K) {
+        self.0
+            .write()
+            .insert(token.into(), key_authorization.into());
+    }
+
+    pub fn remove(&self, token: &str) -> Option<String> {
+        self.0.write().remove(token)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.read().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_by_path() {
+        let tokens = ChallengeTokenStore::new();
+        tokens.insert("token", "token.thumbprint");
+
+        let handler = tokens.clone();
+        assert_eq!(
+            handler
+                .lookup("/.well-known/acme-challenge/token")
+                .as_deref(),
+            Some("token.thumbprint")
+        );
+        assert_eq!(handler.lookup("/token"), None);
+        assert_eq!(handler.lookup("/.well-known/acme-challenge/other"), None);
+
+        assert_eq!(tokens.remove("token").as_deref(), Some("token.thumbprint"));
+        assert!(handler.is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use hyper::client::connect::{Connected, Connection};
+use hyper::service::Service;
+use hyper::Uri;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::UnixStream;
+
+use crate::{HyperAcmeServerBuilder, HyperAcmeServerError};
+
+const UNIX_SCHEME: &str = "unix://";
+const DEFAULT_PATH: &str = "/directory";
+
+// connects every request to the same socket, the host of the uri is ignored
+#[derive(Debug, Clone)]
+pub struct UnixConnector {
+    socket: Arc<PathBuf>,
+}
+
+impl UnixConnector {
+    pub fn new<P: AsRef<Path>>(socket: P) -> Self {
+        Self {
+            socket: Arc::new(socket.as_ref().to_owned()),
+        }
+    }
+}
+
+impl Service<Uri> for UnixConnector {
+    type Response = UnixConnection;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: Uri) -> Self::Future {
+        let socket = self.socket.clone();
+        Box::pin(async move { Ok(UnixConnection(UnixStream::connect(&*socket).await?)) })
+    }
+}
+
+#[derive(Debug)]
+pub struct UnixConnection(UnixStream);
+
+impl Connection for UnixConnection {
+    fn connected(&self) -> Connected {
+        Connected::new()
+    }
+}
+
+impl AsyncRead for UnixConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for UnixConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+// unix:///run/pebble.sock:/dir, the socket path and the directory path are split at the last colon
+fn parse_unix_url(url: &str) -> Option<(&str, &str)> {
+    let url = url.strip_prefix(UNIX_SCHEME)?;
+    let (socket, path) = match url.rsplit_once(':') {
+        Some((socket, path)) if path.starts_with('/') => (socket, path),
+        _ => (url, DEFAULT_PATH),
+    };
+
+    match socket.is_empty() {
+        true => None,
+        false => Some((socket, path)),
+    }
+}
+
+impl HyperAcmeServerBuilder<UnixConnector> {
+    pub fn unix(&mut self, url: &str) -> Result<&mut Self, HyperAcmeServerError> {
+        let (socket, path) =
+            parse_unix_url(url).ok_or_else(|| HyperAcmeServerError::InvalidUnixUrl(url.into()))?;
+
+        // the authority is only needed by hyper, the connector ignores it
+        self.connector(UnixConnector::new(socket))
+            .url(format!("http://localhost{}", path));
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{body, Body, Client};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixListener;
+
+    use super::*;
+
+    #[test]
+    fn unix_url() {
+        assert_eq!(
+            parse_unix_url("unix:///run/pebble.sock:/dir"),
+            Some(("/run/pebble.sock", "/dir"))
+        );
+        assert_eq!(
+            parse_unix_url("unix:///run/pebble.sock"),
+            Some(("/run/pebble.sock", "/directory"))
+        );
+        assert_eq!(parse_unix_url("unix://"), None);
+        assert_eq!(parse_unix_url("https://example.com"), None);
+    }
+
+    #[tokio::test]
+    async fn connector_uses_socket() -> Result<(), Box<dyn std::error::Error>> {
+        let socket = std::env::temp_dir().join(format!("async_acme_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket)?;
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                .await
+                .unwrap();
+        });
+
+        let client = Client::builder().build::<_, Body>(UnixConnector::new(&socket));
+        let res = client.get("http://localhost/directory".parse()?).await?;
+        assert_eq!(body::to_bytes(res.into_body()).await?, "ok");
+
+        std::fs::remove_file(&socket)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use hyper::client::HttpConnector;
+use hyper::http::header::CONTENT_TYPE;
+use hyper::http::Method;
+use hyper::{Body, Client, Request, Uri};
+#[cfg(feature = "rustls-tls")]
+use hyper_rustls::HttpsConnectorBuilder;
+use ring::hmac;
+use serde::Serialize;
+use std::fmt::{self, Debug, Formatter, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use time::OffsetDateTime;
+
+use crate::runtime::{default_runtime, timeout};
+use crate::Runtime;
+
+#[cfg(feature = "rustls-tls")]
+type Connector = hyper_rustls::HttpsConnector<HttpConnector>;
+// the tls stack of the os as rustls-tls is disabled
+#[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+type Connector = hyper_tls::HttpsConnector<HttpConnector>;
+
+const SIGNATURE_HEADER: &str = "x-signature-256";
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error(transparent)]
+    Hyper(#[from] hyper::Error),
+    #[error(transparent)]
+    Http(#[from] hyper::http::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("Webhook {0} timed out")]
+    Timeout(Uri),
+    #[error("Webhook {0} responded with {1}")]
+    Status(Uri, u16),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CertificateEvent {
+    Issued {
+        domains: Vec<String>,
+        not_after: i64,
+    },
+    Renewed {
+        domains: Vec<String>,
+        not_after: i64,
+    },
+    Expiring {
+        domains: Vec<String>,
+        not_after: i64,
+    },
+    Failed {
+        domains: Vec<String>,
+        error: String,
+    },
+    Revoked {
+        domains: Vec<String>,
+    },
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    timestamp: i64,
+    #[serde(flatten)]
+    event: &'a CertificateEvent,
+}
+
+#[derive(Clone)]
+struct Webhook {
+    url: Uri,
+    key: Option<hmac::Key>,
+}
+
+// posts certificate events as json, signed webhooks carry the hex encoded hmac-sha256 of the body
+// as x-signature-256: sha256=...
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    client: Client<Connector>,
+    webhooks: Vec<Webhook>,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl Default for WebhookNotifier {
+    fn default() -> Self {
+        #[cfg(feature = "rustls-tls")]
+        let connector = HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http1()
+            .build();
+        // allows plain http as well
+        #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+        let connector = Connector::new();
+
+        Self {
+            client: Client::builder().build(connector),
+            webhooks: Vec::new(),
+            runtime: default_runtime(),
+        }
+    }
+}
+
+impl WebhookNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn webhook(mut self, url: Uri) -> Self {
+        self.webhooks.push(Webhook { url, key: None });
+        self
+    }
+
+    pub fn signed_webhook<T: AsRef<[u8]>>(mut self, url: Uri, secret: T) -> Self {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_ref());
+        self.webhooks.push(Webhook {
+            url,
+            key: Some(key),
+        });
+        self
+    }
+
+    // runs the request timeout, the hyper client still needs tokio
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.runtime = Arc::new(runtime);
+        self
+    }
+
+    // every webhook is tried, the last error is returned
+    pub async fn notify(&self, event: &CertificateEvent) -> Result<(), WebhookError> {
+        let body = serde_json::to_vec(&Payload {
+            timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            event,
+        })?;
+
+        let mut res = Ok(());
+        for webhook in &self.webhooks {
+            if let Err(err) = self.send(webhook, body.clone()).await {
+                res = Err(err);
+            }
+        }
+
+        res
+    }
+
+    async fn send(&self, webhook: &Webhook, body: Vec<u8>) -> Result<(), WebhookError> {
+        let mut req = Request::builder()
+            .method(Method::POST)
+            .uri(webhook.url.clone())
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(key) = &webhook.key {
+            req = req.header(SIGNATURE_HEADER, signature(key, &body));
+        }
+
+        let res = timeout(
+            &*self.runtime,
+            WEBHOOK_TIMEOUT,
+            self.client.request(req.body(Body::from(body))?),
+        )
+        .await
+        .map_err(|_| WebhookError::Timeout(webhook.url.clone()))??;
+
+        match res.status().is_success() {
+            true => Ok(()),
+            false => Err(WebhookError::Status(
+                webhook.url.clone(),
+                res.status().as_u16(),
+            )),
+        }
+    }
+}
+
+impl Debug for WebhookNotifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let urls: Vec<_> = self.webhooks.iter().map(|webhook| &webhook.url).collect();
+        f.debug_struct("WebhookNotifier")
+            .field("webhooks", &urls)
+            .finish()
+    }
+}
+
+fn signature(key: &hmac::Key, body: &[u8]) -> String {
+    let tag = hmac::sign(key, body);
+    tag.as_ref()
+        .iter()
+        .fold(String::from("sha256="), |mut signature, byte| {
+            let _ = write!(signature, "{:02x}", byte);
+            signature
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload() {
+        let event = CertificateEvent::Failed {
+            domains: vec!["example.com".into()],
+            error: "timeout".into(),
+        };
+        let payload = serde_json::to_value(&Payload {
+            timestamp: 0,
+            event: &event,
+        })
+        .unwrap();
+
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "timestamp": 0,
+                "event": "failed",
+                "domains": ["example.com"],
+                "error": "timeout",
+            })
+        );
+    }
+
+    #[test]
+    fn hmac_signature() {
+        // rfc 4231 test case 2
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"Jefe");
+        assert_eq!(
+            signature(&key, b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -1,8 +1,21 @@
+use acme_core::ApiChallengeType;
+use async_acme::{ChallengeProof, Solver};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use reqwest::{Method, RequestBuilder};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::time::Duration;
 use testcontainers::clients::Cli;
 use testcontainers::core::WaitFor;
 use testcontainers::images::generic::GenericImage;
 use testcontainers::{Container, RunnableImage};
+use tokio::time::sleep;
+
+const TTL: u32 = 60;
+const PROPAGATION_INTERVAL: Duration = Duration::from_secs(1);
+const PROPAGATION_ATTEMPTS: usize = 30;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ApiServer {
@@ -31,6 +44,7 @@ pub struct ApiZone {
     pub type_val: String,
     pub url: String,
     pub kind: ZoneKind,
+    #[serde(default)]
     pub rrsets: Vec<()>,
     #[serde(skip_serializing)]
     pub serial: u32,
@@ -49,7 +63,7 @@ pub struct ApiZone {
     pub account: Option<String>,
     #[serde(default)]
     pub nameservers: Vec<String>,
-    pub master_tsgi_key_ids: Vec<String>,
+    pub master_tsig_key_ids: Vec<String>,
     pub slave_tsig_key_ids: Vec<String>,
 }
 
@@ -73,6 +87,7 @@ pub enum RRSetType {
     A,
     PTR,
     MX,
+    TXT,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -88,8 +103,9 @@ pub struct RRSet {
     #[serde(rename = "type")]
     pub type_val: RRSetType,
     pub ttl: u32,
-    pub changetype: String,
+    pub changetype: RRSetChangeType,
     pub records: Vec<ApiRecord>,
+    #[serde(default)]
     pub comments: Vec<ApiComment>,
 }
 
@@ -130,9 +146,10 @@ pub fn powerdns_container<T: Into<String>>(docker: &Cli, name: T) -> Container<'
 }
 
 #[derive(Clone)]
-struct Client {
+pub struct Client {
     client: reqwest::Client,
     base_url: String,
+    api_key: String,
 }
 
 type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
@@ -142,21 +159,18 @@ impl Client {
         format!("{}{}", self.base_url, path.as_ref())
     }
 
-    async fn get<T, R>(&self, path: T) -> Result<R, Error>
-    where
-        T: AsRef<str>,
-        R: for<'a> Deserialize<'a>,
-    {
-        let res = self
-            .client
-            .get(self.format_url(path))
-            .header("X-API-Key", "root")
-            .send()
-            .await?;
+    fn request<T: AsRef<str>>(&self, method: Method, path: T) -> RequestBuilder {
+        self.client
+            .request(method, self.format_url(path))
+            .header("X-API-Key", &self.api_key)
+    }
+
+    async fn send(&self, req: RequestBuilder) -> Result<reqwest::Response, Error> {
+        let res = req.send().await?;
         let status = res.status();
 
         if status.is_success() {
-            return Ok(res.json().await?);
+            return Ok(res);
         }
 
         let error: ApiError = res.json().await?;
@@ -165,10 +179,30 @@ impl Client {
         Err(error.into())
     }
 
-    pub fn new<T: Into<String>>(base_url: T) -> Self {
+    async fn get<T, R>(&self, path: T) -> Result<R, Error>
+    where
+        T: AsRef<str>,
+        R: for<'a> Deserialize<'a>,
+    {
+        let res = self.send(self.request(Method::GET, path)).await?;
+        Ok(res.json().await?)
+    }
+
+    async fn patch<T, B>(&self, path: T, body: &B) -> Result<(), Error>
+    where
+        T: AsRef<str>,
+        B: Serialize,
+    {
+        self.send(self.request(Method::PATCH, path).json(body))
+            .await?;
+        Ok(())
+    }
+
+    pub fn new<T: Into<String>, K: Into<String>>(base_url: T, api_key: K) -> Self {
         Self {
             client: reqwest::Client::new(),
             base_url: base_url.into(),
+            api_key: api_key.into(),
         }
     }
 
@@ -196,12 +230,152 @@ impl Client {
     }
 }
 
-struct Server<'a> {
+// the api key should not end up in logs
+impl Debug for Client {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("base_url", &self.base_url)
+            .finish()
+    }
+}
+
+pub struct Server<'a> {
     client: &'a Client,
     inner: ApiServer,
 }
 
-impl<'a> Server<'a> {}
+impl<'a> Server<'a> {
+    fn zone_path(&self, zone_id: &str) -> String {
+        format!("/servers/{}/zones/{}", self.inner.id, zone_id)
+    }
+
+    // without rrsets, only the zone metadata is needed for now
+    pub async fn get_zone<T: AsRef<str>>(&self, zone_id: T) -> Result<ApiZone, Error> {
+        let path = format!("{}?rrsets=false", self.zone_path(zone_id.as_ref()));
+        self.client.get(path).await
+    }
+
+    pub async fn patch_rrsets<T: AsRef<str>>(
+        &self,
+        zone_id: T,
+        rrsets: Vec<RRSet>,
+    ) -> Result<(), Error> {
+        #[derive(Serialize)]
+        struct Patch {
+            rrsets: Vec<RRSet>,
+        }
+
+        let path = self.zone_path(zone_id.as_ref());
+        self.client.patch(path, &Patch { rrsets }).await
+    }
+}
+
+// dns-01 solver creating the _acme-challenge TXT rrsets in a zone hosted by powerdns
+#[derive(Debug)]
+pub struct PowerDnsSolver {
+    client: Client,
+    server_id: String,
+    zone_id: String,
+    // a wildcard and its base domain share one rrset
+    records: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl PowerDnsSolver {
+    // zone_id is the canonical zone name including the trailing dot
+    pub fn new<S: Into<String>, Z: Into<String>>(client: Client, server_id: S, zone_id: Z) -> Self {
+        Self {
+            client,
+            server_id: server_id.into(),
+            zone_id: zone_id.into(),
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn update(&self, name: String, values: Vec<String>) -> Result<(), Error> {
+        let changetype = match values.is_empty() {
+            true => RRSetChangeType::DELETE,
+            false => RRSetChangeType::REPLACE,
+        };
+        let records = values
+            .into_iter()
+            .map(|value| ApiRecord {
+                content: format!("\"{}\"", value),
+                disabled: false,
+            })
+            .collect();
+        let rrset = RRSet {
+            name,
+            type_val: RRSetType::TXT,
+            ttl: TTL,
+            changetype,
+            records,
+            comments: Vec::new(),
+        };
+
+        let server = self.client.get_server(&self.server_id).await?;
+        server.patch_rrsets(&self.zone_id, vec![rrset]).await
+    }
+
+    // native zones are replicated by the backend, master zones have to notify their slaves
+    async fn wait_for_propagation(&self) -> Result<(), Error> {
+        let server = self.client.get_server(&self.server_id).await?;
+        for _ in 0..PROPAGATION_ATTEMPTS {
+            let zone = server.get_zone(&self.zone_id).await?;
+            match zone.kind {
+                ZoneKind::Master if zone.notified_serial < zone.serial => {
+                    sleep(PROPAGATION_INTERVAL).await
+                }
+                _ => return Ok(()),
+            }
+        }
+
+        Err(format!("serial of {} did not propagate", self.zone_id).into())
+    }
+}
+
+fn fqdn(name: String) -> String {
+    match name.ends_with('.') {
+        true => name,
+        false => format!("{}.", name),
+    }
+}
+
+#[async_trait]
+impl Solver for PowerDnsSolver {
+    fn challenge_type(&self) -> ApiChallengeType {
+        ApiChallengeType::DNS
+    }
+
+    async fn present(&self, proof: &ChallengeProof) -> Result<(), Error> {
+        let name = fqdn(proof.dns_name());
+        let values = {
+            let mut records = self.records.lock();
+            let values = records.entry(name.clone()).or_default();
+            values.push(proof.dns_value());
+            values.clone()
+        };
+
+        self.update(name, values).await?;
+        self.wait_for_propagation().await
+    }
+
+    async fn cleanup(&self, proof: &ChallengeProof) -> Result<(), Error> {
+        let name = fqdn(proof.dns_name());
+        let value = proof.dns_value();
+        let values = {
+            let mut records = self.records.lock();
+            let values = records.entry(name.clone()).or_default();
+            values.retain(|v| v != &value);
+            let values = values.clone();
+            if values.is_empty() {
+                records.remove(&name);
+            }
+            values
+        };
+
+        self.update(name, values).await
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -218,7 +392,7 @@ mod tests {
         let powerdns = powerdns_container(&docker, "powerdns");
         let powerdns_port = powerdns.get_host_port_ipv4(8081);
 
-        let client = Client::new(format!("http://localhost:{}/api/v1", powerdns_port));
+        let client = Client::new(format!("http://localhost:{}/api/v1", powerdns_port), "root");
         let servers = client.get_servers().await?;
         assert_eq!(servers.len(), 1);
 
@@ -226,4 +400,50 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn solver() -> Result<(), Error> {
+        let docker = Cli::default();
+
+        let _mysql = MySQL::run(&docker, "powerdns");
+
+        let powerdns = powerdns_container(&docker, "powerdns");
+        let powerdns_port = powerdns.get_host_port_ipv4(8081);
+
+        let base_url = format!("http://localhost:{}/api/v1", powerdns_port);
+        let client = Client::new(&base_url, "root");
+        client
+            .send(
+                client
+                    .request(Method::POST, "/servers/localhost/zones")
+                    .json(&serde_json::json!({
+                        "name": "example.com.",
+                        "kind": "Native",
+                        "nameservers": ["ns1.example.com."],
+                    })),
+            )
+            .await?;
+
+        let solver = PowerDnsSolver::new(client.clone(), "localhost", "example.com.");
+        let proof = ChallengeProof {
+            domain: "*.example.com".into(),
+            token: "token".into(),
+            key_authorization: "token.thumbprint".into(),
+        };
+        let txt = |zone: serde_json::Value| {
+            zone["rrsets"].as_array().unwrap().iter().any(|rrset| {
+                rrset["name"] == "_acme-challenge.example.com." && rrset["type"] == "TXT"
+            })
+        };
+
+        solver.present(&proof).await?;
+        let zone = client.get("/servers/localhost/zones/example.com.").await?;
+        assert!(txt(zone));
+
+        solver.cleanup(&proof).await?;
+        let zone = client.get("/servers/localhost/zones/example.com.").await?;
+        assert!(!txt(zone));
+
+        Ok(())
+    }
 }